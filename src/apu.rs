@@ -0,0 +1,495 @@
+mod envelope;
+mod length;
+mod noise;
+mod recorder;
+mod resampler;
+mod sample_buffer;
+mod square;
+mod wav;
+mod wave;
+
+pub use sample_buffer::SampleBuffer;
+pub use wav::WavWriter;
+
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::CLOCK_SPEED;
+use crate::memory::address_space::AddressSpace;
+use noise::Noise;
+use recorder::Recorder;
+use resampler::Resampler;
+use square::Square;
+use wave::{Wave, WAVE_RAM_SIZE};
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+// FF10-FF3F: Sound registers and wave pattern RAM
+const APU_START: u16 = 0xFF10;
+const APU_END: u16 = 0xFF3F;
+
+pub const NR10_ADDR: u16 = 0xFF10;
+pub const NR14_ADDR: u16 = 0xFF14;
+pub const NR21_ADDR: u16 = 0xFF16;
+pub const NR24_ADDR: u16 = 0xFF19;
+pub const NR30_ADDR: u16 = 0xFF1A;
+pub const NR34_ADDR: u16 = 0xFF1E;
+pub const NR41_ADDR: u16 = 0xFF20;
+pub const NR44_ADDR: u16 = 0xFF23;
+pub const NR50_ADDR: u16 = 0xFF24;
+pub const NR51_ADDR: u16 = 0xFF25;
+pub const NR52_ADDR: u16 = 0xFF26;
+
+const WAVE_RAM_START: u16 = 0xFF30;
+const WAVE_RAM_END: u16 = 0xFF3F;
+
+const POWER_ON: u8 = 0b1000_0000;
+
+// The frame sequencer runs at 512 Hz.
+const FRAME_SEQUENCER_CYCLES: u32 = CLOCK_SPEED / 512;
+
+// About a quarter of a second of samples at the default rate.
+const SAMPLE_BUFFER_SIZE: usize = 12_000;
+
+// Samples are moved to the shared buffer on every tick, so only a few are
+// ever ready at once.
+const READY_BUFFER_SIZE: usize = 16;
+
+/// Audio Processing Unit
+///
+/// Mixes two square channels, a wave channel and a noise channel into a
+/// stereo signal, which is resampled into a buffer for frontends to drain.
+pub struct Apu {
+    square1: Square,
+    square2: Square,
+    wave: Wave,
+    noise: Noise,
+    nr50: u8,
+    nr51: u8,
+    powered: bool,
+    // T-cycles until the next frame sequencer step.
+    sequencer_timer: u32,
+    sequencer_step: u8,
+    sample_rate: u32,
+    resampler: Resampler,
+    // Samples produced by the resampler this tick, gathered locally so the
+    // shared buffer is only locked when there are some to move into it.
+    ready: SampleBuffer,
+    ready_samples: Vec<[f32; 2]>,
+    // Shared so that an audio callback on another thread can drain it.
+    samples: Arc<Mutex<SampleBuffer>>,
+    recorder: Option<Recorder>,
+}
+
+impl Apu {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            square1: Square::new(true),
+            square2: Square::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            nr50: 0x00,
+            nr51: 0x00,
+            powered: false,
+            sequencer_timer: FRAME_SEQUENCER_CYCLES,
+            sequencer_step: 0,
+            sample_rate,
+            resampler: Resampler::new(CLOCK_SPEED, sample_rate),
+            ready: SampleBuffer::new(READY_BUFFER_SIZE),
+            ready_samples: Vec::with_capacity(READY_BUFFER_SIZE),
+            samples: Arc::new(Mutex::new(SampleBuffer::new(SAMPLE_BUFFER_SIZE))),
+            recorder: None,
+        }
+    }
+
+    /// Moves the samples produced so far onto the end of `output`.
+    pub fn drain_samples(&mut self, output: &mut Vec<[f32; 2]>) {
+        self.samples.lock().unwrap().drain_into(output);
+    }
+
+    /// Buffer the output is resampled into, for draining from another thread.
+    pub fn sample_buffer(&self) -> Arc<Mutex<SampleBuffer>> {
+        Arc::clone(&self.samples)
+    }
+
+    /// Length of the output waiting to be drained.
+    pub fn buffered(&self) -> Duration {
+        let samples = self.samples.lock().unwrap().len();
+        Duration::from_secs_f64(samples as f64 / self.sample_rate as f64)
+    }
+
+    /// Starts writing the output to a WAV file at `path`, and each channel to
+    /// its own file alongside it if `split_channels` is set. Any recording
+    /// already in progress is stopped first.
+    pub fn start_recording(&mut self, path: &Path, split_channels: bool) -> io::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(Recorder::create(path, self.sample_rate, split_channels)?);
+        Ok(())
+    }
+
+    /// Completes the WAV files being recorded, if any.
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes the files being recorded playable up to this point, in case
+    /// the recording is never stopped.
+    pub fn update_recording(&mut self) -> io::Result<()> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.update_headers(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    fn power_off(&mut self) {
+        // Every register is cleared, but wave RAM is left intact.
+        let ram: Vec<u8> = (0..WAVE_RAM_SIZE).map(|i| self.wave.read_ram(i)).collect();
+        self.square1 = Square::new(true);
+        self.square2 = Square::new(false);
+        self.wave = Wave::new();
+        self.noise = Noise::new();
+        for (i, byte) in ram.into_iter().enumerate() {
+            self.wave.write_ram(i, byte);
+        }
+        self.nr50 = 0x00;
+        self.nr51 = 0x00;
+        self.powered = false;
+    }
+
+    fn power_on(&mut self) {
+        self.powered = true;
+        self.sequencer_timer = FRAME_SEQUENCER_CYCLES;
+        self.sequencer_step = 0;
+    }
+
+    fn read_nr52(&self) -> u8 {
+        let mut value = 0x70;
+        if self.powered {
+            value |= POWER_ON;
+        }
+        let channels = [
+            self.square1.enabled(),
+            self.square2.enabled(),
+            self.wave.enabled(),
+            self.noise.enabled(),
+        ];
+        for (bit, enabled) in channels.into_iter().enumerate() {
+            if enabled {
+                value |= 1 << bit;
+            }
+        }
+        value
+    }
+
+    /// Steps the frame sequencer, which clocks the length timers at 256 Hz,
+    /// the sweep at 128 Hz and the envelopes at 64 Hz.
+    fn step_frame_sequencer(&mut self) {
+        if self.sequencer_step.is_multiple_of(2) {
+            self.square1.clock_length();
+            self.square2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if self.sequencer_step % 4 == 2 {
+            self.square1.clock_sweep();
+        }
+        if self.sequencer_step == 7 {
+            self.square1.clock_envelope();
+            self.square2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+        self.sequencer_step = (self.sequencer_step + 1) % 8;
+    }
+
+    /// Each channel's contribution to the stereo output, after panning and
+    /// master volume.
+    fn channel_levels(&self) -> [[f32; 2]; 4] {
+        let channels = [
+            (self.square1.dac_enabled(), self.square1.output()),
+            (self.square2.dac_enabled(), self.square2.output()),
+            (self.wave.dac_enabled(), self.wave.output()),
+            (self.noise.dac_enabled(), self.noise.output()),
+        ];
+        let left_volume = ((self.nr50 >> 4) & 0b111) as f32 + 1.0;
+        let right_volume = (self.nr50 & 0b111) as f32 + 1.0;
+        let mut levels = [[0.0; 2]; 4];
+        for (channel, (dac_enabled, output)) in channels.into_iter().enumerate() {
+            if !dac_enabled {
+                continue;
+            }
+            // Each DAC maps 0-15 onto an analog level from 1 to -1.
+            let level = 1.0 - output as f32 / 7.5;
+            if self.nr51 & (0x10 << channel) != 0 {
+                levels[channel][0] = level / 4.0 * left_volume / 8.0;
+            }
+            if self.nr51 & (0x01 << channel) != 0 {
+                levels[channel][1] = level / 4.0 * right_volume / 8.0;
+            }
+        }
+        levels
+    }
+
+    /// Stereo output level, from -1.0 to 1.0 on each side.
+    fn mix(levels: &[[f32; 2]; 4]) -> [f32; 2] {
+        levels.iter().fold([0.0; 2], |mix, level| {
+            [mix[0] + level[0], mix[1] + level[1]]
+        })
+    }
+}
+
+impl AddressSpace for Apu {
+    fn accepts(&self, addr: u16) -> bool {
+        (APU_START..=APU_END).contains(&addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        match addr {
+            WAVE_RAM_START..=WAVE_RAM_END => {
+                self.wave.write_ram((addr - WAVE_RAM_START) as usize, byte);
+            }
+            NR52_ADDR => match (self.powered, byte & POWER_ON != 0) {
+                (true, false) => self.power_off(),
+                (false, true) => self.power_on(),
+                _ => {}
+            },
+            // Other registers are read only while powered off.
+            _ if !self.powered => {}
+            NR10_ADDR..=NR14_ADDR => self.square1.write((addr - NR10_ADDR) as usize, byte),
+            NR21_ADDR..=NR24_ADDR => self.square2.write((addr - NR21_ADDR + 1) as usize, byte),
+            NR30_ADDR..=NR34_ADDR => self.wave.write((addr - NR30_ADDR) as usize, byte),
+            NR41_ADDR..=NR44_ADDR => self.noise.write((addr - NR41_ADDR + 1) as usize, byte),
+            NR50_ADDR => self.nr50 = byte,
+            NR51_ADDR => self.nr51 = byte,
+            _ => {}
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        match addr {
+            NR10_ADDR..=NR14_ADDR => self.square1.read((addr - NR10_ADDR) as usize),
+            NR21_ADDR..=NR24_ADDR => self.square2.read((addr - NR21_ADDR + 1) as usize),
+            NR30_ADDR..=NR34_ADDR => self.wave.read((addr - NR30_ADDR) as usize),
+            NR41_ADDR..=NR44_ADDR => self.noise.read((addr - NR41_ADDR + 1) as usize),
+            NR50_ADDR => self.nr50,
+            NR51_ADDR => self.nr51,
+            NR52_ADDR => self.read_nr52(),
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave.read_ram((addr - WAVE_RAM_START) as usize),
+            // Unmapped registers between the channels.
+            _ => 0xFF,
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        if self.powered {
+            self.sequencer_timer -= (cycles as u32).min(self.sequencer_timer);
+            if self.sequencer_timer == 0 {
+                self.sequencer_timer = FRAME_SEQUENCER_CYCLES;
+                self.step_frame_sequencer();
+            }
+            self.square1.tick(cycles);
+            self.square2.tick(cycles);
+            self.wave.tick(cycles);
+            self.noise.tick(cycles);
+        }
+        let levels = self.channel_levels();
+        self.resampler.set_level(Self::mix(&levels));
+        self.resampler.advance(cycles as u32, &mut self.ready);
+        if !self.ready.is_empty() {
+            self.ready.drain_into(&mut self.ready_samples);
+            let mut samples = self.samples.lock().unwrap();
+            for sample in self.ready_samples.drain(..) {
+                samples.push(sample);
+            }
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(levels, cycles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn powered() -> Apu {
+        let mut apu = Apu::new(DEFAULT_SAMPLE_RATE);
+        apu.set_byte(NR52_ADDR, 0x80);
+        apu
+    }
+
+    fn tick(apu: &mut Apu, cycles: u32) {
+        for _ in 0..cycles / 4 {
+            apu.tick(4);
+        }
+    }
+
+    #[test]
+    fn accepts_sound_registers_and_wave_ram() {
+        let apu = powered();
+        assert!(!apu.accepts(0xFF0F));
+        assert!(apu.accepts(NR10_ADDR));
+        assert!(apu.accepts(NR52_ADDR));
+        assert!(apu.accepts(0xFF3F));
+        assert!(!apu.accepts(0xFF40));
+    }
+
+    #[test]
+    fn unmapped_registers_read_ff() {
+        let mut apu = powered();
+        for addr in [0xFF15, 0xFF1F, 0xFF27, 0xFF2F] {
+            apu.set_byte(addr, 0x00);
+            assert_eq!(apu.get_byte(addr), 0xFF, "{addr:#06X}");
+        }
+    }
+
+    #[test]
+    fn nr52_reports_power_and_channel_status() {
+        let mut apu = Apu::new(DEFAULT_SAMPLE_RATE);
+        assert_eq!(apu.get_byte(NR52_ADDR), 0x70);
+        apu.set_byte(NR52_ADDR, 0x8F);
+        assert_eq!(apu.get_byte(NR52_ADDR), 0xF0, "Channel bits are read only");
+
+        apu.set_byte(0xFF17, 0xF0);
+        apu.set_byte(NR24_ADDR, 0x80);
+        assert_eq!(apu.get_byte(NR52_ADDR), 0xF2);
+    }
+
+    #[test]
+    fn power_off_clears_registers_but_not_wave_ram() {
+        let mut apu = powered();
+        apu.set_byte(NR50_ADDR, 0x77);
+        apu.set_byte(0xFF12, 0xF0);
+        apu.set_byte(NR14_ADDR, 0x80);
+        apu.set_byte(WAVE_RAM_START, 0xAB);
+
+        apu.set_byte(NR52_ADDR, 0x00);
+        assert_eq!(apu.get_byte(NR50_ADDR), 0x00);
+        assert_eq!(apu.get_byte(0xFF12), 0x00);
+        assert_eq!(apu.get_byte(NR52_ADDR), 0x70);
+        assert_eq!(apu.get_byte(WAVE_RAM_START), 0xAB);
+
+        apu.set_byte(NR50_ADDR, 0x77);
+        assert_eq!(apu.get_byte(NR50_ADDR), 0x00, "Read only while off");
+        apu.set_byte(WAVE_RAM_START, 0xCD);
+        assert_eq!(apu.get_byte(WAVE_RAM_START), 0xCD);
+    }
+
+    #[test]
+    fn frame_sequencer_clocks_length_at_256_hz() {
+        let mut apu = powered();
+        apu.set_byte(0xFF17, 0xF0);
+        // Length of 2, with the length timer enabled.
+        apu.set_byte(0xFF16, 0x3E);
+        apu.set_byte(NR24_ADDR, 0xC0);
+
+        // Steps 0 and 2 clock the length timer.
+        tick(&mut apu, 2 * FRAME_SEQUENCER_CYCLES);
+        assert_eq!(apu.get_byte(NR52_ADDR) & 0b10, 0b10);
+        tick(&mut apu, FRAME_SEQUENCER_CYCLES);
+        assert_eq!(apu.get_byte(NR52_ADDR) & 0b10, 0);
+    }
+
+    #[test]
+    fn mixes_panned_channels() {
+        let mut apu = powered();
+        apu.set_byte(NR50_ADDR, 0x70);
+        // Channel 2 on the left only, DAC on but silent.
+        apu.set_byte(NR51_ADDR, 0x20);
+        apu.set_byte(0xFF17, 0x08);
+        apu.set_byte(NR24_ADDR, 0x80);
+        assert_eq!(Apu::mix(&apu.channel_levels()), [0.25, 0.0]);
+    }
+
+    #[test]
+    fn produces_samples() {
+        let mut apu = powered();
+        apu.set_byte(NR50_ADDR, 0x77);
+        apu.set_byte(NR51_ADDR, 0xFF);
+        apu.set_byte(0xFF11, 0x80);
+        apu.set_byte(0xFF12, 0xF0);
+        apu.set_byte(0xFF13, 0x00);
+        apu.set_byte(NR14_ADDR, 0x87);
+        tick(&mut apu, CLOCK_SPEED / 10);
+
+        let mut samples = Vec::new();
+        apu.drain_samples(&mut samples);
+        assert!(samples.len() > 4_700 && samples.len() <= 4_800);
+        assert!(samples.iter().any(|sample| sample[0] > 0.1));
+        assert!(samples.iter().any(|sample| sample[0] < -0.1));
+    }
+
+    #[test]
+    fn sample_buffer_is_shared() {
+        let mut apu = powered();
+        let buffer = apu.sample_buffer();
+        tick(&mut apu, CLOCK_SPEED / 10);
+        let buffered = apu.buffered();
+
+        let mut samples = Vec::new();
+        buffer.lock().unwrap().drain_into(&mut samples);
+        assert!(!samples.is_empty());
+        assert_eq!(
+            buffered,
+            Duration::from_secs_f64(samples.len() as f64 / DEFAULT_SAMPLE_RATE as f64)
+        );
+        assert_eq!(apu.buffered(), Duration::ZERO);
+    }
+
+    #[test]
+    fn records_mix_and_channels_to_wav_files() {
+        let path = std::env::temp_dir().join(format!("rustboy-{}-apu.wav", std::process::id()));
+        let mut apu = powered();
+        apu.start_recording(&path, true).unwrap();
+        assert!(apu.is_recording());
+        apu.set_byte(NR50_ADDR, 0x77);
+        apu.set_byte(NR51_ADDR, 0x22);
+        apu.set_byte(0xFF17, 0xF0);
+        apu.set_byte(NR24_ADDR, 0x87);
+        tick(&mut apu, CLOCK_SPEED / 10);
+        apu.stop_recording().unwrap();
+        assert!(!apu.is_recording());
+
+        let wav = std::fs::read(&path).unwrap();
+        let data_size = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize;
+        assert_eq!(wav.len(), 44 + data_size);
+        assert!(data_size / 4 > 4_700 && data_size / 4 <= 4_800);
+
+        let paths = recorder::channel_paths(&path);
+        let square2 = std::fs::read(&paths[1]).unwrap();
+        let square1 = std::fs::read(&paths[0]).unwrap();
+        assert_eq!(square2, wav, "Channel 2 is the only one playing");
+        assert!(square1[44..].iter().all(|&byte| byte == 0));
+        std::fs::remove_file(&path).unwrap();
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn updates_recording_before_it_stops() {
+        let path =
+            std::env::temp_dir().join(format!("rustboy-{}-apu-update.wav", std::process::id()));
+        let mut apu = powered();
+        apu.start_recording(&path, false).unwrap();
+        tick(&mut apu, CLOCK_SPEED / 10);
+        apu.update_recording().unwrap();
+
+        let wav = std::fs::read(&path).unwrap();
+        let data_size = u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize;
+        assert!(data_size > 0);
+        assert_eq!(wav.len(), 44 + data_size);
+        apu.stop_recording().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+/// Volume envelope shared by the square and noise channels, set by NRx2.
+#[derive(Default)]
+pub struct Envelope {
+    register: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn read(&self) -> u8 {
+        self.register
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        self.register = byte;
+    }
+
+    /// The DAC is powered whenever the initial volume or direction is set.
+    pub fn dac_enabled(&self) -> bool {
+        self.register & 0xF8 != 0
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn initial_volume(&self) -> u8 {
+        self.register >> 4
+    }
+
+    fn increases(&self) -> bool {
+        self.register & 0b1000 != 0
+    }
+
+    fn pace(&self) -> u8 {
+        self.register & 0b111
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume();
+        self.timer = self.pace();
+    }
+
+    /// Clocked at 64 Hz by the frame sequencer.
+    pub fn clock(&mut self) {
+        if self.pace() == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.pace();
+        if self.increases() && self.volume < 15 {
+            self.volume += 1;
+        } else if !self.increases() && self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn triggered(register: u8) -> Envelope {
+        let mut envelope = Envelope::default();
+        envelope.write(register);
+        envelope.trigger();
+        envelope
+    }
+
+    #[test]
+    fn dac_enabled_by_volume_or_direction() {
+        assert!(!triggered(0x07).dac_enabled());
+        assert!(triggered(0x08).dac_enabled());
+        assert!(triggered(0x10).dac_enabled());
+    }
+
+    #[test]
+    fn decreases_every_pace_clocks() {
+        let mut envelope = triggered(0xF2);
+        assert_eq!(envelope.volume(), 15);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 15);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 14);
+    }
+
+    #[test]
+    fn stops_at_limits() {
+        let mut envelope = triggered(0xF9);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 15);
+
+        let mut envelope = triggered(0x01);
+        envelope.clock();
+        assert_eq!(envelope.volume(), 0);
+    }
+
+    #[test]
+    fn pace_of_zero_disables_envelope() {
+        let mut envelope = triggered(0x80);
+        for _ in 0..16 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.volume(), 8);
+    }
+}
@@ -0,0 +1,78 @@
+/// Length timer, which silences a channel after a set time when enabled.
+pub struct LengthCounter {
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    /// Loads the initial length timer value written to NRx1.
+    pub fn load(&mut self, value: u8) {
+        self.counter = self.max - value as u16;
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn trigger(&mut self) {
+        if self.counter == 0 {
+            self.counter = self.max;
+        }
+    }
+
+    /// Clocked at 256 Hz by the frame sequencer. Returns true when the timer
+    /// expires and the channel should be disabled.
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expires_after_remaining_length() {
+        let mut length = LengthCounter::new(64);
+        length.load(60);
+        length.set_enabled(true);
+        for _ in 0..3 {
+            assert!(!length.clock());
+        }
+        assert!(length.clock());
+        assert!(!length.clock(), "Only expires once");
+    }
+
+    #[test]
+    fn does_not_count_when_disabled() {
+        let mut length = LengthCounter::new(64);
+        length.load(63);
+        assert!(!length.clock());
+        length.set_enabled(true);
+        assert!(length.clock());
+    }
+
+    #[test]
+    fn trigger_reloads_expired_timer() {
+        let mut length = LengthCounter::new(256);
+        length.set_enabled(true);
+        length.trigger();
+        for _ in 0..255 {
+            assert!(!length.clock());
+        }
+        assert!(length.clock());
+    }
+}
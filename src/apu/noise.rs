@@ -0,0 +1,197 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+// Bits of NR41-NR44 which always read back as 1. NR40 doesn't exist.
+const READ_MASKS: [u8; 5] = [0xFF, 0xFF, 0x00, 0x00, 0xBF];
+
+/// Noise channel (channel 4), driven by a linear feedback shift register.
+pub struct Noise {
+    registers: [u8; 5],
+    length: LengthCounter,
+    envelope: Envelope,
+    lfsr: u16,
+    // T-cycles until the LFSR is next clocked.
+    timer: i32,
+    enabled: bool,
+}
+
+impl Noise {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 5],
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            lfsr: 0,
+            timer: 0,
+            enabled: false,
+        }
+    }
+
+    fn clock_shift(&self) -> u8 {
+        self.registers[3] >> 4
+    }
+
+    fn short_mode(&self) -> bool {
+        self.registers[3] & 0b1000 != 0
+    }
+
+    fn period(&self) -> i32 {
+        let divisor = match self.registers[3] & 0b111 {
+            0 => 8,
+            divider => divider as i32 * 16,
+        };
+        divisor << self.clock_shift()
+    }
+
+    /// Reads NR40-NR44, given as 0-4.
+    pub fn read(&self, register: usize) -> u8 {
+        let value = match register {
+            2 => self.envelope.read(),
+            _ => self.registers[register],
+        };
+        value | READ_MASKS[register]
+    }
+
+    /// Writes NR40-NR44, given as 0-4.
+    pub fn write(&mut self, register: usize, byte: u8) {
+        self.registers[register] = byte;
+        match register {
+            1 => self.length.load(byte & 0x3F),
+            2 => {
+                self.envelope.write(byte);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            4 => {
+                self.length.set_enabled(byte & 0x40 != 0);
+                if byte & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+        self.timer = self.period();
+    }
+
+    fn clock_lfsr(&mut self) {
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+        if self.short_mode() {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    /// Current output level, from 0 to 15.
+    pub fn output(&self) -> u8 {
+        if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume()
+        } else {
+            0
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if !self.enabled {
+            return;
+        }
+        self.timer -= cycles as i32;
+        while self.timer <= 0 {
+            self.timer += self.period();
+            // Clock shifts of 14 and 15 stop the LFSR.
+            if self.clock_shift() < 14 {
+                self.clock_lfsr();
+            }
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn playing(nr43: u8) -> Noise {
+        let mut noise = Noise::new();
+        noise.write(2, 0xF0);
+        noise.write(3, nr43);
+        noise.write(4, 0x80);
+        noise
+    }
+
+    fn sequence(noise: &mut Noise, steps: usize) -> Vec<u8> {
+        let period = noise.period() as u8;
+        (0..steps)
+            .map(|_| {
+                noise.tick(period);
+                noise.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lfsr_starts_silent_then_outputs_noise() {
+        let mut noise = playing(0x00);
+        assert_eq!(noise.output(), 0, "LFSR starts with all bits set");
+        // The first zero is shifted down after 15 clocks.
+        let levels = sequence(&mut noise, 15);
+        assert_eq!(levels[..14], [0; 14]);
+        assert_eq!(levels[14], 15);
+    }
+
+    #[test]
+    fn short_mode_repeats_every_127_clocks() {
+        let mut noise = playing(0x08);
+        let first = sequence(&mut noise, 127);
+        let second = sequence(&mut noise, 127);
+        assert_eq!(first, second);
+        assert_ne!(first[..63], first[64..127]);
+    }
+
+    #[test]
+    fn large_clock_shift_stops_lfsr() {
+        // Period of 8 << 14 T-cycles.
+        let mut noise = playing(0xE0);
+        for _ in 0..2048 {
+            noise.tick(255);
+        }
+        assert_eq!(noise.lfsr, 0x7FFF);
+    }
+
+    #[test]
+    fn unused_bits_read_as_set() {
+        let noise = Noise::new();
+        let values: Vec<u8> = (0..5).map(|register| noise.read(register)).collect();
+        assert_eq!(values, READ_MASKS);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use super::resampler::Resampler;
+use super::sample_buffer::SampleBuffer;
+use super::wav::WavWriter;
+use super::Apu;
+use crate::clock::CLOCK_SPEED;
+
+// Samples are written out on every tick, so only a few are ever buffered.
+const TRACK_BUFFER_SIZE: usize = 16;
+
+/// One resampled signal being written to a WAV file.
+struct Track {
+    resampler: Resampler,
+    buffer: SampleBuffer,
+    samples: Vec<[f32; 2]>,
+    wav: WavWriter<BufWriter<File>>,
+}
+
+impl Track {
+    fn create(path: &Path, sample_rate: u32) -> io::Result<Self> {
+        Ok(Self {
+            resampler: Resampler::new(CLOCK_SPEED, sample_rate),
+            buffer: SampleBuffer::new(TRACK_BUFFER_SIZE),
+            samples: Vec::with_capacity(TRACK_BUFFER_SIZE),
+            wav: WavWriter::create(path, sample_rate)?,
+        })
+    }
+
+    fn record(&mut self, level: [f32; 2], cycles: u8) -> io::Result<()> {
+        self.resampler.set_level(level);
+        self.resampler.advance(cycles as u32, &mut self.buffer);
+        self.buffer.drain_into(&mut self.samples);
+        for sample in self.samples.drain(..) {
+            self.wav.write_sample(sample)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the APU's mixed output, and optionally each channel on its own,
+/// to WAV files.
+///
+/// Write errors can't be reported while the APU is ticking, so the first
+/// one stops the recording and is returned by `finish`.
+pub struct Recorder {
+    mixed: Track,
+    channels: Option<[Track; 4]>,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    /// Records the mix to `path`. With `split_channels`, each channel is also
+    /// recorded next to it, e.g. to `song.ch1.wav` through `song.ch4.wav`.
+    pub fn create(path: &Path, sample_rate: u32, split_channels: bool) -> io::Result<Self> {
+        let channels = if split_channels {
+            let [ch1, ch2, ch3, ch4] = channel_paths(path);
+            Some([
+                Track::create(&ch1, sample_rate)?,
+                Track::create(&ch2, sample_rate)?,
+                Track::create(&ch3, sample_rate)?,
+                Track::create(&ch4, sample_rate)?,
+            ])
+        } else {
+            None
+        };
+        Ok(Self {
+            mixed: Track::create(path, sample_rate)?,
+            channels,
+            error: None,
+        })
+    }
+
+    /// Records `cycles` T-cycles of the given per-channel stereo levels.
+    pub fn record(&mut self, levels: [[f32; 2]; 4], cycles: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = self.record_tracks(levels, cycles) {
+            log::error!("Stopped audio recording: {}", error);
+            self.error = Some(error);
+        }
+    }
+
+    fn record_tracks(&mut self, levels: [[f32; 2]; 4], cycles: u8) -> io::Result<()> {
+        self.mixed.record(Apu::mix(&levels), cycles)?;
+        if let Some(channels) = &mut self.channels {
+            for (track, level) in channels.iter_mut().zip(levels) {
+                track.record(level, cycles)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in the WAV headers for the samples recorded so far.
+    pub fn update_headers(&mut self) -> io::Result<()> {
+        if self.error.is_some() {
+            return Ok(());
+        }
+        self.mixed.wav.update_header()?;
+        for track in self.channels.iter_mut().flatten() {
+            track.wav.update_header()?;
+        }
+        Ok(())
+    }
+
+    /// Completes the WAV files, returning the first error hit while recording.
+    pub fn finish(self) -> io::Result<()> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.mixed.wav.finish()?;
+        for track in self.channels.into_iter().flatten() {
+            track.wav.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Paths of the per-channel recordings, alongside the mixed one.
+pub(super) fn channel_paths(path: &Path) -> [PathBuf; 4] {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "wav".to_string());
+    [1, 2, 3, 4].map(|channel| path.with_extension(format!("ch{channel}.{extension}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn channel_paths_sit_next_to_mix() {
+        let [ch1, _, _, ch4] = channel_paths(Path::new("out/song.wav"));
+        assert_eq!(ch1, Path::new("out/song.ch1.wav"));
+        assert_eq!(ch4, Path::new("out/song.ch4.wav"));
+        let [ch1, ..] = channel_paths(Path::new("song"));
+        assert_eq!(ch1, Path::new("song.ch1.wav"));
+    }
+}
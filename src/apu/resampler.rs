@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use super::sample_buffer::SampleBuffer;
+
+// Half the width of the band-limited impulse, in output samples.
+const HALF_WIDTH: usize = 8;
+const WIDTH: usize = 2 * HALF_WIDTH;
+
+// Fractional sample positions the impulse is precomputed for.
+const PHASES: usize = 64;
+
+// Cutoff as a fraction of the output sample rate, a little below Nyquist to
+// leave room for the window's transition band.
+const CUTOFF: f64 = 0.45;
+
+// Charge factor per T-cycle of the capacitor between the DACs and the
+// amplifier, which removes any DC offset from the output.
+const HIGH_PASS_CHARGE: f64 = 0.999958;
+
+/// Band-limited resampler from the APU clock to the output sample rate.
+///
+/// Sampling the mixed output directly would alias the square and noise
+/// channels' harmonics. Instead, each change in level is added as a
+/// windowed-sinc impulse, and the impulses are integrated into band-limited
+/// steps as samples are produced.
+pub struct Resampler {
+    // Impulse taps for each phase, normalised to sum to 1.
+    kernel: Vec<[f32; WIDTH]>,
+    samples_per_cycle: f64,
+    // Input cycles since the resampler was created.
+    cycles: u64,
+    // Number of output samples produced so far.
+    produced: u64,
+    // Impulses added to the samples from `produced` onwards.
+    pending: VecDeque<[f32; 2]>,
+    integrator: [f32; 2],
+    level: [f32; 2],
+    charge: f32,
+    capacitor: [f32; 2],
+}
+
+impl Resampler {
+    pub fn new(clock_rate: u32, sample_rate: u32) -> Self {
+        let cycles_per_sample = clock_rate as f64 / sample_rate as f64;
+        Self {
+            kernel: (0..=PHASES).map(impulse).collect(),
+            samples_per_cycle: sample_rate as f64 / clock_rate as f64,
+            cycles: 0,
+            produced: 0,
+            pending: VecDeque::new(),
+            integrator: [0.0; 2],
+            level: [0.0; 2],
+            charge: HIGH_PASS_CHARGE.powf(cycles_per_sample) as f32,
+            capacitor: [0.0; 2],
+        }
+    }
+
+    fn position(&self) -> f64 {
+        self.cycles as f64 * self.samples_per_cycle
+    }
+
+    /// Sets the stereo input level from the current time onwards.
+    pub fn set_level(&mut self, level: [f32; 2]) {
+        let delta = [level[0] - self.level[0], level[1] - self.level[1]];
+        if delta == [0.0; 2] {
+            return;
+        }
+        self.level = level;
+
+        let position = self.position();
+        let sample = position.floor();
+        let phase = ((position - sample) * PHASES as f64).round() as usize;
+        // Output sample the first tap contributes to, relative to the next
+        // one to be produced. Taps before the first sample are dropped.
+        let first = sample as i64 + 1 - HALF_WIDTH as i64 - self.produced as i64;
+        let end = (first + WIDTH as i64) as usize;
+        if self.pending.len() < end {
+            self.pending.resize(end, [0.0; 2]);
+        }
+        for (tap, weight) in self.kernel[phase].iter().enumerate() {
+            let Ok(index) = usize::try_from(first + tap as i64) else {
+                continue;
+            };
+            let pending = &mut self.pending[index];
+            pending[0] += delta[0] * weight;
+            pending[1] += delta[1] * weight;
+        }
+    }
+
+    /// Advances the input clock, producing every output sample which can no
+    /// longer be affected by later changes in level.
+    pub fn advance(&mut self, cycles: u32, output: &mut SampleBuffer) {
+        self.cycles += cycles as u64;
+        let ready = (self.position().floor() as u64 + 1).saturating_sub(HALF_WIDTH as u64);
+        while self.produced < ready {
+            let impulse = self.pending.pop_front().unwrap_or_default();
+            let mut sample = [0.0; 2];
+            for channel in 0..2 {
+                self.integrator[channel] += impulse[channel];
+                sample[channel] = self.integrator[channel] - self.capacitor[channel];
+                self.capacitor[channel] = self.integrator[channel] - sample[channel] * self.charge;
+            }
+            output.push(sample);
+            self.produced += 1;
+        }
+    }
+}
+
+/// Blackman windowed sinc impulse, offset by `phase / PHASES` of a sample.
+fn impulse(phase: usize) -> [f32; WIDTH] {
+    let offset = phase as f64 / PHASES as f64;
+    let mut taps = [0.0; WIDTH];
+    for (tap, weight) in taps.iter_mut().enumerate() {
+        let x = tap as f64 + 1.0 - HALF_WIDTH as f64 - offset;
+        let t = PI * 2.0 * CUTOFF * x;
+        let sinc = if t == 0.0 { 1.0 } else { t.sin() / t };
+        let window = 0.42
+            + 0.5 * (PI * x / HALF_WIDTH as f64).cos()
+            + 0.08 * (2.0 * PI * x / HALF_WIDTH as f64).cos();
+        *weight = sinc * window;
+    }
+    let sum: f64 = taps.iter().sum();
+    taps.map(|weight| (weight / sum) as f32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CLOCK_RATE: u32 = 4_194_304;
+
+    fn drain(buffer: &mut SampleBuffer) -> Vec<[f32; 2]> {
+        let mut samples = Vec::new();
+        buffer.drain_into(&mut samples);
+        samples
+    }
+
+    #[test]
+    fn produces_samples_at_output_rate() {
+        let mut resampler = Resampler::new(CLOCK_RATE, 48_000);
+        let mut buffer = SampleBuffer::new(100_000);
+        for _ in 0..CLOCK_RATE / 4 {
+            resampler.advance(4, &mut buffer);
+        }
+        // The last few samples wait for the impulse to pass.
+        assert_eq!(buffer.len(), 48_000 - HALF_WIDTH + 1);
+    }
+
+    #[test]
+    fn impulses_sum_to_one() {
+        for phase in 0..=PHASES {
+            let sum: f32 = impulse(phase).iter().sum();
+            assert!((sum - 1.0).abs() < 1e-5, "Phase {phase}");
+        }
+    }
+
+    #[test]
+    fn step_settles_at_new_level() {
+        let mut resampler = Resampler::new(CLOCK_RATE, 48_000);
+        let mut buffer = SampleBuffer::new(1_000);
+        resampler.advance(1_000, &mut buffer);
+        resampler.set_level([0.5, -0.5]);
+        resampler.advance(2_000, &mut buffer);
+        let samples = drain(&mut buffer);
+        // The high-pass filter has already started to pull it back to 0.
+        let last = samples.last().unwrap();
+        assert!((last[0] - 0.5).abs() < 0.05, "{last:?}");
+        assert!((last[1] + 0.5).abs() < 0.05, "{last:?}");
+        // Band-limiting spreads the step over several samples.
+        assert!(samples
+            .iter()
+            .any(|sample| sample[0] > 0.05 && sample[0] < 0.45));
+    }
+
+    #[test]
+    fn removes_dc_offset() {
+        let mut resampler = Resampler::new(CLOCK_RATE, 48_000);
+        let mut buffer = SampleBuffer::new(100_000);
+        resampler.set_level([1.0, 1.0]);
+        resampler.advance(CLOCK_RATE, &mut buffer);
+        let samples = drain(&mut buffer);
+        assert!(samples.last().unwrap()[0].abs() < 0.01);
+    }
+}
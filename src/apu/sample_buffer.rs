@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+
+/// Ring buffer of stereo samples waiting to be played by a frontend. When it
+/// isn't drained fast enough, the oldest samples are dropped.
+pub struct SampleBuffer {
+    samples: VecDeque<[f32; 2]>,
+    capacity: usize,
+}
+
+impl SampleBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: [f32; 2]) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Moves every buffered sample, oldest first, onto the end of `output`.
+    pub fn drain_into(&mut self, output: &mut Vec<[f32; 2]>) {
+        output.extend(self.samples.drain(..));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn drains_samples_in_order() {
+        let mut buffer = SampleBuffer::new(4);
+        buffer.push([0.1, 0.2]);
+        buffer.push([0.3, 0.4]);
+        let mut output = vec![[0.0, 0.0]];
+        buffer.drain_into(&mut output);
+        assert_eq!(output, [[0.0, 0.0], [0.1, 0.2], [0.3, 0.4]]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn drops_oldest_samples_when_full() {
+        let mut buffer = SampleBuffer::new(2);
+        for value in [1.0, 2.0, 3.0] {
+            buffer.push([value, value]);
+        }
+        let mut output = Vec::new();
+        buffer.drain_into(&mut output);
+        assert_eq!(output, [[2.0, 2.0], [3.0, 3.0]]);
+    }
+}
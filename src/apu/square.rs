@@ -0,0 +1,320 @@
+use super::envelope::Envelope;
+use super::length::LengthCounter;
+
+// Waveforms for each duty cycle, one bit per step.
+const DUTY_PATTERNS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1], // 12.5%
+    [1, 0, 0, 0, 0, 0, 0, 1], // 25%
+    [1, 0, 0, 0, 0, 1, 1, 1], // 50%
+    [0, 1, 1, 1, 1, 1, 1, 0], // 75%
+];
+
+// Bits of NRx0-NRx4 which always read back as 1.
+const READ_MASKS: [u8; 5] = [0x80, 0x3F, 0x00, 0xFF, 0xBF];
+
+/// Frequency sweep, only present on channel 1.
+#[derive(Default)]
+struct Sweep {
+    register: u8,
+    enabled: bool,
+    shadow: u16,
+    timer: u8,
+}
+
+enum SweepStep {
+    Unchanged,
+    Frequency(u16),
+    Overflow,
+}
+
+impl Sweep {
+    fn pace(&self) -> u8 {
+        (self.register >> 4) & 0b111
+    }
+
+    fn negates(&self) -> bool {
+        self.register & 0b1000 != 0
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0b111
+    }
+
+    /// A pace of 0 is treated as 8 by the sweep timer.
+    fn reload_timer(&mut self) {
+        self.timer = match self.pace() {
+            0 => 8,
+            pace => pace,
+        };
+    }
+
+    /// Next frequency, or `None` if it would overflow 11 bits.
+    fn calculate(&self) -> Option<u16> {
+        let delta = self.shadow >> self.shift();
+        let frequency = if self.negates() {
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        };
+        (frequency <= 0x7FF).then_some(frequency)
+    }
+
+    /// Returns false if the initial overflow check disables the channel.
+    fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow = frequency;
+        self.reload_timer();
+        self.enabled = self.pace() != 0 || self.shift() != 0;
+        self.shift() == 0 || self.calculate().is_some()
+    }
+
+    /// Clocked at 128 Hz by the frame sequencer.
+    fn clock(&mut self) -> SweepStep {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return SweepStep::Unchanged;
+        }
+        self.reload_timer();
+        if !self.enabled || self.pace() == 0 {
+            return SweepStep::Unchanged;
+        }
+        match self.calculate() {
+            None => SweepStep::Overflow,
+            Some(frequency) if self.shift() != 0 => {
+                self.shadow = frequency;
+                // The new frequency is checked again, but not used.
+                match self.calculate() {
+                    None => SweepStep::Overflow,
+                    Some(_) => SweepStep::Frequency(frequency),
+                }
+            }
+            Some(_) => SweepStep::Unchanged,
+        }
+    }
+}
+
+/// Square wave channel (channels 1 and 2).
+pub struct Square {
+    registers: [u8; 5],
+    sweep: Option<Sweep>,
+    length: LengthCounter,
+    envelope: Envelope,
+    duty_step: usize,
+    // T-cycles until the next duty step.
+    timer: i32,
+    enabled: bool,
+}
+
+impl Square {
+    pub fn new(with_sweep: bool) -> Self {
+        Self {
+            registers: [0; 5],
+            sweep: with_sweep.then(Sweep::default),
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            duty_step: 0,
+            timer: 0,
+            enabled: false,
+        }
+    }
+
+    fn duty(&self) -> usize {
+        (self.registers[1] >> 6) as usize
+    }
+
+    fn frequency(&self) -> u16 {
+        (self.registers[4] as u16 & 0b111) << 8 | self.registers[3] as u16
+    }
+
+    fn set_frequency(&mut self, frequency: u16) {
+        self.registers[3] = frequency as u8;
+        self.registers[4] = (self.registers[4] & !0b111) | (frequency >> 8) as u8;
+    }
+
+    fn period(&self) -> i32 {
+        (0x800 - self.frequency() as i32) * 4
+    }
+
+    /// Reads NRx0-NRx4, given as 0-4.
+    pub fn read(&self, register: usize) -> u8 {
+        let value = match register {
+            2 => self.envelope.read(),
+            _ => self.registers[register],
+        };
+        value | READ_MASKS[register]
+    }
+
+    /// Writes NRx0-NRx4, given as 0-4.
+    pub fn write(&mut self, register: usize, byte: u8) {
+        self.registers[register] = byte;
+        match register {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    sweep.register = byte;
+                }
+            }
+            1 => self.length.load(byte & 0x3F),
+            2 => {
+                self.envelope.write(byte);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            4 => {
+                self.length.set_enabled(byte & 0x40 != 0);
+                if byte & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.timer = self.period();
+        let frequency = self.frequency();
+        if let Some(sweep) = &mut self.sweep {
+            if !sweep.trigger(frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    /// Current output level, from 0 to 15.
+    pub fn output(&self) -> u8 {
+        if self.enabled {
+            DUTY_PATTERNS[self.duty()][self.duty_step] * self.envelope.volume()
+        } else {
+            0
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if !self.enabled {
+            return;
+        }
+        self.timer -= cycles as i32;
+        while self.timer <= 0 {
+            self.timer += self.period();
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+        match sweep.clock() {
+            SweepStep::Unchanged => {}
+            SweepStep::Frequency(frequency) => self.set_frequency(frequency),
+            SweepStep::Overflow => self.enabled = false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn playing(frequency: u16) -> Square {
+        let mut square = Square::new(true);
+        // 50% duty, full volume.
+        square.write(1, 0x80);
+        square.write(2, 0xF0);
+        square.write(3, frequency as u8);
+        square.write(4, 0x80 | (frequency >> 8) as u8);
+        square
+    }
+
+    #[test]
+    fn unused_bits_read_as_set() {
+        let square = Square::new(true);
+        let values: Vec<u8> = (0..5).map(|register| square.read(register)).collect();
+        assert_eq!(values, READ_MASKS);
+    }
+
+    #[test]
+    fn trigger_requires_dac() {
+        let mut square = Square::new(false);
+        square.write(4, 0x80);
+        assert!(!square.enabled());
+        square.write(2, 0x10);
+        square.write(4, 0x80);
+        assert!(square.enabled());
+        square.write(2, 0x00);
+        assert!(!square.enabled(), "Disabling the DAC disables the channel");
+    }
+
+    #[test]
+    fn steps_through_duty_cycle() {
+        // Period of (2048 - 2044) * 4 = 16 T-cycles per step.
+        let mut square = playing(2044);
+        let mut levels = Vec::new();
+        for _ in 0..8 {
+            square.tick(16);
+            levels.push(square.output());
+        }
+        assert_eq!(levels, [0, 0, 0, 0, 15, 15, 15, 15]);
+    }
+
+    #[test]
+    fn length_timer_disables_channel() {
+        let mut square = playing(0);
+        square.write(1, 0x3E);
+        square.write(4, 0xC0);
+        square.clock_length();
+        assert!(square.enabled());
+        square.clock_length();
+        assert!(!square.enabled());
+    }
+
+    #[test]
+    fn sweep_increases_frequency() {
+        let mut square = playing(0x100);
+        // Pace 1, increasing, shift 1.
+        square.write(0, 0x11);
+        square.write(4, 0x81);
+        square.clock_sweep();
+        assert_eq!(square.frequency(), 0x180);
+        square.clock_sweep();
+        assert_eq!(square.frequency(), 0x240);
+    }
+
+    #[test]
+    fn sweep_overflow_disables_channel() {
+        let mut square = playing(0x500);
+        square.write(0, 0x11);
+        square.write(4, 0x85);
+        assert!(square.enabled());
+        square.clock_sweep();
+        assert!(!square.enabled());
+    }
+
+    #[test]
+    fn sweep_overflow_on_trigger_disables_channel() {
+        let mut square = playing(0x7F0);
+        square.write(0, 0x01);
+        square.write(4, 0x87);
+        assert!(!square.enabled());
+    }
+}
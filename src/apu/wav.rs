@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+const BYTES_PER_FRAME: u32 = (CHANNELS * BITS_PER_SAMPLE / 8) as u32;
+
+// Size of the RIFF, fmt and data chunk headers before the sample data.
+const HEADER_SIZE: u32 = 44;
+
+// Offsets of the chunk sizes, which are only known once recording stops.
+const RIFF_SIZE_OFFSET: u64 = 4;
+const DATA_SIZE_OFFSET: u64 = 40;
+
+/// Writer of 16-bit stereo PCM WAV files.
+///
+/// The header is written up front with empty sizes, which are filled in by
+/// `finish`, or by `update_header` to leave a playable file should the
+/// process be killed before then.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    data_size: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &Path, sample_rate: u32) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        // Format 1 is integer PCM.
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * BYTES_PER_FRAME).to_le_bytes())?;
+        writer.write_all(&(BYTES_PER_FRAME as u16).to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            data_size: 0,
+        })
+    }
+
+    /// Appends a stereo sample, clipping each side to -1.0 to 1.0.
+    pub fn write_sample(&mut self, sample: [f32; 2]) -> io::Result<()> {
+        if self.data_size > u32::MAX - HEADER_SIZE - BYTES_PER_FRAME {
+            return Err(io::Error::other("WAV file size limit reached"));
+        }
+        for level in sample {
+            let value = (level.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.data_size += BYTES_PER_FRAME;
+        Ok(())
+    }
+
+    /// Fills in the chunk sizes for the samples written so far, and flushes
+    /// them to the underlying writer.
+    pub fn update_header(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+
+    /// Fills in the chunk sizes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.update_header()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_header() {
+        let wav = WavWriter::new(Cursor::new(Vec::new()), 48_000).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+        assert_eq!(bytes.len(), HEADER_SIZE as usize);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4), 36);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&bytes, 24), 48_000);
+        assert_eq!(u32_at(&bytes, 28), 192_000);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(&bytes, 40), 0);
+    }
+
+    #[test]
+    fn writes_clipped_samples_and_sizes() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 44_100).unwrap();
+        wav.write_sample([0.0, 1.0]).unwrap();
+        wav.write_sample([-2.0, 0.5]).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+
+        assert_eq!(u32_at(&bytes, 4), 36 + 8);
+        assert_eq!(u32_at(&bytes, 40), 8);
+        let samples: Vec<i16> = bytes[44..]
+            .chunks(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(samples, [0, 32767, -32767, 16384]);
+    }
+
+    #[test]
+    fn updates_header_while_recording() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 44_100).unwrap();
+        wav.write_sample([0.0, 0.0]).unwrap();
+        wav.update_header().unwrap();
+        assert_eq!(u32_at(wav.writer.get_ref(), 40), 4);
+
+        wav.write_sample([0.5, 0.5]).unwrap();
+        let bytes = wav.finish().unwrap().into_inner();
+        assert_eq!(bytes.len(), HEADER_SIZE as usize + 8);
+        assert_eq!(u32_at(&bytes, 40), 8);
+    }
+}
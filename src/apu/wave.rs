@@ -0,0 +1,183 @@
+use super::length::LengthCounter;
+
+// FF30-FF3F: Wave pattern RAM, holding 32 4-bit samples.
+pub const WAVE_RAM_SIZE: usize = 16;
+
+// Bits of NR30-NR34 which always read back as 1.
+const READ_MASKS: [u8; 5] = [0x7F, 0xFF, 0x9F, 0xFF, 0xBF];
+
+/// Wave channel (channel 3), which plays back samples from wave RAM.
+pub struct Wave {
+    registers: [u8; 5],
+    ram: [u8; WAVE_RAM_SIZE],
+    length: LengthCounter,
+    // Index of the current 4-bit sample in wave RAM.
+    position: usize,
+    // T-cycles until the next sample.
+    timer: i32,
+    enabled: bool,
+}
+
+impl Wave {
+    pub fn new() -> Self {
+        Self {
+            registers: [0; 5],
+            ram: [0; WAVE_RAM_SIZE],
+            length: LengthCounter::new(256),
+            position: 0,
+            timer: 0,
+            enabled: false,
+        }
+    }
+
+    fn frequency(&self) -> u16 {
+        (self.registers[4] as u16 & 0b111) << 8 | self.registers[3] as u16
+    }
+
+    fn period(&self) -> i32 {
+        (0x800 - self.frequency() as i32) * 2
+    }
+
+    /// Right shift applied to samples for the output level in NR32.
+    fn volume_shift(&self) -> u8 {
+        match (self.registers[2] >> 5) & 0b11 {
+            0 => 4,
+            level => level - 1,
+        }
+    }
+
+    /// Reads NR30-NR34, given as 0-4.
+    pub fn read(&self, register: usize) -> u8 {
+        self.registers[register] | READ_MASKS[register]
+    }
+
+    /// Writes NR30-NR34, given as 0-4.
+    pub fn write(&mut self, register: usize, byte: u8) {
+        self.registers[register] = byte;
+        match register {
+            0 if !self.dac_enabled() => self.enabled = false,
+            1 => self.length.load(byte),
+            4 => {
+                self.length.set_enabled(byte & 0x40 != 0);
+                if byte & 0x80 != 0 {
+                    self.trigger();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn read_ram(&self, index: usize) -> u8 {
+        self.ram[index]
+    }
+
+    pub fn write_ram(&mut self, index: usize, byte: u8) {
+        self.ram[index] = byte;
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled();
+        self.length.trigger();
+        self.position = 0;
+        self.timer = self.period();
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.registers[0] & 0x80 != 0
+    }
+
+    /// Current output level, from 0 to 15.
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        let byte = self.ram[self.position / 2];
+        // The upper nibble is played first.
+        let sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0F
+        };
+        sample >> self.volume_shift()
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if !self.enabled {
+            return;
+        }
+        self.timer -= cycles as i32;
+        while self.timer <= 0 {
+            self.timer += self.period();
+            self.position = (self.position + 1) % (WAVE_RAM_SIZE * 2);
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn playing(volume: u8) -> Wave {
+        let mut wave = Wave::new();
+        for index in 0..WAVE_RAM_SIZE {
+            wave.write_ram(index, 0x0F);
+        }
+        wave.write(0, 0x80);
+        wave.write(2, volume << 5);
+        // Period of (2048 - 2040) * 2 = 16 T-cycles per sample.
+        wave.write(3, 0xF8);
+        wave.write(4, 0x87);
+        wave
+    }
+
+    #[test]
+    fn plays_upper_nibble_first() {
+        let mut wave = playing(1);
+        wave.tick(16);
+        assert_eq!(wave.output(), 0x0F, "Second sample");
+        wave.tick(16);
+        assert_eq!(wave.output(), 0x00, "Third sample");
+    }
+
+    #[test]
+    fn output_level_shifts_samples() {
+        let mut wave = playing(2);
+        wave.tick(16);
+        assert_eq!(wave.output(), 0x07);
+
+        let mut wave = playing(0);
+        wave.tick(16);
+        assert_eq!(wave.output(), 0x00, "Muted");
+    }
+
+    #[test]
+    fn disabling_dac_disables_channel() {
+        let mut wave = playing(1);
+        assert!(wave.enabled());
+        wave.write(0, 0x00);
+        assert!(!wave.enabled());
+    }
+
+    #[test]
+    fn unused_bits_read_as_set() {
+        let wave = Wave::new();
+        let values: Vec<u8> = (0..5).map(|register| wave.read(register)).collect();
+        assert_eq!(values, READ_MASKS);
+    }
+}
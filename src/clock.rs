@@ -0,0 +1,261 @@
+use std::time::{Duration, Instant};
+
+/// T-cycles per second of the DMG system clock.
+pub const CLOCK_SPEED: u32 = 4_194_304;
+
+/// T-cycles taken by the PPU to draw one frame, including VBlank.
+pub const CYCLES_PER_FRAME: u32 = 70224;
+
+// If emulation falls this far behind, give up on catching up rather than
+// running flat out until the backlog is cleared.
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+// At `Speed::Audio`, emulation waits while more than this much output is
+// buffered, polling it at the interval below.
+const AUDIO_LATENCY: Duration = Duration::from_millis(50);
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Achieved speed is averaged over windows of roughly this length.
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+
+/// How fast emulation runs relative to real hardware.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Speed {
+    /// Real time scaled by a factor, e.g. 2.0 for fast-forward or 0.5 for
+    /// slow motion. The factor must be positive and finite.
+    Multiplier(f64),
+    /// As fast as the host allows.
+    Unthrottled,
+    /// Paced by audio playback, which avoids the buffer under- or overrunning
+    /// when the sound card's clock drifts from the wall clock. Requires the
+    /// output to be drained as it is played.
+    Audio,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::Multiplier(1.0)
+    }
+}
+
+/// Paces emulation against the wall clock. Rather than sleeping after every
+/// cycle, a batch of cycles (usually a frame) is run at full speed, then
+/// `sync` sleeps until that batch is due.
+pub struct Clock {
+    speed: Speed,
+    // Wall clock time at which the cycles emulated so far are due.
+    deadline: Instant,
+    window_start: Instant,
+    window_cycles: u64,
+    // Speed achieved over the last complete window, as a percentage of real
+    // hardware.
+    achieved: f64,
+}
+
+impl Clock {
+    pub fn new(speed: Speed) -> Self {
+        Self::check_speed(speed);
+        let now = Instant::now();
+        Self {
+            speed,
+            deadline: now,
+            window_start: now,
+            window_cycles: 0,
+            achieved: 0.0,
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        Self::check_speed(speed);
+        self.speed = speed;
+        self.deadline = Instant::now();
+    }
+
+    // Pausing is up to the caller, as a zero multiplier would never finish
+    // sleeping.
+    fn check_speed(speed: Speed) {
+        if let Speed::Multiplier(multiplier) = speed {
+            assert!(
+                multiplier.is_finite() && multiplier > 0.0,
+                "Invalid speed multiplier {multiplier}"
+            );
+        }
+    }
+
+    /// Speed achieved over the last second, as a percentage of real hardware.
+    pub fn speed_percentage(&self) -> f64 {
+        self.achieved
+    }
+
+    /// Wall clock time `cycles` should take at the current speed.
+    fn duration_of(&self, cycles: u32) -> Option<Duration> {
+        match self.speed {
+            Speed::Multiplier(multiplier) => Some(Duration::from_secs_f64(
+                cycles as f64 / (CLOCK_SPEED as f64 * multiplier),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Records that `cycles` have been emulated, sleeping until they are due.
+    pub fn sync(&mut self, cycles: u32) {
+        if let Some(sleep) = self.advance_deadline(cycles, Instant::now()) {
+            std::thread::sleep(sleep);
+        }
+        self.measure(cycles, Instant::now());
+    }
+
+    /// Moves the deadline on by `cycles`, returning how long to sleep from
+    /// `now` until it is reached.
+    fn advance_deadline(&mut self, cycles: u32, now: Instant) -> Option<Duration> {
+        self.deadline += self.duration_of(cycles)?;
+        if self.deadline > now {
+            return Some(self.deadline - now);
+        }
+        if now - self.deadline > MAX_LAG {
+            self.deadline = now;
+        }
+        None
+    }
+
+    /// Records that `cycles` have been emulated, and at `Speed::Audio` waits
+    /// until the output `buffered`, polled until it drains, is back down to
+    /// `AUDIO_LATENCY`.
+    pub fn sync_audio(&mut self, cycles: u32, mut buffered: impl FnMut() -> Duration) {
+        if self.speed == Speed::Audio {
+            // Nothing may be draining the buffer, so don't wait forever.
+            let start = Instant::now();
+            while buffered() > AUDIO_LATENCY && start.elapsed() < MAX_LAG {
+                std::thread::sleep(AUDIO_POLL_INTERVAL);
+            }
+        }
+        self.sync(cycles);
+    }
+
+    fn measure(&mut self, cycles: u32, now: Instant) {
+        self.window_cycles += cycles as u64;
+        let elapsed = now - self.window_start;
+        if elapsed >= MEASURE_WINDOW {
+            let real_time_cycles = elapsed.as_secs_f64() * CLOCK_SPEED as f64;
+            self.achieved = self.window_cycles as f64 / real_time_cycles * 100.0;
+            log::debug!("Running at {:.1}% speed", self.achieved);
+            self.window_start = now;
+            self.window_cycles = 0;
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(Speed::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_lasts_one_sixtieth_of_a_second() {
+        let clock = Clock::default();
+        let duration = clock.duration_of(CYCLES_PER_FRAME).unwrap();
+        assert_eq!(duration.as_micros(), 16742);
+    }
+
+    #[test]
+    fn multiplier_scales_duration() {
+        let fast = Clock::new(Speed::Multiplier(2.0));
+        let slow = Clock::new(Speed::Multiplier(0.5));
+        assert_eq!(
+            fast.duration_of(CLOCK_SPEED),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(slow.duration_of(CLOCK_SPEED), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn unthrottled_never_sleeps() {
+        let mut clock = Clock::new(Speed::Unthrottled);
+        assert_eq!(clock.duration_of(CLOCK_SPEED), None);
+
+        let start = Instant::now();
+        clock.sync(CLOCK_SPEED);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn sync_sleeps_until_cycles_are_due() {
+        let mut clock = Clock::new(Speed::Multiplier(100.0));
+        let start = clock.deadline;
+        let sleep = clock.advance_deadline(CLOCK_SPEED, start);
+        assert_eq!(sleep, Some(Duration::from_millis(10)));
+
+        let sleep = clock.advance_deadline(CLOCK_SPEED, start + Duration::from_millis(15));
+        assert_eq!(sleep, Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn gives_up_catching_up_when_too_far_behind() {
+        let mut clock = Clock::default();
+        let start = clock.deadline;
+        let now = start + Duration::from_secs(1);
+        assert_eq!(clock.advance_deadline(CYCLES_PER_FRAME, now), None);
+        assert_eq!(clock.deadline, now);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid speed multiplier")]
+    fn rejects_zero_multiplier() {
+        Clock::new(Speed::Multiplier(0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid speed multiplier")]
+    fn rejects_nan_multiplier() {
+        Clock::default().set_speed(Speed::Multiplier(f64::NAN));
+    }
+
+    #[test]
+    fn audio_waits_for_output_to_drain() {
+        let mut clock = Clock::new(Speed::Audio);
+        assert_eq!(clock.duration_of(CLOCK_SPEED), None);
+
+        let mut buffered = Duration::from_millis(53);
+        let mut polls = 0;
+        clock.sync_audio(CYCLES_PER_FRAME, || {
+            polls += 1;
+            buffered -= Duration::from_millis(1);
+            buffered
+        });
+        assert_eq!(polls, 3);
+    }
+
+    #[test]
+    fn audio_stops_waiting_when_not_drained() {
+        let mut clock = Clock::new(Speed::Audio);
+        let start = Instant::now();
+        clock.sync_audio(CYCLES_PER_FRAME, || Duration::from_secs(1));
+        assert!(start.elapsed() >= MAX_LAG);
+    }
+
+    #[test]
+    fn other_speeds_ignore_audio() {
+        let mut clock = Clock::new(Speed::Unthrottled);
+        clock.sync_audio(CYCLES_PER_FRAME, || panic!("Polled"));
+    }
+
+    #[test]
+    fn measures_achieved_speed() {
+        let mut clock = Clock::default();
+        let start = clock.window_start;
+        clock.measure(CLOCK_SPEED, start + Duration::from_millis(500));
+        assert_eq!(clock.speed_percentage(), 0.0, "Window not complete");
+
+        clock.measure(CLOCK_SPEED, start + Duration::from_secs(2));
+        assert_eq!(clock.speed_percentage(), 100.0);
+    }
+}
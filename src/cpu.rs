@@ -1,24 +1,67 @@
-mod clock;
 mod operations;
 mod registers;
 mod run_extended_operation;
 mod run_operation;
 
+use std::fmt;
+
+use crate::interrupts::{Interrupt, IE_ADDR, IF_ADDR};
+use crate::joypad::P1_ADDR;
 use crate::memory::address_space::AddressSpace;
-use operations::Operation;
+use crate::model::Model;
+use operations::{ConditionalOperation, Operation};
 use registers::Registers;
 
-use clock::Clock;
 use run_extended_operation::run_extended_operation;
 use run_operation::run_operation;
 
+/// What the CPU does on fetching an illegal opcode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IllegalOpcodeAction {
+    /// Lock up as the hardware does, while the rest of the system keeps
+    /// running.
+    #[default]
+    LockUp,
+    /// Lock up, and also return from `run_for` so the state can be inspected.
+    Break,
+}
+
+/// An illegal opcode which locked up the CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IllegalOpcode {
+    pub op_code: u8,
+    pub addr: u16,
+}
+
+impl fmt::Display for IllegalOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CPU locked up on illegal opcode {:#04X} at {:#06X}",
+            self.op_code, self.addr
+        )
+    }
+}
+
+impl std::error::Error for IllegalOpcode {}
+
 pub struct Cpu {
     reg: Registers,
     mmu: Box<dyn AddressSpace>,
-    clock: Clock,
-    remaining_cycles: u8,
+    // T-cycles elapsed since the current step began.
+    step_cycles: u8,
+    // T-cycles run beyond the end of the last `run_for` call.
+    overrun: u32,
     ime: bool,
+    ime_scheduled: bool,
     is_halted: bool,
+    halt_bug: bool,
+    is_stopped: bool,
+    locked_up: Option<IllegalOpcode>,
+    illegal_opcode_action: IllegalOpcodeAction,
+    break_requested: bool,
+    model: Model,
+    double_speed: bool,
 }
 
 impl Cpu {
@@ -26,38 +69,236 @@ impl Cpu {
         Cpu {
             reg: Registers::new(),
             mmu: Box::new(mmu),
-            clock: Clock::default(),
-            remaining_cycles: 0,
-            ime: true,
+            step_cycles: 0,
+            overrun: 0,
+            // Interrupts are disabled when the boot ROM hands over control.
+            ime: false,
+            ime_scheduled: false,
             is_halted: false,
+            halt_bug: false,
+            is_stopped: false,
+            locked_up: None,
+            illegal_opcode_action: IllegalOpcodeAction::default(),
+            break_requested: false,
+            model: Model::Dmg,
+            double_speed: false,
+        }
+    }
+
+    /// Selects the hardware being emulated, which decides the post-boot
+    /// register values and whether STOP can switch speed.
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+    }
+
+    /// Sets the registers to the values left by the boot ROM, ready to start
+    /// executing the cartridge at 0x0100. Games detect a CGB from A = 0x11.
+    pub fn skip_boot_rom(&mut self) {
+        match self.model {
+            Model::Dmg => {
+                self.reg.set_af(0x01B0);
+                self.reg.set_bc(0x0013);
+                self.reg.set_de(0x00D8);
+                self.reg.set_hl(0x014D);
+            }
+            Model::Cgb => {
+                self.reg.set_af(0x1180);
+                self.reg.set_bc(0x0000);
+                self.reg.set_de(0xFF56);
+                self.reg.set_hl(0x000D);
+            }
         }
+        self.reg.set_sp(0xFFFE);
+        self.reg.set_pc(0x0100);
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    pub fn set_illegal_opcode_action(&mut self, action: IllegalOpcodeAction) {
+        self.illegal_opcode_action = action;
     }
 
-    pub fn run(&mut self) {
-        loop {
-            self.tick();
-            self.clock.sleep_until_next_cycle();
+    /// The illegal opcode which locked up the CPU, if any.
+    pub fn locked_up(&self) -> Option<IllegalOpcode> {
+        self.locked_up
+    }
+
+    /// Runs for the given number of T-cycles, as fast as possible.
+    ///
+    /// Returns early with an error if the CPU locks up and is set to break
+    /// on illegal opcodes.
+    pub fn run_for(&mut self, cycles: u32) -> Result<(), IllegalOpcode> {
+        let mut elapsed = self.overrun;
+        while elapsed < cycles {
+            let step_cycles = self.step() as u32;
+            // `cycles` is counted at normal speed, so in double speed mode
+            // each CPU cycle only takes half as long.
+            elapsed += if self.double_speed {
+                step_cycles / 2
+            } else {
+                step_cycles
+            };
+            if self.break_requested {
+                self.break_requested = false;
+                self.overrun = 0;
+                return Err(self.locked_up.expect("Break without lock up"));
+            }
         }
+        // Instructions can't be split, so carry any excess into the next call.
+        self.overrun = elapsed - cycles;
+        Ok(())
     }
 
-    fn tick(&mut self) {
-        if self.remaining_cycles > 0 {
-            self.remaining_cycles -= 1;
-            return;
+    /// Runs a single instruction, interrupt dispatch, or M-cycle of HALT or
+    /// STOP, returning the number of T-cycles taken.
+    fn step(&mut self) -> u8 {
+        self.step_cycles = 0;
+        if self.is_stopped {
+            // The system clock, and everything driven by it, is halted in
+            // STOP mode.
+            self.is_stopped = !self.joypad_line_low();
+            return 4;
+        }
+        if self.locked_up.is_some() {
+            // Nothing but a reset brings the CPU back, but the rest of the
+            // system keeps running.
+            self.cycle();
+            return self.step_cycles;
+        }
+        if self.mmu.stalls_cpu() {
+            // Paused while HDMA copies into VRAM, with interrupts held off.
+            self.cycle();
+            return self.step_cycles;
+        }
+        if self.service_interrupt() {
+            return self.step_cycles;
+        }
+        if self.is_halted {
+            self.cycle();
+            return self.step_cycles;
         }
-        let op_code = self.read_u8();
+        // EI only takes effect after the instruction following it.
+        let enable_ime = self.ime_scheduled;
+        let op_code = self.fetch_op_code();
         self.execute(op_code);
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+        self.step_cycles
+    }
+
+    /// Advances the rest of the system by one M-cycle. Called for every bus
+    /// access, and for internal delays within instructions.
+    fn cycle(&mut self) {
+        self.mmu.tick(if self.double_speed { 2 } else { 4 });
+        self.step_cycles += 4;
+    }
+
+    /// Toggles CGB double speed mode, on STOP with the switch armed in KEY1.
+    fn switch_speed(&mut self) {
+        self.double_speed = !self.double_speed;
+        log::debug!("Switched to double speed: {}", self.double_speed);
+        self.mmu.set_double_speed(self.double_speed);
+    }
+
+    /// Reads a byte over the bus, taking one M-cycle.
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        self.cycle();
+        self.mmu.get_byte(addr)
+    }
+
+    /// Writes a byte over the bus, taking one M-cycle.
+    fn write_byte(&mut self, addr: u16, byte: u8) {
+        self.cycle();
+        self.mmu.set_byte(addr, byte);
+    }
+
+    fn fetch_op_code(&mut self) -> u8 {
+        if self.halt_bug {
+            // HALT bug: the byte following HALT is read twice, as the PC
+            // fails to increment after fetching it.
+            self.halt_bug = false;
+            return self.read_byte(self.reg.pc());
+        }
+        self.read_u8()
+    }
+
+    /// Whether any of the selected P1 input lines is held low.
+    fn joypad_line_low(&mut self) -> bool {
+        self.mmu.get_byte(P1_ADDR) & 0x0F != 0x0F
+    }
+
+    fn pending_interrupts(&mut self) -> u8 {
+        let enabled = self.mmu.get_byte(IE_ADDR);
+        let requested = self.mmu.get_byte(IF_ADDR);
+        enabled & requested & 0x1F
+    }
+
+    /// Dispatches the highest priority pending interrupt, if interrupts are
+    /// enabled. Any pending interrupt wakes the CPU from HALT, regardless of IME.
+    fn service_interrupt(&mut self) -> bool {
+        let pending = self.pending_interrupts();
+        let Some(interrupt) = Interrupt::highest_priority(pending) else {
+            return false;
+        };
+
+        self.is_halted = false;
+
+        if !self.ime {
+            return false;
+        }
+
+        log::trace!("Servicing interrupt {interrupt:?}");
+
+        // Dispatch takes 5 M-cycles: two internal delays, pushing PC, and
+        // then jumping to the vector.
+        self.ime = false;
+        self.cycle();
+        self.cycle();
+        let requested = self.mmu.get_byte(IF_ADDR);
+        self.mmu.set_byte(IF_ADDR, requested & !interrupt.bit());
+        self.push_u16(self.reg.pc());
+        self.reg.set_pc(interrupt.vector());
+        self.cycle();
+        true
     }
 
     fn run_operation(&mut self, op: impl Operation, cycles: u8) {
         let pc = self.reg.pc();
         log::trace!("({pc:#06X}): {op}");
-        self.remaining_cycles += cycles;
         op.run(self);
+        self.finish_instruction(cycles);
+    }
+
+    fn run_conditional_operation(
+        &mut self,
+        op: impl ConditionalOperation,
+        taken_cycles: u8,
+        not_taken_cycles: u8,
+    ) {
+        let pc = self.reg.pc();
+        log::trace!("({pc:#06X}): {op}");
+        let cycles = if op.run(self) {
+            taken_cycles
+        } else {
+            not_taken_cycles
+        };
+        self.finish_instruction(cycles);
+    }
+
+    /// Operations only model the M-cycles in which they access the bus, so
+    /// any remaining internal delay happens at the end.
+    fn finish_instruction(&mut self, cycles: u8) {
+        while self.step_cycles < cycles {
+            self.cycle();
+        }
     }
 
     fn read_u8(&mut self) -> u8 {
-        let res = self.mmu.get_byte(self.reg.pc());
+        let res = self.read_byte(self.reg.pc());
         self.reg.incr_pc();
         res
     }
@@ -68,6 +309,24 @@ impl Cpu {
         (h << 8) | l
     }
 
+    fn push_u16(&mut self, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        self.reg.decr_sp();
+        self.write_byte(self.reg.sp(), high);
+        self.reg.decr_sp();
+        self.write_byte(self.reg.sp(), low);
+    }
+
+    fn lock_up(&mut self, op_code: u8) {
+        let illegal = IllegalOpcode {
+            op_code,
+            addr: self.reg.pc().wrapping_sub(1),
+        };
+        log::error!("{illegal}");
+        self.locked_up = Some(illegal);
+        self.break_requested = self.illegal_opcode_action == IllegalOpcodeAction::Break;
+    }
+
     fn execute(&mut self, op_code: u8) {
         run_operation(self, op_code);
     }
@@ -76,3 +335,486 @@ impl Cpu {
         run_extended_operation(self, op_code);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::interrupts::InterruptController;
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+    use crate::timer::{Timer, TAC_ADDR, TIMA_ADDR};
+
+    fn with_interrupts() -> Cpu {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(InterruptController::new());
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        let mut cpu = Cpu::new(mmu);
+        cpu.reg.set_pc(0x1234);
+        cpu.reg.set_sp(0xFFFE);
+        cpu
+    }
+
+    /// RAM which records the T-cycle on which each write lands.
+    struct Probe {
+        ram: Ram,
+        cycles: u32,
+        writes: Vec<(u32, u16, u8)>,
+    }
+
+    impl AddressSpace for Probe {
+        fn accepts(&self, addr: u16) -> bool {
+            self.ram.accepts(addr)
+        }
+
+        fn get_byte(&mut self, addr: u16) -> u8 {
+            self.ram.get_byte(addr)
+        }
+
+        fn set_byte(&mut self, addr: u16, byte: u8) {
+            self.writes.push((self.cycles, addr, byte));
+            self.ram.set_byte(addr, byte);
+        }
+
+        fn tick(&mut self, cycles: u8) {
+            self.cycles += cycles as u32;
+        }
+    }
+
+    fn with_probe(program: &[u8]) -> (Cpu, Rc<RefCell<Probe>>) {
+        let mut ram = Ram::new(0x0000, 0xFFFF);
+        for (i, byte) in program.iter().enumerate() {
+            ram.set_byte(0x1234 + i as u16, *byte);
+        }
+        let probe = Rc::new(RefCell::new(Probe {
+            ram,
+            cycles: 0,
+            writes: Vec::new(),
+        }));
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(InterruptController::new());
+        mmu.add_address_space(probe.clone());
+        let mut cpu = Cpu::new(mmu);
+        cpu.reg.set_pc(0x1234);
+        cpu.reg.set_sp(0xFFFE);
+        (cpu, probe)
+    }
+
+    #[test]
+    fn services_requested_and_enabled_interrupt() {
+        let mut cpu = with_interrupts();
+        cpu.ime = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Timer.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Timer.bit());
+
+        assert_eq!(cpu.step(), 20, "Dispatch should take 5 M-cycles");
+
+        assert_eq!(cpu.reg.pc(), 0x0050, "Should jump to timer vector");
+        assert_eq!(cpu.reg.sp(), 0xFFFC);
+        assert_eq!(cpu.mmu.get_byte(0xFFFD), 0x12);
+        assert_eq!(cpu.mmu.get_byte(0xFFFC), 0x34);
+        assert!(!cpu.ime, "IME should be cleared");
+        assert_eq!(
+            cpu.mmu.get_byte(IF_ADDR) & 0x1F,
+            0x00,
+            "IF bit acknowledged"
+        );
+    }
+
+    #[test]
+    fn services_highest_priority_interrupt_first() {
+        let mut cpu = with_interrupts();
+        cpu.ime = true;
+        cpu.mmu.set_byte(IE_ADDR, 0x1F);
+        cpu.mmu
+            .set_byte(IF_ADDR, Interrupt::Serial.bit() | Interrupt::Stat.bit());
+
+        cpu.step();
+
+        assert_eq!(cpu.reg.pc(), 0x0048);
+        assert_eq!(cpu.mmu.get_byte(IF_ADDR) & 0x1F, Interrupt::Serial.bit());
+    }
+
+    #[test]
+    fn ignores_interrupt_that_is_not_enabled() {
+        let mut cpu = with_interrupts();
+        cpu.ime = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::VBlank.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Timer.bit());
+
+        cpu.step();
+
+        assert_eq!(cpu.reg.pc(), 0x1235, "Should execute next instruction");
+    }
+
+    #[test]
+    fn does_not_service_interrupt_when_ime_is_clear() {
+        let mut cpu = with_interrupts();
+        cpu.ime = false;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Timer.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Timer.bit());
+
+        cpu.step();
+
+        assert_eq!(cpu.reg.pc(), 0x1235, "Should execute next instruction");
+        assert_eq!(cpu.mmu.get_byte(IF_ADDR) & 0x1F, Interrupt::Timer.bit());
+    }
+
+    #[test]
+    fn pending_interrupt_wakes_cpu_from_halt() {
+        let mut cpu = with_interrupts();
+        cpu.ime = false;
+        cpu.is_halted = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Joypad.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Joypad.bit());
+
+        cpu.step();
+
+        assert!(!cpu.is_halted, "CPU should leave HALT");
+    }
+
+    #[test]
+    fn halted_cpu_does_not_fetch_instructions() {
+        let mut cpu = with_interrupts();
+        cpu.is_halted = true;
+
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.reg.pc(), 0x1234, "PC should not advance while halted");
+        assert!(cpu.is_halted);
+    }
+
+    #[test]
+    fn halt_with_ime_set_services_interrupt_on_wake() {
+        let mut cpu = with_interrupts();
+        cpu.ime = true;
+        cpu.is_halted = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::VBlank.bit());
+
+        cpu.step();
+        assert!(cpu.is_halted, "Should remain halted with no request");
+
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::VBlank.bit());
+        cpu.step();
+
+        assert!(!cpu.is_halted);
+        assert_eq!(cpu.reg.pc(), 0x0040);
+        assert_eq!(
+            cpu.mmu.get_byte(0xFFFC),
+            0x34,
+            "Return address is after HALT"
+        );
+    }
+
+    #[test]
+    fn halt_with_ime_clear_resumes_without_servicing() {
+        let mut cpu = with_interrupts();
+        cpu.ime = false;
+        cpu.is_halted = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Timer.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Timer.bit());
+
+        cpu.step();
+
+        assert!(!cpu.is_halted);
+        assert_eq!(cpu.reg.pc(), 0x1235, "Should continue after HALT");
+        assert_eq!(cpu.mmu.get_byte(IF_ADDR) & 0x1F, Interrupt::Timer.bit());
+    }
+
+    #[test]
+    fn halt_bug_reads_next_byte_twice() {
+        let mut cpu = with_interrupts();
+        // HALT; INC B
+        cpu.mmu.set_byte(0x1234, 0x76);
+        cpu.mmu.set_byte(0x1235, 0x04);
+        cpu.reg.set_b(0x00);
+        cpu.ime = false;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Timer.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::Timer.bit());
+
+        // HALT
+        cpu.step();
+        assert!(!cpu.is_halted, "HALT should not suspend the CPU");
+
+        // INC B, executed twice.
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.reg.b(), 0x02);
+        assert_eq!(cpu.reg.pc(), 0x1236);
+    }
+
+    #[test]
+    fn stopped_cpu_waits_for_joypad_line_to_go_low() {
+        let mut cpu = with_interrupts();
+        cpu.mmu.set_byte(P1_ADDR, 0xFF);
+        cpu.is_stopped = true;
+
+        cpu.step();
+        assert!(cpu.is_stopped);
+        assert_eq!(cpu.reg.pc(), 0x1234, "PC should not advance while stopped");
+
+        // Direction keys selected, Down held.
+        cpu.mmu.set_byte(P1_ADDR, 0xE7);
+        cpu.step();
+        assert!(!cpu.is_stopped);
+
+        cpu.step();
+        assert_eq!(cpu.reg.pc(), 0x1235, "Should resume fetching");
+    }
+
+    #[test]
+    fn starts_with_interrupts_disabled() {
+        let cpu = with_interrupts();
+        assert!(!cpu.ime, "IME should be clear after boot");
+    }
+
+    #[test]
+    fn ei_enables_interrupts_after_following_instruction() {
+        let mut cpu = with_interrupts();
+        // EI; NOP; NOP
+        cpu.mmu.set_byte(0x1234, 0xFB);
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::VBlank.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::VBlank.bit());
+
+        // EI
+        cpu.step();
+        assert!(!cpu.ime, "IME should not be set immediately");
+
+        // NOP
+        cpu.step();
+        assert!(cpu.ime, "IME should be set after next instruction");
+        assert_eq!(cpu.reg.pc(), 0x1236, "Interrupt not serviced before NOP");
+
+        cpu.step();
+        assert_eq!(cpu.reg.pc(), 0x0040);
+    }
+
+    #[test]
+    fn ei_followed_by_di_never_enables_interrupts() {
+        let mut cpu = with_interrupts();
+        // EI; DI; NOP
+        cpu.mmu.set_byte(0x1234, 0xFB);
+        cpu.mmu.set_byte(0x1235, 0xF3);
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::VBlank.bit());
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::VBlank.bit());
+
+        for _ in 0..3 {
+            cpu.step();
+        }
+
+        assert!(!cpu.ime);
+        assert_eq!(cpu.reg.pc(), 0x1237, "Interrupt should not be serviced");
+    }
+
+    #[test]
+    fn timer_interrupt_wakes_halted_cpu() {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(interrupts.clone());
+        mmu.add_address_space(Timer::new(interrupts));
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        let mut cpu = Cpu::new(mmu);
+        cpu.reg.set_sp(0xFFFE);
+        cpu.ime = true;
+        cpu.is_halted = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::Timer.bit());
+        cpu.mmu.set_byte(TIMA_ADDR, 0xFF);
+        cpu.mmu.set_byte(TAC_ADDR, 0b101);
+
+        // TIMA overflows after 16 cycles, and is reloaded an M-cycle later.
+        for _ in 0..5 {
+            cpu.step();
+        }
+        assert!(cpu.is_halted);
+
+        cpu.step();
+        assert!(!cpu.is_halted);
+        assert_eq!(cpu.reg.pc(), Interrupt::Timer.vector());
+    }
+
+    #[test]
+    fn skip_boot_rom_sets_post_boot_registers() {
+        let mut cpu = with_interrupts();
+        cpu.skip_boot_rom();
+        assert_eq!(cpu.reg.af(), 0x01B0);
+        assert_eq!(cpu.reg.bc(), 0x0013);
+        assert_eq!(cpu.reg.de(), 0x00D8);
+        assert_eq!(cpu.reg.hl(), 0x014D);
+        assert_eq!(cpu.reg.sp(), 0xFFFE);
+        assert_eq!(cpu.reg.pc(), 0x0100);
+        assert!(!cpu.ime);
+    }
+
+    #[test]
+    fn step_returns_instruction_cycles() {
+        // NOP; LD BC,d16; PUSH BC; POP DE; LD (HL),d8; RLC (HL)
+        let (mut cpu, _) =
+            with_probe(&[0x00, 0x01, 0x34, 0x12, 0xC5, 0xD1, 0x36, 0x00, 0xCB, 0x06]);
+        cpu.reg.set_hl(0x8000);
+        for expected in [4, 12, 16, 12, 12, 16] {
+            assert_eq!(cpu.step(), expected);
+        }
+    }
+
+    #[test]
+    fn push_writes_after_internal_delay() {
+        // PUSH BC
+        let (mut cpu, probe) = with_probe(&[0xC5]);
+        cpu.reg.set_bc(0x239F);
+
+        cpu.step();
+
+        // Fetch, internal delay, then high byte before low byte.
+        assert_eq!(
+            probe.borrow().writes,
+            vec![(12, 0xFFFD, 0x23), (16, 0xFFFC, 0x9F)]
+        );
+    }
+
+    #[test]
+    fn store_to_hl_writes_after_operand_fetch() {
+        // LD (HL),d8
+        let (mut cpu, probe) = with_probe(&[0x36, 0x42]);
+        cpu.reg.set_hl(0xC000);
+
+        assert_eq!(cpu.step(), 12);
+        assert_eq!(probe.borrow().writes, vec![(12, 0xC000, 0x42)]);
+    }
+
+    #[test]
+    fn store_to_a16_writes_on_final_m_cycle() {
+        // LD (a16),A
+        let (mut cpu, probe) = with_probe(&[0xEA, 0x00, 0xC0]);
+        cpu.reg.set_a(0x42);
+
+        assert_eq!(cpu.step(), 16);
+        assert_eq!(probe.borrow().writes, vec![(16, 0xC000, 0x42)]);
+    }
+
+    #[test]
+    fn rst_pushes_return_address() {
+        // RST 08H
+        let (mut cpu, probe) = with_probe(&[0xCF]);
+
+        assert_eq!(cpu.step(), 16);
+        assert_eq!(cpu.reg.pc(), 0x0008);
+        assert_eq!(cpu.reg.sp(), 0xFFFC);
+        assert_eq!(
+            probe.borrow().writes,
+            vec![(12, 0xFFFD, 0x12), (16, 0xFFFC, 0x35)]
+        );
+    }
+
+    #[test]
+    fn run_for_carries_over_partial_instructions() {
+        // LD BC,d16, repeated.
+        let (mut cpu, probe) = with_probe(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00]);
+
+        // The instruction can't be split, so it runs past the requested cycles.
+        cpu.run_for(4).unwrap();
+        assert_eq!(probe.borrow().cycles, 12);
+        assert_eq!(cpu.overrun, 8);
+
+        // The overrun is deducted from the next call.
+        cpu.run_for(8).unwrap();
+        assert_eq!(probe.borrow().cycles, 12);
+        cpu.run_for(12).unwrap();
+        assert_eq!(probe.borrow().cycles, 24);
+        assert_eq!(cpu.overrun, 0);
+    }
+
+    /// Memory which pauses the CPU for a number of T-cycles, like HDMA does.
+    struct Stall {
+        mmu: Mmu,
+        cycles: u32,
+    }
+
+    impl AddressSpace for Stall {
+        fn accepts(&self, addr: u16) -> bool {
+            self.mmu.accepts(addr)
+        }
+
+        fn get_byte(&mut self, addr: u16) -> u8 {
+            self.mmu.get_byte(addr)
+        }
+
+        fn set_byte(&mut self, addr: u16, byte: u8) {
+            self.mmu.set_byte(addr, byte);
+        }
+
+        fn tick(&mut self, cycles: u8) {
+            self.cycles = self.cycles.saturating_sub(cycles as u32);
+        }
+
+        fn stalls_cpu(&self) -> bool {
+            self.cycles > 0
+        }
+    }
+
+    #[test]
+    fn stalled_cpu_waits_without_fetching() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(InterruptController::new());
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        let mut cpu = Cpu::new(Stall { mmu, cycles: 32 });
+        for _ in 0..8 {
+            assert_eq!(cpu.step(), 4);
+            assert_eq!(cpu.reg.pc(), 0x0000);
+        }
+        cpu.step();
+        assert_eq!(cpu.reg.pc(), 0x0001, "NOP fetched once the stall ends");
+    }
+
+    #[test]
+    fn double_speed_runs_twice_as_many_cycles() {
+        // NOP, repeated.
+        let (mut cpu, probe) = with_probe(&[0x00; 16]);
+        cpu.switch_speed();
+        cpu.run_for(16).unwrap();
+        assert_eq!(cpu.reg.pc(), 0x1234 + 8);
+        assert_eq!(probe.borrow().cycles, 16, "Peripherals run at normal speed");
+    }
+
+    #[test]
+    fn illegal_opcode_locks_up_cpu() {
+        // Illegal opcode 0xD3
+        let (mut cpu, probe) = with_probe(&[0xD3, 0x00]);
+        cpu.ime = true;
+        cpu.mmu.set_byte(IE_ADDR, Interrupt::VBlank.bit());
+
+        assert_eq!(cpu.run_for(4), Ok(()));
+        let illegal = IllegalOpcode {
+            op_code: 0xD3,
+            addr: 0x1234,
+        };
+        assert_eq!(cpu.locked_up(), Some(illegal));
+
+        cpu.mmu.set_byte(IF_ADDR, Interrupt::VBlank.bit());
+        assert_eq!(cpu.run_for(100), Ok(()));
+        assert_eq!(cpu.reg.pc(), 0x1235, "PC should not advance");
+        assert_eq!(probe.borrow().cycles, 104, "Peripherals keep running");
+    }
+
+    #[test]
+    fn illegal_opcode_breaks_when_configured() {
+        // NOP; illegal opcode 0xFD
+        let (mut cpu, probe) = with_probe(&[0x00, 0xFD, 0x00]);
+        cpu.set_illegal_opcode_action(IllegalOpcodeAction::Break);
+
+        let illegal = IllegalOpcode {
+            op_code: 0xFD,
+            addr: 0x1235,
+        };
+        assert_eq!(cpu.run_for(100), Err(illegal));
+        assert_eq!(probe.borrow().cycles, 8, "Should stop at the lock up");
+
+        // Resuming carries on with the CPU locked up.
+        assert_eq!(cpu.run_for(100), Ok(()));
+        assert_eq!(cpu.locked_up(), Some(illegal));
+        assert_eq!(cpu.reg.pc(), 0x1236);
+    }
+}
@@ -62,3 +62,10 @@ use crate::cpu::Cpu;
 pub trait Operation: fmt::Display {
     fn run(&self, cpu: &mut Cpu);
 }
+
+/// An operation which only branches when its condition holds, and takes a
+/// different number of cycles depending on whether it did.
+pub trait ConditionalOperation: fmt::Display {
+    /// Runs the operation, returning whether the branch was taken.
+    fn run(&self, cpu: &mut Cpu) -> bool;
+}
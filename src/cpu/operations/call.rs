@@ -26,13 +26,10 @@ impl Operation for Call {
         // The lower-order byte of a16 is placed in byte 2 of the object code, and the
         // higher-order byte is placed in byte 3.
         let a16 = cpu.read_u16();
-        let pc = cpu.reg.pc();
-        let sp = cpu.reg.sp();
-        let [pc_low, pc_high] = pc.to_be_bytes();
-        cpu.mmu.set_byte(sp - 2, pc_high);
-        cpu.mmu.set_byte(sp - 1, pc_low);
+        // SP is decremented in an internal M-cycle before the writes.
+        cpu.cycle();
+        cpu.push_u16(cpu.reg.pc());
         cpu.reg.set_pc(a16);
-        cpu.reg.set_sp(sp - 2);
     }
 }
 
@@ -2,7 +2,7 @@ use std::fmt;
 
 use super::call::Call;
 use super::condition::Condition;
-use crate::cpu::operations::Operation;
+use crate::cpu::operations::{ConditionalOperation, Operation};
 use crate::cpu::Cpu;
 
 pub struct ConditionalCall {
@@ -15,15 +15,15 @@ impl ConditionalCall {
     }
 }
 
-impl Operation for ConditionalCall {
-    fn run(&self, cpu: &mut Cpu) {
+impl ConditionalOperation for ConditionalCall {
+    fn run(&self, cpu: &mut Cpu) -> bool {
         if self.cond.check(cpu) {
             Call.run(cpu);
+            true
         } else {
             // Increment PC counter.
             cpu.read_u16();
-            // Don't have a better way of doing this at this time.
-            cpu.remaining_cycles = cpu.remaining_cycles.saturating_sub(12);
+            false
         }
     }
 }
@@ -9,6 +9,7 @@ pub struct Di;
 impl Operation for Di {
     fn run(&self, cpu: &mut Cpu) {
         cpu.ime = false;
+        cpu.ime_scheduled = false;
     }
 }
 
@@ -42,4 +43,12 @@ mod test {
         Di.run(&mut cpu);
         assert!(!cpu.ime, "IME should not be set");
     }
+
+    #[test]
+    fn cancels_scheduled_interrupt_master_enable() {
+        let mut cpu = empty();
+        cpu.ime_scheduled = true;
+        Di.run(&mut cpu);
+        assert!(!cpu.ime_scheduled, "Pending EI should be cancelled");
+    }
 }
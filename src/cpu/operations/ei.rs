@@ -4,11 +4,13 @@ use crate::cpu::operations::Operation;
 use crate::cpu::Cpu;
 
 /// Enable Interrupt
+///
+/// IME is only set once the instruction following EI has executed.
 pub struct Ei;
 
 impl Operation for Ei {
     fn run(&self, cpu: &mut Cpu) {
-        cpu.ime = true;
+        cpu.ime_scheduled = true;
     }
 }
 
@@ -36,10 +38,11 @@ mod test {
     }
 
     #[test]
-    fn sets_interrupt_master_enabled_flag() {
+    fn schedules_interrupt_master_enabled_flag() {
         let mut cpu = empty();
         cpu.ime = false;
         Ei.run(&mut cpu);
-        assert!(cpu.ime, "IME should be set");
+        assert!(!cpu.ime, "IME should not be set immediately");
+        assert!(cpu.ime_scheduled, "IME should be scheduled");
     }
 }
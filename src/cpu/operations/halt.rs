@@ -4,11 +4,19 @@ use crate::cpu::operations::Operation;
 use crate::cpu::Cpu;
 
 /// Halt
+///
+/// Suspends instruction fetch until an interrupt is pending. If IME is clear
+/// and an interrupt is already pending, the CPU does not halt and instead
+/// fails to increment the PC after fetching the next byte (the "HALT bug").
 pub struct Halt;
 
 impl Operation for Halt {
     fn run(&self, cpu: &mut Cpu) {
-        cpu.is_halted = true;
+        if !cpu.ime && cpu.pending_interrupts() != 0 {
+            cpu.halt_bug = true;
+        } else {
+            cpu.is_halted = true;
+        }
     }
 }
 
@@ -21,6 +29,8 @@ impl fmt::Display for Halt {
 #[cfg(test)]
 mod test {
     use crate::cpu::Cpu;
+    use crate::interrupts::{InterruptController, IE_ADDR, IF_ADDR};
+    use crate::memory::address_space::AddressSpace;
     use crate::memory::void::Void;
 
     use super::*;
@@ -29,6 +39,13 @@ mod test {
         Cpu::new(Void)
     }
 
+    fn with_pending_interrupt() -> Cpu {
+        let mut ic = InterruptController::new();
+        ic.set_byte(IE_ADDR, 0x01);
+        ic.set_byte(IF_ADDR, 0x01);
+        Cpu::new(ic)
+    }
+
     #[test]
     fn display_trait() {
         let op = Halt;
@@ -42,4 +59,22 @@ mod test {
         Halt.run(&mut cpu);
         assert!(cpu.is_halted, "Halted should be set");
     }
+
+    #[test]
+    fn halts_when_ime_set_and_interrupt_pending() {
+        let mut cpu = with_pending_interrupt();
+        cpu.ime = true;
+        Halt.run(&mut cpu);
+        assert!(cpu.is_halted, "Halted should be set");
+        assert!(!cpu.halt_bug, "HALT bug should not trigger");
+    }
+
+    #[test]
+    fn triggers_halt_bug_when_ime_clear_and_interrupt_pending() {
+        let mut cpu = with_pending_interrupt();
+        cpu.ime = false;
+        Halt.run(&mut cpu);
+        assert!(!cpu.is_halted, "Halted should not be set");
+        assert!(cpu.halt_bug, "HALT bug should trigger");
+    }
 }
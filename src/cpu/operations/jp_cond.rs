@@ -3,7 +3,7 @@ use std::fmt;
 use super::condition::Condition;
 use super::jp::Jp;
 use super::targets::AddressTarget;
-use crate::cpu::operations::Operation;
+use crate::cpu::operations::{ConditionalOperation, Operation};
 use crate::cpu::Cpu;
 
 pub struct ConditionalJp {
@@ -17,15 +17,15 @@ impl ConditionalJp {
     }
 }
 
-impl Operation for ConditionalJp {
-    fn run(&self, cpu: &mut Cpu) {
+impl ConditionalOperation for ConditionalJp {
+    fn run(&self, cpu: &mut Cpu) -> bool {
         if self.cond.check(cpu) {
             Jp::new(self.operand).run(cpu);
+            true
         } else {
             // Read from operand, potentially increments program counter
             self.operand.value(cpu);
-            // Don't have a better way of doing this at this time.
-            cpu.remaining_cycles = cpu.remaining_cycles.saturating_sub(4);
+            false
         }
     }
 }
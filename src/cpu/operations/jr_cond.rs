@@ -2,7 +2,7 @@ use std::fmt;
 
 use super::condition::Condition;
 use super::jr::Jr;
-use crate::cpu::operations::Operation;
+use crate::cpu::operations::{ConditionalOperation, Operation};
 use crate::cpu::Cpu;
 
 pub struct ConditionalJr {
@@ -15,15 +15,15 @@ impl ConditionalJr {
     }
 }
 
-impl Operation for ConditionalJr {
-    fn run(&self, cpu: &mut Cpu) {
+impl ConditionalOperation for ConditionalJr {
+    fn run(&self, cpu: &mut Cpu) -> bool {
         if self.cond.check(cpu) {
             Jr.run(cpu);
+            true
         } else {
             // Read byte to increment program counter
             cpu.read_u8();
-            // Remove cycles
-            cpu.remaining_cycles = cpu.remaining_cycles.saturating_sub(4);
+            false
         }
     }
 }
@@ -16,9 +16,9 @@ impl Pop {
 
 impl Operation for Pop {
     fn run(&self, cpu: &mut Cpu) {
-        let qq_l = cpu.mmu.get_byte(cpu.reg.sp()) as u16;
+        let qq_l = cpu.read_byte(cpu.reg.sp()) as u16;
         cpu.reg.incr_sp();
-        let qq_h = cpu.mmu.get_byte(cpu.reg.sp()) as u16;
+        let qq_h = cpu.read_byte(cpu.reg.sp()) as u16;
         cpu.reg.incr_sp();
         let val = (qq_h << 8) + qq_l;
         self.src.set_value(cpu, val);
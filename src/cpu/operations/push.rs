@@ -17,13 +17,9 @@ impl Push {
 impl Operation for Push {
     fn run(&self, cpu: &mut Cpu) {
         let qq = self.src.value(cpu);
-        let qq_h = (qq & 0x00FF) as u8;
-        let qq_l = (qq >> 8) as u8;
-
-        cpu.reg.decr_sp();
-        cpu.mmu.set_byte(cpu.reg.sp(), qq_h);
-        cpu.reg.decr_sp();
-        cpu.mmu.set_byte(cpu.reg.sp(), qq_l);
+        // SP is decremented in an internal M-cycle before the writes.
+        cpu.cycle();
+        cpu.push_u16(qq);
     }
 }
 
@@ -67,9 +63,9 @@ mod test {
         // PUSH BC
         Push::new(PushPopTarget::BC).run(&mut cpu);
 
-        // (FFFDh) ← B, (FFFCh) ← B, SP ← FFFCh
-        assert_eq!(cpu.mmu.get_byte(0xFFFD), cpu.reg.c());
-        assert_eq!(cpu.mmu.get_byte(0xFFFC), cpu.reg.b());
+        // (FFFDh) ← B, (FFFCh) ← C, SP ← FFFCh
+        assert_eq!(cpu.mmu.get_byte(0xFFFD), cpu.reg.b());
+        assert_eq!(cpu.mmu.get_byte(0xFFFC), cpu.reg.c());
         assert_eq!(cpu.reg.sp(), 0xFFFC);
     }
 }
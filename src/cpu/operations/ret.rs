@@ -8,8 +8,8 @@ pub struct Ret;
 impl Operation for Ret {
     fn run(&self, cpu: &mut Cpu) {
         let sp = cpu.reg.sp();
-        let l = cpu.mmu.get_byte(sp) as u16;
-        let h = cpu.mmu.get_byte(sp + 1) as u16;
+        let l = cpu.read_byte(sp) as u16;
+        let h = cpu.read_byte(sp + 1) as u16;
         cpu.reg.set_sp(sp + 2);
         cpu.reg.set_pc(l | h << 8);
     }
@@ -2,7 +2,7 @@ use std::fmt;
 
 use super::condition::Condition;
 use super::ret::Ret;
-use crate::cpu::operations::Operation;
+use crate::cpu::operations::{ConditionalOperation, Operation};
 use crate::cpu::Cpu;
 
 pub struct ConditionalRet {
@@ -15,13 +15,15 @@ impl ConditionalRet {
     }
 }
 
-impl Operation for ConditionalRet {
-    fn run(&self, cpu: &mut Cpu) {
+impl ConditionalOperation for ConditionalRet {
+    fn run(&self, cpu: &mut Cpu) -> bool {
+        // The condition is checked in an internal M-cycle.
+        cpu.cycle();
         if self.cond.check(cpu) {
             Ret.run(cpu);
+            true
         } else {
-            // Don't have a better way of doing this at this time.
-            cpu.remaining_cycles = cpu.remaining_cycles.saturating_sub(12);
+            false
         }
     }
 }
@@ -1,6 +1,5 @@
 use std::fmt;
 
-use super::ei::Ei;
 use super::ret::Ret;
 use crate::cpu::operations::Operation;
 use crate::cpu::Cpu;
@@ -10,7 +9,8 @@ pub struct Reti;
 impl Operation for Reti {
     fn run(&self, cpu: &mut Cpu) {
         Ret.run(cpu);
-        Ei.run(cpu);
+        // Unlike EI, RETI enables interrupts immediately.
+        cpu.ime = true;
     }
 }
 
@@ -14,11 +14,11 @@ impl Rst {
 }
 impl Operation for Rst {
     fn run(&self, cpu: &mut Cpu) {
-        let sp = cpu.reg.sp();
-        let l = self.dest as u16;
-        let h = 0;
-        cpu.reg.set_sp(sp + 2);
-        cpu.reg.set_pc(l | h << 8);
+        // Push the address of the next instruction, as with CALL, and jump to
+        // the fixed address 00HH.
+        cpu.cycle();
+        cpu.push_u16(cpu.reg.pc());
+        cpu.reg.set_pc(self.dest as u16);
     }
 }
 
@@ -80,4 +80,18 @@ mod test {
             "PC should returns to specified address"
         );
     }
+
+    #[test]
+    fn pushes_return_address() {
+        let mut cpu = with_ram(vec![0x00; 0xFFFF]);
+        cpu.reg.set_pc(0x8001);
+        cpu.reg.set_sp(0xFFFE);
+
+        Rst::new(0x18).run(&mut cpu);
+
+        assert_eq!(cpu.reg.pc(), 0x0018);
+        assert_eq!(cpu.reg.sp(), 0xFFFC);
+        assert_eq!(cpu.mmu.get_byte(0xFFFD), 0x80);
+        assert_eq!(cpu.mmu.get_byte(0xFFFC), 0x01);
+    }
 }
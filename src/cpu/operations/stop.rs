@@ -2,13 +2,43 @@ use std::fmt;
 
 use crate::cpu::operations::Operation;
 use crate::cpu::Cpu;
+use crate::speed_switch::{KEY1_ADDR, SWITCH_ARMED};
+use crate::timer::DIV_ADDR;
 
+/// Stop
+///
+/// Enters a low-power mode where the CPU and LCD are halted until one of the
+/// selected joypad input lines goes low. The instruction is followed by a
+/// padding byte, and entering STOP mode resets DIV.
+///
+/// On CGB, when a speed switch has been armed through KEY1, the CPU switches
+/// speed instead of stopping.
 pub struct Stop;
 
 impl Operation for Stop {
-    fn run(&self, _cpu: &mut Cpu) {
-        // TODO:
-        panic!("STOP operation not implemented")
+    fn run(&self, cpu: &mut Cpu) {
+        if cpu.joypad_line_low() {
+            // A button is already held, so STOP mode is not entered. If an
+            // interrupt is pending STOP acts as a single byte NOP, otherwise
+            // it consumes its padding byte and enters HALT.
+            if cpu.pending_interrupts() == 0 {
+                cpu.read_u8();
+                cpu.is_halted = true;
+            }
+            return;
+        }
+
+        // Any write to DIV resets it.
+        cpu.mmu.set_byte(DIV_ADDR, 0x00);
+
+        // Padding byte
+        cpu.read_u8();
+
+        if cpu.model.is_cgb() && cpu.mmu.get_byte(KEY1_ADDR) & SWITCH_ARMED != 0 {
+            cpu.switch_speed();
+            return;
+        }
+        cpu.is_stopped = true;
     }
 }
 
@@ -20,11 +50,98 @@ impl fmt::Display for Stop {
 
 #[cfg(test)]
 mod test {
+    use crate::interrupts::InterruptController;
+    use crate::joypad::P1_ADDR;
+    use crate::memory::address_space::AddressSpace;
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+    use crate::model::Model;
+    use crate::speed_switch::SpeedSwitch;
+
     use super::*;
 
+    fn with_io() -> Cpu {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(InterruptController::new());
+        mmu.add_address_space(SpeedSwitch::new());
+        mmu.add_address_space(Ram::new(0, 0xFF80));
+        // No buttons selected, so all input lines read high.
+        mmu.set_byte(P1_ADDR, 0xFF);
+        let mut cpu = Cpu::new(mmu);
+        cpu.reg.set_pc(0x0100);
+        cpu
+    }
+
     #[test]
     fn display_trait() {
         let op = Stop;
         assert_eq!(format!("{op}"), "STOP 0");
     }
+
+    #[test]
+    fn consumes_padding_byte() {
+        let mut cpu = with_io();
+        Stop.run(&mut cpu);
+        assert_eq!(cpu.reg.pc(), 0x0101);
+    }
+
+    #[test]
+    fn enters_stopped_state() {
+        let mut cpu = with_io();
+        Stop.run(&mut cpu);
+        assert!(cpu.is_stopped, "Stopped should be set");
+        assert!(!cpu.is_halted, "Halted should not be set");
+    }
+
+    #[test]
+    fn resets_divider_register() {
+        let mut cpu = with_io();
+        cpu.mmu.set_byte(DIV_ADDR, 0xAB);
+        Stop.run(&mut cpu);
+        assert_eq!(cpu.mmu.get_byte(DIV_ADDR), 0x00);
+    }
+
+    #[test]
+    fn enters_halt_instead_when_button_already_held() {
+        let mut cpu = with_io();
+        // Action buttons selected, A held.
+        cpu.mmu.set_byte(P1_ADDR, 0x1E);
+        Stop.run(&mut cpu);
+        assert!(!cpu.is_stopped, "Stopped should not be set");
+        assert!(cpu.is_halted, "Halted should be set");
+        assert_eq!(cpu.reg.pc(), 0x0101);
+    }
+
+    #[test]
+    fn keeps_divider_register_when_button_already_held() {
+        let mut cpu = with_io();
+        cpu.mmu.set_byte(DIV_ADDR, 0xAB);
+        cpu.mmu.set_byte(P1_ADDR, 0x1E);
+        Stop.run(&mut cpu);
+        assert_eq!(cpu.mmu.get_byte(DIV_ADDR), 0xAB);
+    }
+
+    #[test]
+    fn switches_speed_when_armed_on_cgb() {
+        let mut cpu = with_io();
+        cpu.set_model(Model::Cgb);
+        cpu.mmu.set_byte(KEY1_ADDR, SWITCH_ARMED);
+        Stop.run(&mut cpu);
+        assert!(!cpu.is_stopped, "Stopped should not be set");
+        assert!(cpu.double_speed());
+        assert_eq!(cpu.mmu.get_byte(KEY1_ADDR), 0xFE, "Switch disarmed");
+
+        cpu.mmu.set_byte(KEY1_ADDR, SWITCH_ARMED);
+        Stop.run(&mut cpu);
+        assert!(!cpu.double_speed());
+    }
+
+    #[test]
+    fn ignores_key1_on_dmg() {
+        let mut cpu = with_io();
+        cpu.mmu.set_byte(KEY1_ADDR, SWITCH_ARMED);
+        Stop.run(&mut cpu);
+        assert!(cpu.is_stopped);
+        assert!(!cpu.double_speed());
+    }
 }
@@ -18,7 +18,7 @@ impl Swap {
 impl Operation for Swap {
     fn run(&self, cpu: &mut Cpu) {
         let x = self.operand.value(cpu);
-        let swapped = x << 4 | x >> 4;
+        let swapped = x.rotate_right(4);
         self.operand.set_value(cpu, swapped);
         cpu.reg.set_z_flag(swapped == 0);
         cpu.reg.set_cy_flag(false);
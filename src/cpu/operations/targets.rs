@@ -23,7 +23,7 @@ impl ArithmeticTarget8Bit {
             Self::E => cpu.reg.e(),
             Self::H => cpu.reg.h(),
             Self::L => cpu.reg.l(),
-            Self::HLAddr => cpu.mmu.get_byte(cpu.reg.hl()),
+            Self::HLAddr => cpu.read_byte(cpu.reg.hl()),
             Self::D8 => cpu.read_u8(),
         }
     }
@@ -36,7 +36,7 @@ impl ArithmeticTarget8Bit {
             Self::E => cpu.reg.set_e(val),
             Self::H => cpu.reg.set_h(val),
             Self::L => cpu.reg.set_l(val),
-            Self::HLAddr => cpu.mmu.set_byte(cpu.reg.hl(), val),
+            Self::HLAddr => cpu.write_byte(cpu.reg.hl(), val),
             Self::D8 => panic!("Illegal Operation. Cannot set value."),
         };
     }
@@ -132,31 +132,31 @@ impl LdTarget {
             Self::E => cpu.reg.e(),
             Self::H => cpu.reg.h(),
             Self::L => cpu.reg.l(),
-            Self::BCAddr => cpu.mmu.get_byte(cpu.reg.bc()),
-            Self::DEAddr => cpu.mmu.get_byte(cpu.reg.de()),
-            Self::HLAddr => cpu.mmu.get_byte(cpu.reg.hl()),
+            Self::BCAddr => cpu.read_byte(cpu.reg.bc()),
+            Self::DEAddr => cpu.read_byte(cpu.reg.de()),
+            Self::HLAddr => cpu.read_byte(cpu.reg.hl()),
             Self::HLIAddr => {
                 let hl = cpu.reg.hl();
                 cpu.reg.incr_hl();
-                cpu.mmu.get_byte(hl)
+                cpu.read_byte(hl)
             }
             Self::HLDAddr => {
                 let hl = cpu.reg.hl();
                 cpu.reg.decr_hl();
-                cpu.mmu.get_byte(hl)
+                cpu.read_byte(hl)
             }
             Self::D8 => cpu.read_u8(),
             Self::A16 => {
                 let addr = cpu.read_u16();
-                cpu.mmu.get_byte(addr)
+                cpu.read_byte(addr)
             }
             Self::CAddr => {
                 let c = cpu.reg.c() as u16;
-                cpu.mmu.get_byte(c + 0xFF00)
+                cpu.read_byte(c + 0xFF00)
             }
             Self::A8 => {
                 let x = cpu.read_u8() as u16;
-                cpu.mmu.get_byte(x + 0xFF00)
+                cpu.read_byte(x + 0xFF00)
             }
         }
     }
@@ -170,31 +170,31 @@ impl LdTarget {
             Self::E => cpu.reg.set_e(val),
             Self::H => cpu.reg.set_h(val),
             Self::L => cpu.reg.set_l(val),
-            Self::BCAddr => cpu.mmu.set_byte(cpu.reg.bc(), val),
-            Self::DEAddr => cpu.mmu.set_byte(cpu.reg.de(), val),
-            Self::HLAddr => cpu.mmu.set_byte(cpu.reg.hl(), val),
+            Self::BCAddr => cpu.write_byte(cpu.reg.bc(), val),
+            Self::DEAddr => cpu.write_byte(cpu.reg.de(), val),
+            Self::HLAddr => cpu.write_byte(cpu.reg.hl(), val),
             Self::HLIAddr => {
                 let hl = cpu.reg.hl();
                 cpu.reg.incr_hl();
-                cpu.mmu.set_byte(hl, val);
+                cpu.write_byte(hl, val);
             }
             Self::HLDAddr => {
                 let hl = cpu.reg.hl();
                 cpu.reg.decr_hl();
-                cpu.mmu.set_byte(hl, val);
+                cpu.write_byte(hl, val);
             }
             Self::D8 => panic!("Illegal Operation. Cannot set value."),
             Self::A16 => {
                 let addr = cpu.read_u16();
-                cpu.mmu.set_byte(addr, val);
+                cpu.write_byte(addr, val);
             }
             Self::CAddr => {
                 let c = cpu.reg.c() as u16;
-                cpu.mmu.set_byte(c + 0xFF00, val);
+                cpu.write_byte(c + 0xFF00, val);
             }
             Self::A8 => {
                 let x = cpu.read_u8() as u16;
-                cpu.mmu.set_byte(x + 0xFF00, val);
+                cpu.write_byte(x + 0xFF00, val);
             }
         };
     }
@@ -261,8 +261,8 @@ impl Ld16Target {
                 let nn = cpu.read_u16();
                 let upper = (val >> 8) as u8;
                 let lower = (val & 0x00FF) as u8;
-                cpu.mmu.set_byte(nn, lower);
-                cpu.mmu.set_byte(nn + 1, upper);
+                cpu.write_byte(nn, lower);
+                cpu.write_byte(nn + 1, upper);
             }
         };
     }
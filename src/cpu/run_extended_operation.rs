@@ -90,7 +90,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x43 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::E), 8),
         0x44 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::H), 8),
         0x45 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::L), 8),
-        0x46 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::HLAddr), 16),
+        0x46 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::HLAddr), 12),
         0x47 => cpu.run_operation(Bit::new(0, ArithmeticTarget8Bit::A), 8),
         0x48 => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::B), 8),
         0x49 => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::C), 8),
@@ -98,7 +98,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x4B => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::E), 8),
         0x4C => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::H), 8),
         0x4D => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::L), 8),
-        0x4E => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::HLAddr), 16),
+        0x4E => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::HLAddr), 12),
         0x4F => cpu.run_operation(Bit::new(1, ArithmeticTarget8Bit::A), 8),
 
         0x50 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::B), 8),
@@ -107,7 +107,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x53 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::E), 8),
         0x54 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::H), 8),
         0x55 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::L), 8),
-        0x56 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::HLAddr), 16),
+        0x56 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::HLAddr), 12),
         0x57 => cpu.run_operation(Bit::new(2, ArithmeticTarget8Bit::A), 8),
         0x58 => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::B), 8),
         0x59 => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::C), 8),
@@ -115,7 +115,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x5B => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::E), 8),
         0x5C => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::H), 8),
         0x5D => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::L), 8),
-        0x5E => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::HLAddr), 16),
+        0x5E => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::HLAddr), 12),
         0x5F => cpu.run_operation(Bit::new(3, ArithmeticTarget8Bit::A), 8),
 
         0x60 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::B), 8),
@@ -124,7 +124,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x63 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::E), 8),
         0x64 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::H), 8),
         0x65 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::L), 8),
-        0x66 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::HLAddr), 16),
+        0x66 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::HLAddr), 12),
         0x67 => cpu.run_operation(Bit::new(4, ArithmeticTarget8Bit::A), 8),
         0x68 => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::B), 8),
         0x69 => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::C), 8),
@@ -132,7 +132,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x6B => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::E), 8),
         0x6C => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::H), 8),
         0x6D => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::L), 8),
-        0x6E => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::HLAddr), 16),
+        0x6E => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::HLAddr), 12),
         0x6F => cpu.run_operation(Bit::new(5, ArithmeticTarget8Bit::A), 8),
 
         0x70 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::B), 8),
@@ -141,7 +141,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x73 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::E), 8),
         0x74 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::H), 8),
         0x75 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::L), 8),
-        0x76 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::HLAddr), 16),
+        0x76 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::HLAddr), 12),
         0x77 => cpu.run_operation(Bit::new(6, ArithmeticTarget8Bit::A), 8),
         0x78 => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::B), 8),
         0x79 => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::C), 8),
@@ -149,7 +149,7 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0x7B => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::E), 8),
         0x7C => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::H), 8),
         0x7D => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::L), 8),
-        0x7E => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::HLAddr), 16),
+        0x7E => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::HLAddr), 12),
         0x7F => cpu.run_operation(Bit::new(7, ArithmeticTarget8Bit::A), 8),
 
         0x80 => cpu.run_operation(Res::new(0, ArithmeticTarget8Bit::B), 8),
@@ -289,3 +289,33 @@ pub fn run_extended_operation(cpu: &mut Cpu, op_code: u8) {
         0xFF => cpu.run_operation(Set::new(7, ArithmeticTarget8Bit::A), 8),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+
+    use super::*;
+
+    #[test]
+    fn op_codes_take_published_cycles() {
+        for op_code in 0..=0xFF {
+            // Register operands take 8 cycles including the prefix. (HL)
+            // operands take 16, except BIT which only reads it and takes 12.
+            let expected = match (op_code, op_code & 0x07) {
+                (_, 0x06) if (0x40..0x80).contains(&op_code) => 12,
+                (_, 0x06) => 16,
+                _ => 8,
+            };
+
+            let mut mmu = Mmu::new();
+            mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+            let mut cpu = Cpu::new(mmu);
+            cpu.mmu.set_byte(0xC000, 0xCB);
+            cpu.mmu.set_byte(0xC001, op_code);
+            cpu.reg.set_pc(0xC000);
+            cpu.reg.set_hl(0xC100);
+            assert_eq!(cpu.step(), expected, "CB {op_code:#04X}");
+        }
+    }
+}
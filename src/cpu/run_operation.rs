@@ -85,7 +85,7 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0x1E => cpu.run_operation(Ld::new(LdTarget::E, LdTarget::D8), 8),
         0x1F => cpu.run_operation(Rra, 4),
 
-        0x20 => cpu.run_operation(ConditionalJr::new(Condition::NZ), 12),
+        0x20 => cpu.run_conditional_operation(ConditionalJr::new(Condition::NZ), 12, 8),
         0x21 => cpu.run_operation(Ld16::new(Ld16Target::HL, Ld16Target::D16), 12),
         0x22 => cpu.run_operation(Ld::new(LdTarget::HLIAddr, LdTarget::A), 8),
         0x23 => cpu.run_operation(Inc16::new(ArithmeticTarget16Bit::HL), 8),
@@ -93,7 +93,7 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0x25 => cpu.run_operation(Dec::new(ArithmeticTarget8Bit::H), 4),
         0x26 => cpu.run_operation(Ld::new(LdTarget::H, LdTarget::D8), 8),
         0x27 => cpu.run_operation(Daa, 4),
-        0x28 => cpu.run_operation(ConditionalJr::new(Condition::Z), 12),
+        0x28 => cpu.run_conditional_operation(ConditionalJr::new(Condition::Z), 12, 8),
         0x29 => cpu.run_operation(AddHl::new(ArithmeticTarget16Bit::HL), 8),
         0x2A => cpu.run_operation(Ld::new(LdTarget::A, LdTarget::HLIAddr), 8),
         0x2B => cpu.run_operation(Dec16::new(ArithmeticTarget16Bit::HL), 8),
@@ -102,7 +102,7 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0x2E => cpu.run_operation(Ld::new(LdTarget::L, LdTarget::D8), 8),
         0x2F => cpu.run_operation(Cpl, 4),
 
-        0x30 => cpu.run_operation(ConditionalJr::new(Condition::NC), 12),
+        0x30 => cpu.run_conditional_operation(ConditionalJr::new(Condition::NC), 12, 8),
         0x31 => cpu.run_operation(Ld16::new(Ld16Target::SP, Ld16Target::D16), 12),
         0x32 => cpu.run_operation(Ld::new(LdTarget::HLDAddr, LdTarget::A), 8),
         0x33 => cpu.run_operation(Inc16::new(ArithmeticTarget16Bit::SP), 8),
@@ -110,7 +110,7 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0x35 => cpu.run_operation(Dec::new(ArithmeticTarget8Bit::HLAddr), 12),
         0x36 => cpu.run_operation(Ld::new(LdTarget::HLAddr, LdTarget::D8), 12),
         0x37 => cpu.run_operation(Scf, 4),
-        0x38 => cpu.run_operation(ConditionalJr::new(Condition::C), 12),
+        0x38 => cpu.run_conditional_operation(ConditionalJr::new(Condition::C), 12, 8),
         0x39 => cpu.run_operation(AddHl::new(ArithmeticTarget16Bit::SP), 8),
         0x3A => cpu.run_operation(Ld::new(LdTarget::A, LdTarget::HLIAddr), 8),
         0x3B => cpu.run_operation(Dec16::new(ArithmeticTarget16Bit::SP), 8),
@@ -255,36 +255,52 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0xBE => cpu.run_operation(Cp::new(ArithmeticTarget8Bit::HLAddr), 8),
         0xBF => cpu.run_operation(Cp::new(ArithmeticTarget8Bit::A), 4),
 
-        0xC0 => cpu.run_operation(ConditionalRet::new(Condition::NZ), 20),
+        0xC0 => cpu.run_conditional_operation(ConditionalRet::new(Condition::NZ), 20, 8),
         0xC1 => cpu.run_operation(Pop::new(PushPopTarget::BC), 12),
-        0xC2 => cpu.run_operation(ConditionalJp::new(Condition::NZ, AddressTarget::A16), 16),
+        0xC2 => cpu.run_conditional_operation(
+            ConditionalJp::new(Condition::NZ, AddressTarget::A16),
+            16,
+            12,
+        ),
         0xC3 => cpu.run_operation(Jp::new(AddressTarget::A16), 16),
-        0xC4 => cpu.run_operation(ConditionalCall::new(Condition::NZ), 24),
+        0xC4 => cpu.run_conditional_operation(ConditionalCall::new(Condition::NZ), 24, 12),
         0xC5 => cpu.run_operation(Push::new(PushPopTarget::BC), 16),
         0xC6 => cpu.run_operation(Add::new(ArithmeticTarget8Bit::D8), 8),
         0xC7 => cpu.run_operation(Rst::new(0x00), 16),
-        0xC8 => cpu.run_operation(ConditionalRet::new(Condition::Z), 20),
+        0xC8 => cpu.run_conditional_operation(ConditionalRet::new(Condition::Z), 20, 8),
         0xC9 => cpu.run_operation(Ret, 16),
-        0xCA => cpu.run_operation(ConditionalJp::new(Condition::Z, AddressTarget::A16), 16),
+        0xCA => cpu.run_conditional_operation(
+            ConditionalJp::new(Condition::Z, AddressTarget::A16),
+            16,
+            12,
+        ),
         0xCB => cpu.run_operation(PrefixCB, 4),
-        0xCC => cpu.run_operation(ConditionalCall::new(Condition::Z), 24),
+        0xCC => cpu.run_conditional_operation(ConditionalCall::new(Condition::Z), 24, 12),
         0xCD => cpu.run_operation(Call, 24),
         0xCE => cpu.run_operation(Adc::new(ArithmeticTarget8Bit::D8), 8),
         0xCF => cpu.run_operation(Rst::new(0x08), 16),
 
-        0xD0 => cpu.run_operation(ConditionalRet::new(Condition::NC), 20),
+        0xD0 => cpu.run_conditional_operation(ConditionalRet::new(Condition::NC), 20, 8),
         0xD1 => cpu.run_operation(Pop::new(PushPopTarget::DE), 12),
-        0xD2 => cpu.run_operation(ConditionalJp::new(Condition::NC, AddressTarget::A16), 16),
+        0xD2 => cpu.run_conditional_operation(
+            ConditionalJp::new(Condition::NC, AddressTarget::A16),
+            16,
+            12,
+        ),
 
-        0xD4 => cpu.run_operation(ConditionalCall::new(Condition::NC), 24),
+        0xD4 => cpu.run_conditional_operation(ConditionalCall::new(Condition::NC), 24, 12),
         0xD5 => cpu.run_operation(Push::new(PushPopTarget::DE), 16),
         0xD6 => cpu.run_operation(Sub::new(ArithmeticTarget8Bit::D8), 8),
         0xD7 => cpu.run_operation(Rst::new(0x10), 16),
-        0xD8 => cpu.run_operation(ConditionalRet::new(Condition::C), 20),
+        0xD8 => cpu.run_conditional_operation(ConditionalRet::new(Condition::C), 20, 8),
         0xD9 => cpu.run_operation(Reti, 16),
-        0xDA => cpu.run_operation(ConditionalJp::new(Condition::C, AddressTarget::A16), 16),
+        0xDA => cpu.run_conditional_operation(
+            ConditionalJp::new(Condition::C, AddressTarget::A16),
+            16,
+            12,
+        ),
 
-        0xDC => cpu.run_operation(ConditionalCall::new(Condition::C), 24),
+        0xDC => cpu.run_conditional_operation(ConditionalCall::new(Condition::C), 24, 12),
 
         0xDE => cpu.run_operation(Sbc::new(ArithmeticTarget8Bit::D8), 8),
         0xDF => cpu.run_operation(Rst::new(0x18), 16),
@@ -298,7 +314,7 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0xE7 => cpu.run_operation(Rst::new(0x20), 16),
         0xE8 => cpu.run_operation(AddSp, 16),
         0xE9 => cpu.run_operation(Jp::new(AddressTarget::HLAddr), 4),
-        0xEA => cpu.run_operation(Ld::new(LdTarget::A, LdTarget::A16), 16),
+        0xEA => cpu.run_operation(Ld::new(LdTarget::A16, LdTarget::A), 16),
 
         0xEE => cpu.run_operation(Xor::new(ArithmeticTarget8Bit::D8), 8),
         0xEF => cpu.run_operation(Rst::new(0x28), 16),
@@ -313,12 +329,113 @@ pub fn run_operation(cpu: &mut Cpu, op_code: u8) {
         0xF7 => cpu.run_operation(Rst::new(0x30), 16),
         0xF8 => cpu.run_operation(LdHlSp, 12),
         0xF9 => cpu.run_operation(Ld16::new(Ld16Target::SP, Ld16Target::HL), 8),
-        0xFA => cpu.run_operation(Ld::new(LdTarget::A16, LdTarget::A), 16),
+        0xFA => cpu.run_operation(Ld::new(LdTarget::A, LdTarget::A16), 16),
         0xFB => cpu.run_operation(Ei, 4),
 
         0xFE => cpu.run_operation(Cp::new(ArithmeticTarget8Bit::D8), 8),
         0xFF => cpu.run_operation(Rst::new(0x38), 16),
 
-        _ => panic!("No op code corresponding to {op_code:#04X}"),
+        0xD3 | 0xDB | 0xDD | 0xE3 | 0xE4 | 0xEB | 0xEC | 0xED | 0xF4 | 0xFC | 0xFD => {
+            cpu.lock_up(op_code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+
+    use super::*;
+
+    // Cycles for each opcode, from the published opcode table. Conditional
+    // instructions list the cycles for when the branch isn't taken, and 0
+    // marks an illegal opcode.
+    #[rustfmt::skip]
+    const CYCLES: [u8; 256] = [
+        4, 12,  8,  8,  4,  4,  8,  4, 20,  8,  8,  8,  4,  4,  8,  4, // 0x
+        4, 12,  8,  8,  4,  4,  8,  4, 12,  8,  8,  8,  4,  4,  8,  4, // 1x
+        8, 12,  8,  8,  4,  4,  8,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 2x
+        8, 12,  8,  8, 12, 12, 12,  4,  8,  8,  8,  8,  4,  4,  8,  4, // 3x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 4x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 5x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 6x
+        8,  8,  8,  8,  8,  8,  4,  8,  4,  4,  4,  4,  4,  4,  8,  4, // 7x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 8x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // 9x
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Ax
+        4,  4,  4,  4,  4,  4,  8,  4,  4,  4,  4,  4,  4,  4,  8,  4, // Bx
+        8, 12, 12, 16, 12, 16,  8, 16,  8, 16, 12,  4, 12, 24,  8, 16, // Cx
+        8, 12, 12,  0, 12, 16,  8, 16,  8, 16, 12,  0, 12,  0,  8, 16, // Dx
+       12, 12,  8,  0,  0, 16,  8, 16, 16,  4, 16,  0,  0,  0,  8, 16, // Ex
+       12, 12,  8,  4,  0, 16,  8, 16, 12,  8, 16,  4,  0,  0,  8, 16, // Fx
+    ];
+
+    /// Cycles for conditional instructions when the branch is taken.
+    fn taken_cycles(op_code: u8) -> Option<u8> {
+        match op_code {
+            0x20 | 0x28 | 0x30 | 0x38 => Some(12),
+            0xC0 | 0xC8 | 0xD0 | 0xD8 => Some(20),
+            0xC2 | 0xCA | 0xD2 | 0xDA => Some(16),
+            0xC4 | 0xCC | 0xD4 | 0xDC => Some(24),
+            _ => None,
+        }
+    }
+
+    fn cpu_with_op_code(op_code: u8, flags: u8) -> Cpu {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        let mut cpu = Cpu::new(mmu);
+        cpu.mmu.set_byte(0xC000, op_code);
+        cpu.reg.set_pc(0xC000);
+        cpu.reg.set_sp(0xD000);
+        cpu.reg.set_hl(0xC100);
+        cpu.reg.set_af(flags as u16);
+        cpu
+    }
+
+    #[test]
+    fn op_codes_take_published_cycles() {
+        for op_code in 0..=0xFF {
+            // STOP suspends the CPU, and the CB prefix is covered by the
+            // extended operation table.
+            if CYCLES[op_code as usize] == 0 || op_code == 0x10 || op_code == 0xCB {
+                continue;
+            }
+            // Z and C conditions have bit 3 set, NZ and NC have it clear.
+            let taken_when_set = op_code & 0x08 != 0;
+            for flags in [0x00, 0xF0] {
+                let expected = match taken_cycles(op_code) {
+                    Some(taken) if (flags != 0) == taken_when_set => taken,
+                    _ => CYCLES[op_code as usize],
+                };
+
+                let mut cpu = cpu_with_op_code(op_code, flags);
+                assert_eq!(
+                    cpu.step(),
+                    expected,
+                    "{op_code:#04X} with flags {flags:#04X}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ld_a16_stores_and_loads_a() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        let mut cpu = Cpu::new(mmu);
+        // LD (C123),A then LD A,(C124)
+        for (offset, byte) in [0xEA, 0x23, 0xC1, 0xFA, 0x24, 0xC1].into_iter().enumerate() {
+            cpu.mmu.set_byte(0xC000 + offset as u16, byte);
+        }
+        cpu.mmu.set_byte(0xC124, 0x56);
+        cpu.reg.set_pc(0xC000);
+        cpu.reg.set_a(0x12);
+
+        cpu.step();
+        assert_eq!(cpu.mmu.get_byte(0xC123), 0x12);
+        cpu.step();
+        assert_eq!(cpu.reg.a(), 0x56);
     }
 }
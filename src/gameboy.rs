@@ -1,58 +1,423 @@
-use crate::cpu::Cpu;
+use std::cell::{Ref, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::apu::{Apu, SampleBuffer, DEFAULT_SAMPLE_RATE};
+use crate::clock::{Clock, Speed, CYCLES_PER_FRAME};
+use crate::cpu::{Cpu, IllegalOpcode, IllegalOpcodeAction};
+use crate::input::InputSource;
+use crate::interrupts::InterruptController;
+use crate::joypad::{Button, Joypad};
+use crate::memory::address_space::AddressSpace;
+use crate::memory::boot_rom::load_boot_rom;
 use crate::memory::cartridge::Cartridge;
+use crate::memory::hdma::Hdma;
+use crate::memory::io_registers::IoRegisters;
+use crate::memory::mbc::RtcClock;
 use crate::memory::mmu::Mmu;
-use crate::memory::ram::Ram;
+use crate::memory::oam_dma::OamDma;
+use crate::memory::work_ram::WorkRam;
+use crate::model::Model;
+use crate::ppu::{Ppu, Renderer, BCPD_ADDR, BCPS_ADDR};
+use crate::serial::{LocalLink, Serial, SerialLink};
+use crate::speed_switch::SpeedSwitch;
+use crate::timer::Timer;
+
+// Internal timer counter when the DMG boot ROM hands over to the cartridge.
+const POST_BOOT_TIMER_COUNTER: u16 = 0xABCC;
+
+// I/O registers as left by the DMG boot ROM.
+const POST_BOOT_IO: [(u16, u8); 31] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF0F, 0xE1), // IF
+    // The APU must be powered on before its other registers can be written.
+    (0xFF26, 0xF1), // NR52
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF21, 0x00), // NR42
+    (0xFF22, 0x00), // NR43
+    (0xFF23, 0xBF), // NR44
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF40, 0x91), // LCDC
+    (0xFF42, 0x00), // SCY
+    (0xFF43, 0x00), // SCX
+    (0xFF45, 0x00), // LYC
+    (0xFF47, 0xFC), // BGP
+    (0xFF4A, 0x00), // WY
+    (0xFF4B, 0x00), // WX
+    (0xFF50, 0x01), // Boot ROM disable
+];
 
-static FOUR_KB: u16 = 0x1000;
-static EIGHT_KB: u16 = 0x2000;
+// RGB555 white, which the CGB boot ROM fills the BG palettes with.
+const POST_BOOT_BG_COLOR: u16 = 0x7FFF;
+
+// Battery-backed RAM and audio recordings are flushed to disk roughly once
+// per emulated second.
+const SAVE_INTERVAL_FRAMES: u64 = 60;
+
+/// Settings used when building a `GameBoy`.
+#[derive(Default)]
+pub struct Options {
+    /// Rendering strategy used by the PPU, trading accuracy for speed.
+    pub renderer: Renderer,
+    /// Where battery-backed RAM is saved, defaulting to the ROM path with a
+    /// `.sav` extension.
+    pub save_path: Option<PathBuf>,
+    /// Time source for cartridges with a real time clock.
+    pub rtc_clock: RtcClock,
+    /// Boot ROM dump to run in place of the embedded one.
+    pub boot_rom: Option<PathBuf>,
+    /// Start at 0x0100 with the state left by the boot ROM, without running it.
+    pub skip_boot: bool,
+    /// Emulation speed relative to real hardware.
+    pub speed: Speed,
+    pub illegal_opcode_action: IllegalOpcodeAction,
+    /// Audio output rate, or `DEFAULT_SAMPLE_RATE` if not set.
+    pub sample_rate: Option<u32>,
+    /// Hardware to emulate, or the one the cartridge asks for if not set.
+    /// Only the DMG boot ROM is embedded, so CGB mode skips the boot ROM
+    /// unless `boot_rom` is given.
+    pub model: Option<Model>,
+}
 
 pub struct GameBoy {
+    model: Model,
     cpu: Cpu,
+    ppu: Rc<RefCell<Ppu>>,
+    joypad: Rc<RefCell<Joypad>>,
+    serial: Rc<RefCell<Serial>>,
+    apu: Rc<RefCell<Apu>>,
+    cartridge: Rc<RefCell<Cartridge>>,
+    clock: Clock,
+    input_sources: Vec<Box<dyn InputSource>>,
+    frame: u64,
+    // Set from another thread, e.g. a signal handler, to make `run` return.
+    quit: Arc<AtomicBool>,
 }
 
 impl GameBoy {
     pub fn load_cartridge(filename: &str) -> std::io::Result<Self> {
-        let game_rom = Cartridge::load(filename)?;
+        Self::load_cartridge_with_options(filename, Options::default())
+    }
 
-        let mut mmu = Mmu::new();
+    pub fn load_cartridge_with_options(filename: &str, options: Options) -> std::io::Result<Self> {
+        let save_path = options
+            .save_path
+            .unwrap_or_else(|| Path::new(filename).with_extension("sav"));
+        let mut cartridge = Cartridge::load_with_save_path(filename, save_path)?;
+        cartridge.set_rtc_clock(options.rtc_clock);
+        if let Some(path) = &options.boot_rom {
+            cartridge.replace_boot_rom(load_boot_rom(path)?);
+        }
+        let model = options
+            .model
+            .unwrap_or_else(|| Model::for_cartridge(cartridge.header()));
+        let skip_boot = options.skip_boot || (model.is_cgb() && options.boot_rom.is_none());
+        log::info!("Running in {} mode", model);
+        let cartridge = Rc::new(RefCell::new(cartridge));
 
-        // 8000-9FFF: 8 KiB Video RAM (VRAM)
-        mmu.add_address_space(Ram::new(0x8000, EIGHT_KB));
+        let mut mmu = Mmu::new();
 
+        // 0000-3FFF: 16 KiB ROM bank 00
+        // 4000-7FFF: 16 KiB ROM Bank 01~NN
         // A000-BFFF: 8 KiB External RAM
-        mmu.add_address_space(Ram::new(0xA000, EIGHT_KB));
+        // FF50: Boot ROM disable
+        mmu.add_address_space(cartridge.clone());
 
-        // C000-CFFF: 4 KiB Work RAM (WRAM)
-        mmu.add_address_space(Ram::new(0xC000, FOUR_KB));
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let ppu = Rc::new(RefCell::new(Ppu::with_model(
+            interrupts.clone(),
+            options.renderer,
+            model,
+        )));
 
-        // D000-DFFF: 4 KiB Work RAM (WRAM)
-        mmu.add_address_space(Ram::new(0xD000, FOUR_KB));
+        // 8000-9FFF: 8 KiB Video RAM (VRAM), switchable on CGB
+        // FE00-FE9F: Sprite attribute table (OAM)
+        // FEA0-FEFF: Not Usable
+        // FF40-FF45, FF47-FF4B: LCD registers
+        // FF4F, FF68-FF6B: CGB VRAM bank and palettes
+        mmu.add_address_space(ppu.clone());
 
+        // C000-CFFF: 4 KiB Work RAM (WRAM)
+        // D000-DFFF: 4 KiB Work RAM (WRAM), switchable on CGB
         // E000-FDFF: Mirror of C000~DDFF (ECHO RAM)
+        // FF70: CGB WRAM bank
+        mmu.add_work_ram(WorkRam::with_model(model));
 
-        // FE00-FE9F: Sprite attribute table (OAM)
-        mmu.add_address_space(Ram::new(0xFE00, 0x00A0));
+        // FF0F: Interrupt Flag register (IF)
+        // FFFF: Interrupt Enable register (IE)
+        mmu.add_address_space(interrupts.clone());
 
-        // FEA0-FEFF: Not Usable
+        // FF00: Joypad
+        let joypad = Rc::new(RefCell::new(Joypad::new(interrupts.clone())));
+        mmu.add_address_space(joypad.clone());
+
+        // FF01-FF02: Serial transfer
+        let serial = Rc::new(RefCell::new(Serial::new(interrupts.clone())));
+        mmu.add_address_space(serial.clone());
+
+        // FF04-FF07: Timer
+        let mut timer = Timer::new(interrupts);
+        if skip_boot {
+            timer.set_counter(POST_BOOT_TIMER_COUNTER);
+        }
+        mmu.add_address_space(timer);
+
+        // FF10-FF26: Sound registers
+        // FF30-FF3F: Wave pattern RAM
+        let sample_rate = options.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+        let apu = Rc::new(RefCell::new(Apu::new(sample_rate)));
+        mmu.add_address_space(apu.clone());
+
+        // FF46: OAM DMA source address and start
+        mmu.add_oam_dma(Rc::new(RefCell::new(OamDma::new(ppu.clone()))));
+
+        if model.is_cgb() {
+            // FF4D: Speed switch (KEY1)
+            mmu.add_address_space(SpeedSwitch::new());
+
+            // FF51-FF55: VRAM DMA (HDMA)
+            mmu.add_hdma(Rc::new(RefCell::new(Hdma::new(ppu.clone()))));
+        }
 
         // FF00-FF7F: I/O Registers
-        mmu.add_address_space(Ram::new(0xFF00, 0x007F));
+        mmu.add_address_space(IoRegisters::new());
 
         // FF80-FFFE: High RAM (HRAM)
-        mmu.add_address_space(Ram::new(0xFF80, 0x007F));
+        mmu.add_hram();
 
-        // FFFF-FFFF: Interrupt Enable register (IE)
+        if skip_boot {
+            for (addr, byte) in POST_BOOT_IO {
+                mmu.set_byte(addr, byte);
+            }
+            if model.is_cgb() {
+                // Auto-incrementing from the first color of palette 0.
+                mmu.set_byte(BCPS_ADDR, 0x80);
+                for _ in 0..32 {
+                    for byte in POST_BOOT_BG_COLOR.to_le_bytes() {
+                        mmu.set_byte(BCPD_ADDR, byte);
+                    }
+                }
+            }
+        }
 
-        // 0000-3FFF: 16 KiB ROM bank 00
-        // 4000-7FFF: 16 KiB ROM Bank 01~NN
-        mmu.add_address_space(game_rom);
+        let mut cpu = Cpu::new(mmu);
+        cpu.set_model(model);
+        cpu.set_illegal_opcode_action(options.illegal_opcode_action);
+        if skip_boot {
+            cpu.skip_boot_rom();
+        }
+
+        Ok(GameBoy {
+            model,
+            cpu,
+            ppu,
+            joypad,
+            serial,
+            apu,
+            cartridge,
+            clock: Clock::new(options.speed),
+            input_sources: Vec::new(),
+            frame: 0,
+            quit: Arc::new(AtomicBool::new(false)),
+        })
+    }
 
-        let cpu = Cpu::new(mmu);
+    /// Runs until a quit is requested through `quit_handle`, or the CPU
+    /// breaks on an illegal opcode if set to do so.
+    pub fn run(&mut self) -> Result<(), IllegalOpcode> {
+        while !self.quit.load(Ordering::Relaxed) {
+            let result = self.run_frame();
+            let apu = &self.apu;
+            self.clock
+                .sync_audio(CYCLES_PER_FRAME, || apu.borrow().buffered());
+            if result.is_err() || self.frame.is_multiple_of(SAVE_INTERVAL_FRAMES) {
+                if let Err(error) = self.save() {
+                    log::error!("Failed to write save file: {}", error);
+                }
+                if let Err(error) = self.apu.borrow_mut().update_recording() {
+                    log::error!("Failed to update audio recording: {}", error);
+                }
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Emulates up to `frames` frames as fast as possible, stopping early if
+    /// a quit is requested through `quit_handle`.
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), IllegalOpcode> {
+        for _ in 0..frames {
+            if self.quit.load(Ordering::Relaxed) {
+                break;
+            }
+            self.run_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Flag which makes `run` or `run_frames` return after the current frame
+    /// once set.
+    pub fn quit_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.quit)
+    }
+
+    /// Emulates one frame's worth of cycles as fast as possible.
+    pub fn run_frame(&mut self) -> Result<(), IllegalOpcode> {
+        for source in &mut self.input_sources {
+            for event in source.poll(self.frame) {
+                self.joypad
+                    .borrow_mut()
+                    .set_button(event.button, event.pressed);
+            }
+        }
+        self.frame += 1;
+        self.cpu.run_for(CYCLES_PER_FRAME)
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.joypad.borrow_mut().set_button(button, pressed);
+    }
 
-        Ok(GameBoy { cpu })
+    /// Connects a link cable to the serial port.
+    pub fn connect_serial<Link: SerialLink + 'static>(&mut self, link: Link) {
+        self.serial.borrow_mut().connect(Box::new(link));
     }
 
-    pub fn run(&mut self) {
-        self.cpu.run();
+    /// Connects a link cable between this and another `GameBoy`, which must
+    /// be run alternately on the same thread.
+    pub fn link(&mut self, other: &mut GameBoy) {
+        let (first, second) = LocalLink::pair();
+        self.connect_serial(first);
+        other.connect_serial(second);
+    }
+
+    /// Moves the audio samples produced so far onto the end of `output`.
+    pub fn drain_samples(&mut self, output: &mut Vec<[f32; 2]>) {
+        self.apu.borrow_mut().drain_samples(output);
+    }
+
+    /// Buffer the audio output is resampled into, for an audio callback on
+    /// another thread to drain, e.g. at `Speed::Audio`.
+    pub fn sample_buffer(&self) -> Arc<Mutex<SampleBuffer>> {
+        self.apu.borrow().sample_buffer()
+    }
+
+    /// Starts recording the audio output to a WAV file, and with
+    /// `split_channels` each channel to its own file alongside it.
+    pub fn start_recording(&mut self, path: &Path, split_channels: bool) -> std::io::Result<()> {
+        self.apu.borrow_mut().start_recording(path, split_channels)
+    }
+
+    /// Completes the audio recording, if one was started.
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        self.apu.borrow_mut().stop_recording()
+    }
+
+    /// Adds a source of button presses, polled before each frame.
+    pub fn add_input_source<Source: InputSource + 'static>(&mut self, source: Source) {
+        self.input_sources.push(Box::new(source));
+    }
+
+    /// The illegal opcode which locked up the CPU, if any.
+    pub fn locked_up(&self) -> Option<IllegalOpcode> {
+        self.cpu.locked_up()
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.clock.set_speed(speed);
+    }
+
+    /// Speed achieved over the last second, as a percentage of real hardware.
+    pub fn speed_percentage(&self) -> f64 {
+        self.clock.speed_percentage()
+    }
+
+    /// Flushes battery-backed cartridge RAM to the save file.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.cartridge.borrow_mut().save()
+    }
+
+    /// Hardware being emulated.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Shades (0-3) of the last frame rendered by the PPU. In CGB mode these
+    /// are color indices within each pixel's palette, so `color_framebuffer`
+    /// should be used instead.
+    pub fn framebuffer(&self) -> Ref<'_, [u8]> {
+        Ref::map(self.ppu.borrow(), |ppu| ppu.framebuffer())
+    }
+
+    /// RGB555 colors of the last frame rendered by the PPU, in either mode.
+    pub fn color_framebuffer(&self) -> Ref<'_, [u16]> {
+        Ref::map(self.ppu.borrow(), |ppu| ppu.color_framebuffer())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::serial::SerialCapture;
+
+    // Sends DIV out of the serial port, then loops forever.
+    const SEND_DIV: [u8; 10] = [
+        0xF0, 0x04, // LDH A,(DIV)
+        0xE0, 0x01, // LDH (SB),A
+        0x3E, 0x81, // LD A,$81
+        0xE0, 0x02, // LDH (SC),A
+        0x18, 0xFE, // JR -2
+    ];
+
+    fn write_rom(name: &str, cgb_flag: u8, code: &[u8]) -> PathBuf {
+        let mut rom = vec![0x00; 0x8000];
+        rom[0x0100..0x0100 + code.len()].copy_from_slice(code);
+        rom[0x0143] = cgb_flag;
+        let path = std::env::temp_dir().join(format!("rustboy-{}-{name}.gb", std::process::id()));
+        std::fs::write(&path, rom).unwrap();
+        path
+    }
+
+    #[test]
+    fn cgb_without_boot_rom_starts_with_post_boot_divider() {
+        let path = write_rom("cgb-div", 0x80, &SEND_DIV);
+        let mut gb = GameBoy::load_cartridge(path.to_str().unwrap()).unwrap();
+        assert_eq!(gb.model(), Model::Cgb);
+        let capture = SerialCapture::new();
+        gb.connect_serial(capture.clone());
+
+        gb.run_frame().unwrap();
+        assert_eq!(capture.bytes(), [(POST_BOOT_TIMER_COUNTER >> 8) as u8]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_frames_stops_when_quit_requested() {
+        let path = write_rom("quit", 0x00, &[0x18, 0xFE]);
+        let mut gb = GameBoy::load_cartridge(path.to_str().unwrap()).unwrap();
+        gb.run_frames(2).unwrap();
+        assert_eq!(gb.frame, 2);
+
+        gb.quit_handle().store(true, Ordering::Relaxed);
+        gb.run_frames(2).unwrap();
+        assert_eq!(gb.frame, 2);
+        std::fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,36 @@
+mod keyboard;
+mod script;
+
+pub use keyboard::Keyboard;
+pub use script::InputScript;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::joypad::Button;
+
+/// A change in the state of a button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputEvent {
+    pub button: Button,
+    pub pressed: bool,
+}
+
+impl InputEvent {
+    pub fn new(button: Button, pressed: bool) -> Self {
+        Self { button, pressed }
+    }
+}
+
+/// A source of button presses, polled by `GameBoy` before each frame.
+pub trait InputSource {
+    /// Returns the button changes to apply before running the given frame.
+    fn poll(&mut self, frame: u64) -> Vec<InputEvent>;
+}
+
+// Allows a frontend to keep feeding a source after handing it to `GameBoy`.
+impl<Source: InputSource> InputSource for Rc<RefCell<Source>> {
+    fn poll(&mut self, frame: u64) -> Vec<InputEvent> {
+        self.borrow_mut().poll(frame)
+    }
+}
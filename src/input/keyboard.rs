@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::mem;
+
+use super::{InputEvent, InputSource};
+use crate::joypad::Button;
+
+const DEFAULT_KEYMAP: [(&str, Button); 8] = [
+    ("Right", Button::Right),
+    ("Left", Button::Left),
+    ("Up", Button::Up),
+    ("Down", Button::Down),
+    ("Z", Button::A),
+    ("X", Button::B),
+    ("Backspace", Button::Select),
+    ("Enter", Button::Start),
+];
+
+/// Translates key presses from a frontend into button presses.
+///
+/// Keys are identified by name, ignoring case, so that any windowing
+/// library's key codes can be mapped onto them.
+pub struct Keyboard {
+    keymap: HashMap<String, Button>,
+    events: Vec<InputEvent>,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        let mut keyboard = Self {
+            keymap: HashMap::new(),
+            events: Vec::new(),
+        };
+        for (key, button) in DEFAULT_KEYMAP {
+            keyboard.bind(key, button);
+        }
+        keyboard
+    }
+
+    /// Maps `key` to `button`, replacing any existing mapping for the key.
+    pub fn bind(&mut self, key: &str, button: Button) {
+        self.keymap.insert(key.to_lowercase(), button);
+    }
+
+    /// Records a key press or release, returning whether the key is mapped.
+    pub fn key_event(&mut self, key: &str, pressed: bool) -> bool {
+        match self.keymap.get(&key.to_lowercase()) {
+            Some(&button) => {
+                self.events.push(InputEvent::new(button, pressed));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputSource for Keyboard {
+    fn poll(&mut self, _frame: u64) -> Vec<InputEvent> {
+        mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn maps_default_keys() {
+        let mut keyboard = Keyboard::new();
+        assert!(keyboard.key_event("Enter", true));
+        assert!(keyboard.key_event("z", true));
+        assert!(keyboard.key_event("Enter", false));
+        assert_eq!(
+            keyboard.poll(0),
+            vec![
+                InputEvent::new(Button::Start, true),
+                InputEvent::new(Button::A, true),
+                InputEvent::new(Button::Start, false),
+            ]
+        );
+        assert!(keyboard.poll(1).is_empty(), "Events are only returned once");
+    }
+
+    #[test]
+    fn ignores_unmapped_keys() {
+        let mut keyboard = Keyboard::new();
+        assert!(!keyboard.key_event("Q", true));
+        assert!(keyboard.poll(0).is_empty());
+    }
+
+    #[test]
+    fn rebinds_keys() {
+        let mut keyboard = Keyboard::new();
+        keyboard.bind("Space", Button::Select);
+        keyboard.bind("Z", Button::B);
+        keyboard.key_event("space", true);
+        keyboard.key_event("Z", true);
+        assert_eq!(
+            keyboard.poll(0),
+            vec![
+                InputEvent::new(Button::Select, true),
+                InputEvent::new(Button::B, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{InputEvent, InputSource};
+
+/// Button presses read from a text file, for automated tests.
+///
+/// Each line gives a frame number, a button and either `down` or `up`, e.g.
+/// `120 start down`. Blank lines and anything after a `#` are ignored.
+pub struct InputScript {
+    // Sorted by frame.
+    events: Vec<(u64, InputEvent)>,
+    next: usize,
+}
+
+impl InputScript {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut events = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let event = Self::parse_line(line).map_err(|message| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Input script line {}: {message}", number + 1),
+                )
+            })?;
+            events.push(event);
+        }
+        // Stable, so events for the same frame keep their order.
+        events.sort_by_key(|(frame, _)| *frame);
+        Ok(Self { events, next: 0 })
+    }
+
+    fn parse_line(line: &str) -> Result<(u64, InputEvent), String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [frame, button, state] = fields[..] else {
+            return Err(format!(
+                "Expected '<frame> <button> <down|up>', got '{line}'"
+            ));
+        };
+        let frame = frame
+            .parse()
+            .map_err(|_| format!("Invalid frame number '{frame}'"))?;
+        let button = button.parse()?;
+        let pressed = match state {
+            "down" => true,
+            "up" => false,
+            _ => return Err(format!("Expected 'down' or 'up', got '{state}'")),
+        };
+        Ok((frame, InputEvent::new(button, pressed)))
+    }
+
+    /// Whether every event in the script has been returned.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.events.len()
+    }
+}
+
+impl InputSource for InputScript {
+    fn poll(&mut self, frame: u64) -> Vec<InputEvent> {
+        let due = self.events[self.next..]
+            .iter()
+            .take_while(|(event_frame, _)| *event_frame <= frame)
+            .map(|(_, event)| *event)
+            .collect::<Vec<_>>();
+        self.next += due.len();
+        due
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::joypad::Button;
+
+    #[test]
+    fn returns_events_on_their_frame() {
+        let mut script = InputScript::parse(
+            "# Skip the title screen\n\
+             10 start down\n\
+             12 start up\n\
+             \n\
+             12 a down # Same frame\n",
+        )
+        .unwrap();
+
+        assert!(script.poll(9).is_empty());
+        assert_eq!(script.poll(10), vec![InputEvent::new(Button::Start, true)]);
+        assert!(script.poll(11).is_empty());
+        assert!(!script.is_finished());
+        assert_eq!(
+            script.poll(12),
+            vec![
+                InputEvent::new(Button::Start, false),
+                InputEvent::new(Button::A, true),
+            ]
+        );
+        assert!(script.is_finished());
+    }
+
+    #[test]
+    fn returns_missed_events_on_next_poll() {
+        let mut script = InputScript::parse("5 b down\n3 left down\n").unwrap();
+        assert_eq!(
+            script.poll(8),
+            vec![
+                InputEvent::new(Button::Left, true),
+                InputEvent::new(Button::B, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for text in ["10 start", "x start down", "10 turbo down", "10 a pressed"] {
+            let error = InputScript::parse(text).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{text}");
+        }
+        let error = InputScript::parse("1 a down\n2 a sideways").err().unwrap();
+        assert!(error.to_string().contains("line 2"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::memory::address_space::AddressSpace;
+
+// FF0F: Interrupt Flag register (IF)
+pub const IF_ADDR: u16 = 0xFF0F;
+
+// FFFF: Interrupt Enable register (IE)
+pub const IE_ADDR: u16 = 0xFFFF;
+
+// Only the lower five bits of IF and IE correspond to interrupt sources.
+const INTERRUPT_MASK: u8 = 0x1F;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // Ordered from highest to lowest priority.
+    const ALL: [Interrupt; 5] = [
+        Self::VBlank,
+        Self::Stat,
+        Self::Timer,
+        Self::Serial,
+        Self::Joypad,
+    ];
+
+    /// Bit corresponding to this interrupt in the IF and IE registers.
+    pub fn bit(&self) -> u8 {
+        match self {
+            Self::VBlank => 0b0000_0001,
+            Self::Stat => 0b0000_0010,
+            Self::Timer => 0b0000_0100,
+            Self::Serial => 0b0000_1000,
+            Self::Joypad => 0b0001_0000,
+        }
+    }
+
+    /// Address the CPU jumps to when servicing this interrupt.
+    pub fn vector(&self) -> u16 {
+        match self {
+            Self::VBlank => 0x0040,
+            Self::Stat => 0x0048,
+            Self::Timer => 0x0050,
+            Self::Serial => 0x0058,
+            Self::Joypad => 0x0060,
+        }
+    }
+
+    /// Returns the highest priority interrupt set in `flags`, if any.
+    pub fn highest_priority(flags: u8) -> Option<Interrupt> {
+        Self::ALL
+            .into_iter()
+            .find(|interrupt| flags & interrupt.bit() != 0)
+    }
+}
+
+#[derive(Default)]
+pub struct InterruptController {
+    flags: u8,
+    enabled: u8,
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        InterruptController::default()
+    }
+
+    /// Raises a request for `interrupt` by setting its bit in IF.
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flags |= interrupt.bit();
+    }
+}
+
+impl AddressSpace for InterruptController {
+    fn accepts(&self, addr: u16) -> bool {
+        addr == IF_ADDR || addr == IE_ADDR
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            IF_ADDR => self.flags = byte & INTERRUPT_MASK,
+            IE_ADDR => self.enabled = byte,
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            // Unused upper bits of IF always read back as 1.
+            IF_ADDR => self.flags | !INTERRUPT_MASK,
+            IE_ADDR => self.enabled,
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_only_if_and_ie_registers() {
+        let ic = InterruptController::new();
+        assert!(ic.accepts(IF_ADDR));
+        assert!(ic.accepts(IE_ADDR));
+        assert!(!ic.accepts(0xFF0E));
+        assert!(!ic.accepts(0xFF10));
+    }
+
+    #[test]
+    fn request_sets_bit_in_interrupt_flag_register() {
+        let mut ic = InterruptController::new();
+        ic.request(Interrupt::Timer);
+        ic.request(Interrupt::Joypad);
+        assert_eq!(ic.get_byte(IF_ADDR), 0b1111_0100);
+    }
+
+    #[test]
+    fn unused_interrupt_flag_bits_read_as_set() {
+        let mut ic = InterruptController::new();
+        ic.set_byte(IF_ADDR, 0x00);
+        assert_eq!(ic.get_byte(IF_ADDR), 0xE0);
+    }
+
+    #[test]
+    fn interrupt_enable_register_stores_all_bits() {
+        let mut ic = InterruptController::new();
+        ic.set_byte(IE_ADDR, 0xFF);
+        assert_eq!(ic.get_byte(IE_ADDR), 0xFF);
+    }
+
+    #[test]
+    fn highest_priority_prefers_lowest_bit() {
+        let cases = [
+            (0b0000_0000, None),
+            (0b0001_0000, Some(Interrupt::Joypad)),
+            (0b0001_1000, Some(Interrupt::Serial)),
+            (0b0000_0110, Some(Interrupt::Stat)),
+            (0b0001_1111, Some(Interrupt::VBlank)),
+        ];
+        for (flags, expected) in cases {
+            assert_eq!(Interrupt::highest_priority(flags), expected);
+        }
+    }
+
+    #[test]
+    fn vectors_match_hardware() {
+        assert_eq!(Interrupt::VBlank.vector(), 0x40);
+        assert_eq!(Interrupt::Stat.vector(), 0x48);
+        assert_eq!(Interrupt::Timer.vector(), 0x50);
+        assert_eq!(Interrupt::Serial.vector(), 0x58);
+        assert_eq!(Interrupt::Joypad.vector(), 0x60);
+    }
+}
@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::memory::address_space::AddressSpace;
+
+// FF00: Joypad register (P1/JOYP)
+pub const P1_ADDR: u16 = 0xFF00;
+
+// P14 and P15 select the direction and action keys respectively when low.
+const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+const SELECT_ACTIONS: u8 = 0b0010_0000;
+const SELECT_MASK: u8 = SELECT_DIRECTIONS | SELECT_ACTIONS;
+
+// P10-P13 input lines, which read low while a selected key is pressed.
+const LINE_MASK: u8 = 0b0000_1111;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Self::Right,
+        Self::Left,
+        Self::Up,
+        Self::Down,
+        Self::A,
+        Self::B,
+        Self::Select,
+        Self::Start,
+    ];
+
+    /// Input line (P10-P13) the button pulls low when its group is selected.
+    fn line(&self) -> u8 {
+        match self {
+            Self::Right | Self::A => 0b0001,
+            Self::Left | Self::B => 0b0010,
+            Self::Up | Self::Select => 0b0100,
+            Self::Down | Self::Start => 0b1000,
+        }
+    }
+
+    fn is_direction(&self) -> bool {
+        matches!(self, Self::Right | Self::Left | Self::Up | Self::Down)
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Right => "right",
+            Self::Left => "left",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::A => "a",
+            Self::B => "b",
+            Self::Select => "select",
+            Self::Start => "start",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Button {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|button| button.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown button '{s}'"))
+    }
+}
+
+/// Joypad
+///
+/// The eight buttons are arranged in a 2x4 matrix. Writing P1 selects the
+/// direction and/or action keys, and the lower four bits then read low for
+/// each selected key which is held.
+pub struct Joypad {
+    select: u8,
+    directions: u8,
+    actions: u8,
+    interrupts: Rc<RefCell<InterruptController>>,
+}
+
+impl Joypad {
+    pub fn new(interrupts: Rc<RefCell<InterruptController>>) -> Self {
+        Self {
+            select: SELECT_MASK,
+            directions: 0,
+            actions: 0,
+            interrupts,
+        }
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        let previous = self.lines();
+        let keys = if button.is_direction() {
+            &mut self.directions
+        } else {
+            &mut self.actions
+        };
+        if pressed {
+            *keys |= button.line();
+        } else {
+            *keys &= !button.line();
+        }
+        self.update_interrupt(previous);
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        let keys = if button.is_direction() {
+            self.directions
+        } else {
+            self.actions
+        };
+        keys & button.line() != 0
+    }
+
+    /// State of P10-P13, where a cleared bit is a low line.
+    fn lines(&self) -> u8 {
+        let mut low = 0;
+        if self.select & SELECT_DIRECTIONS == 0 {
+            low |= self.directions;
+        }
+        if self.select & SELECT_ACTIONS == 0 {
+            low |= self.actions;
+        }
+        !low & LINE_MASK
+    }
+
+    /// The interrupt is requested when any input line goes from high to low,
+    /// whether from a key press or from selecting a group with a key held.
+    fn update_interrupt(&mut self, previous: u8) {
+        if previous & !self.lines() != 0 {
+            self.interrupts.borrow_mut().request(Interrupt::Joypad);
+        }
+    }
+}
+
+impl AddressSpace for Joypad {
+    fn accepts(&self, addr: u16) -> bool {
+        addr == P1_ADDR
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        let previous = self.lines();
+        self.select = byte & SELECT_MASK;
+        self.update_interrupt(previous);
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        // Bits 6 and 7 are unused and always read back as 1.
+        0b1100_0000 | self.select | self.lines()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::IF_ADDR;
+
+    fn joypad() -> (Joypad, Rc<RefCell<InterruptController>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        (Joypad::new(interrupts.clone()), interrupts)
+    }
+
+    fn joypad_interrupt_requested(interrupts: &Rc<RefCell<InterruptController>>) -> bool {
+        interrupts.borrow_mut().get_byte(IF_ADDR) & Interrupt::Joypad.bit() != 0
+    }
+
+    #[test]
+    fn reads_all_lines_high_with_nothing_selected() {
+        let (mut joypad, _) = joypad();
+        joypad.set_button(Button::Start, true);
+        joypad.set_button(Button::Down, true);
+        joypad.set_byte(P1_ADDR, 0x30);
+        assert_eq!(joypad.get_byte(P1_ADDR), 0xFF);
+    }
+
+    #[test]
+    fn reads_selected_directions() {
+        let (mut joypad, _) = joypad();
+        joypad.set_button(Button::Left, true);
+        joypad.set_button(Button::A, true);
+        joypad.set_byte(P1_ADDR, 0x20);
+        assert_eq!(joypad.get_byte(P1_ADDR), 0xED);
+    }
+
+    #[test]
+    fn reads_selected_actions() {
+        let (mut joypad, _) = joypad();
+        joypad.set_button(Button::Left, true);
+        joypad.set_button(Button::Start, true);
+        joypad.set_byte(P1_ADDR, 0x10);
+        assert_eq!(joypad.get_byte(P1_ADDR), 0xD7);
+    }
+
+    #[test]
+    fn selecting_both_groups_combines_them() {
+        let (mut joypad, _) = joypad();
+        joypad.set_button(Button::Right, true);
+        joypad.set_button(Button::B, true);
+        joypad.set_byte(P1_ADDR, 0x00);
+        assert_eq!(joypad.get_byte(P1_ADDR), 0xC0 | 0b1100);
+    }
+
+    #[test]
+    fn releasing_button_raises_line() {
+        let (mut joypad, _) = joypad();
+        joypad.set_byte(P1_ADDR, 0x10);
+        joypad.set_button(Button::Select, true);
+        assert_eq!(joypad.get_byte(P1_ADDR) & LINE_MASK, 0b1011);
+        joypad.set_button(Button::Select, false);
+        assert_eq!(joypad.get_byte(P1_ADDR) & LINE_MASK, 0b1111);
+        assert!(!joypad.is_pressed(Button::Select));
+    }
+
+    #[test]
+    fn press_of_selected_key_requests_interrupt() {
+        let (mut joypad, interrupts) = joypad();
+        joypad.set_byte(P1_ADDR, 0x20);
+        joypad.set_button(Button::Up, true);
+        assert!(joypad_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn press_of_unselected_key_does_not_request_interrupt() {
+        let (mut joypad, interrupts) = joypad();
+        joypad.set_byte(P1_ADDR, 0x20);
+        joypad.set_button(Button::Start, true);
+        assert!(!joypad_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn release_does_not_request_interrupt() {
+        let (mut joypad, interrupts) = joypad();
+        joypad.set_byte(P1_ADDR, 0x20);
+        joypad.set_button(Button::Up, true);
+        interrupts.borrow_mut().set_byte(IF_ADDR, 0x00);
+        joypad.set_button(Button::Up, false);
+        assert!(!joypad_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn selecting_group_with_key_held_requests_interrupt() {
+        let (mut joypad, interrupts) = joypad();
+        joypad.set_button(Button::A, true);
+        assert!(!joypad_interrupt_requested(&interrupts));
+        joypad.set_byte(P1_ADDR, 0x10);
+        assert!(joypad_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn parses_button_names() {
+        assert_eq!("Start".parse(), Ok(Button::Start));
+        assert_eq!("a".parse(), Ok(Button::A));
+        assert!("turbo".parse::<Button>().is_err());
+    }
+}
@@ -1,7 +1,17 @@
 #![crate_name = "rustboy"]
 #![crate_type = "lib"]
 
+pub mod apu;
 pub mod byte;
+pub mod clock;
 pub mod cpu;
 pub mod gameboy;
+pub mod input;
+pub mod interrupts;
+pub mod joypad;
 pub mod memory;
+pub mod model;
+pub mod ppu;
+pub mod serial;
+pub mod speed_switch;
+pub mod timer;
@@ -1,9 +1,105 @@
-use rustboy::gameboy::GameBoy;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::Ordering;
 
-fn main() -> std::io::Result<()> {
+use rustboy::gameboy::{GameBoy, Options};
+use rustboy::input::InputScript;
+use rustboy::model::Model;
+use rustboy::serial::{SerialCapture, SocketLink};
+
+const USAGE: &str = "Usage: rustboy [--model dmg|cgb] [--input-script FILE] [--serial-stdout] \
+                     [--link-listen ADDR | --link-connect ADDR] \
+                     [--record-audio FILE [--record-channels]] [--frames N] ROM\n\
+                     Link addresses are host:port, or unix:PATH for a Unix socket.\n\
+                     With --frames, N frames are run as fast as possible before exiting.\n\
+                     The model defaults to the one the cartridge asks for.";
+
+enum LinkMode {
+    Listen(String),
+    Connect(String),
+}
+
+fn connect_link(mode: LinkMode) -> io::Result<SocketLink> {
+    match mode {
+        #[cfg(unix)]
+        LinkMode::Listen(addr) if addr.starts_with("unix:") => {
+            SocketLink::listen_unix(Path::new(&addr["unix:".len()..]))
+        }
+        #[cfg(unix)]
+        LinkMode::Connect(addr) if addr.starts_with("unix:") => {
+            SocketLink::connect_unix(Path::new(&addr["unix:".len()..]))
+        }
+        LinkMode::Listen(addr) => SocketLink::listen_tcp(addr),
+        LinkMode::Connect(addr) => SocketLink::connect_tcp(addr),
+    }
+}
+
+fn main() -> io::Result<()> {
     env_logger::init();
-    let filename = std::env::args().last().unwrap();
-    let mut gb = GameBoy::load_cartridge(&filename)?;
-    gb.run();
-    Ok(())
+
+    let usage = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+    let mut filename = None;
+    let mut input_script = None;
+    let mut serial_stdout = false;
+    let mut link = None;
+    let mut record_audio = None;
+    let mut record_channels = false;
+    let mut frames = None;
+    let mut model = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input-script" => input_script = Some(args.next().ok_or_else(usage)?),
+            "--serial-stdout" => serial_stdout = true,
+            "--link-listen" => link = Some(LinkMode::Listen(args.next().ok_or_else(usage)?)),
+            "--link-connect" => link = Some(LinkMode::Connect(args.next().ok_or_else(usage)?)),
+            "--record-audio" => record_audio = Some(args.next().ok_or_else(usage)?),
+            "--record-channels" => record_channels = true,
+            "--frames" => {
+                let count = args.next().ok_or_else(usage)?;
+                frames = Some(count.parse::<u64>().map_err(|_| usage())?);
+            }
+            "--model" => {
+                let name = args.next().ok_or_else(usage)?;
+                model = Some(name.parse::<Model>().map_err(|_| usage())?);
+            }
+            _ => filename = Some(arg),
+        }
+    }
+    let filename = filename.ok_or_else(usage)?;
+
+    let options = Options {
+        model,
+        ..Options::default()
+    };
+    let mut gb = GameBoy::load_cartridge_with_options(&filename, options)?;
+    if let Some(path) = input_script {
+        gb.add_input_source(InputScript::load(Path::new(&path))?);
+    }
+    if let Some(mode) = link {
+        gb.connect_serial(connect_link(mode)?);
+    } else if serial_stdout {
+        gb.connect_serial(SerialCapture::stdout());
+    }
+    if let Some(path) = record_audio {
+        gb.start_recording(Path::new(&path), record_channels)?;
+    }
+
+    // Stop cleanly on Ctrl-C, so the save file and recording are written,
+    // unless it is pressed again while that is stuck.
+    let quit = gb.quit_handle();
+    ctrlc::set_handler(move || {
+        if quit.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+    })
+    .map_err(io::Error::other)?;
+
+    let result = match frames {
+        Some(frames) => gb.run_frames(frames),
+        None => gb.run(),
+    };
+    gb.stop_recording()?;
+    gb.save()?;
+    result.map_err(io::Error::other)
 }
@@ -1,7 +1,13 @@
 pub mod address_space;
 pub mod boot_rom;
 pub mod cartridge;
+pub mod cartridge_header;
+pub mod hdma;
+pub mod io_registers;
+pub mod mbc;
 pub mod mmu;
+pub mod oam_dma;
 pub mod ram;
 pub mod rom;
 pub mod void;
+pub mod work_ram;
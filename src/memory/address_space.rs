@@ -1,5 +1,51 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 pub trait AddressSpace {
     fn accepts(&self, addr: u16) -> bool;
     fn set_byte(&mut self, addr: u16, byte: u8);
     fn get_byte(&mut self, addr: u16) -> u8;
+
+    /// Advances the address space by the given number of T-cycles.
+    /// Most address spaces are passive and have nothing to do.
+    fn tick(&mut self, _cycles: u8) {}
+
+    /// Called when the CPU switches in or out of CGB double speed mode.
+    /// `tick` always counts T-cycles of the normal speed clock, so devices
+    /// clocked by the CPU, like the timer, need to run twice as fast.
+    fn set_double_speed(&mut self, _enabled: bool) {}
+
+    /// Whether the CPU is paused while a device uses the bus, e.g. during
+    /// a CGB VRAM DMA transfer. The rest of the system keeps running.
+    fn stalls_cpu(&self) -> bool {
+        false
+    }
+}
+
+// Allows a device to be mapped into memory while a handle to it is kept
+// elsewhere, e.g. the interrupt controller shared by several peripherals.
+impl<Space: AddressSpace> AddressSpace for Rc<RefCell<Space>> {
+    fn accepts(&self, addr: u16) -> bool {
+        self.borrow().accepts(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        self.borrow_mut().set_byte(addr, byte);
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        self.borrow_mut().get_byte(addr)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.borrow_mut().tick(cycles);
+    }
+
+    fn set_double_speed(&mut self, enabled: bool) {
+        self.borrow_mut().set_double_speed(enabled);
+    }
+
+    fn stalls_cpu(&self) -> bool {
+        self.borrow().stalls_cpu()
+    }
 }
@@ -1,6 +1,18 @@
 use super::rom::Rom;
 
-const BOOT_ROM: [u8; 0x100] = [
+use std::path::Path;
+
+// FF50: Writing a non-zero value unmaps the boot ROM until the next reset.
+pub const BOOT_ROM_DISABLE_ADDR: u16 = 0xFF50;
+
+const BOOT_ROM_SIZE: usize = 0x100;
+// The CGB boot ROM spans 0000-08FF, with a gap at 0100-01FF through which
+// the cartridge header stays visible.
+const CGB_BOOT_ROM_SIZE: usize = 0x900;
+const HEADER_START: u16 = 0x0100;
+const HEADER_END: u16 = 0x01FF;
+
+const BOOT_ROM: [u8; BOOT_ROM_SIZE] = [
     0x31, 0xFE, 0xFF, 0xAF, 0x21, 0xFF, 0x9F, 0x32, 0xCB, 0x7C, 0x20, 0xFB, 0x21, 0x26, 0xFF, 0x0E,
     0x11, 0x3E, 0x80, 0x32, 0xE2, 0x0C, 0x3E, 0xF3, 0xE2, 0x32, 0x3E, 0x77, 0x77, 0x3E, 0xFC, 0xE0,
     0x47, 0x11, 0x04, 0x01, 0x21, 0x10, 0x80, 0x1A, 0xCD, 0x95, 0x00, 0xCD, 0x96, 0x00, 0x13, 0x7B,
@@ -22,3 +34,35 @@ const BOOT_ROM: [u8; 0x100] = [
 pub fn create_boot_rom() -> Rom {
     Rom::new(0, BOOT_ROM.to_vec())
 }
+
+/// Loads a user supplied DMG or CGB boot ROM dump in place of the embedded
+/// one.
+pub fn load_boot_rom(path: &Path) -> std::io::Result<Rom> {
+    log::debug!("Reading boot ROM {}", path.display());
+
+    let contents = std::fs::read(path)?;
+    if contents.len() != BOOT_ROM_SIZE && contents.len() != CGB_BOOT_ROM_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Boot ROM {} is {} bytes, expected {} or {}",
+                path.display(),
+                contents.len(),
+                BOOT_ROM_SIZE,
+                CGB_BOOT_ROM_SIZE
+            ),
+        ));
+    }
+    Ok(Rom::new(0, contents))
+}
+
+/// Whether the cartridge is visible at `addr` while the boot ROM is mapped.
+pub fn is_header_gap(addr: u16) -> bool {
+    (HEADER_START..=HEADER_END).contains(&addr)
+}
+
+/// Nintendo logo, as stored in the boot ROM and compared against the
+/// cartridge header.
+pub fn nintendo_logo() -> &'static [u8] {
+    &BOOT_ROM[0xA8..0xD8]
+}
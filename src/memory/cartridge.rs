@@ -1,29 +1,79 @@
 use super::address_space::AddressSpace;
-use super::boot_rom::create_boot_rom;
+use super::boot_rom::{create_boot_rom, is_header_gap, BOOT_ROM_DISABLE_ADDR};
+use super::cartridge_header::CartridgeHeader;
+use super::mbc::{create_mbc, is_ram_addr, is_rom_addr, Mbc, RtcClock, SAVE_FOOTER_SIZE};
 use super::rom::Rom;
-use super::void::Void;
 
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
 
 pub struct Cartridge {
-    spaces: Vec<Box<dyn AddressSpace>>,
-    void: Box<dyn AddressSpace>,
+    // Overlays the first 256 bytes of the cartridge ROM until unmapped.
+    boot_rom: Option<Rom>,
+    header: CartridgeHeader,
+    mbc: Box<dyn Mbc>,
+    // Where external RAM is persisted, for battery-backed cartridges.
+    save_path: Option<PathBuf>,
+    // External RAM has been written since it was last saved.
+    ram_dirty: bool,
 }
 
 impl Cartridge {
+    /// Loads a ROM, keeping battery-backed RAM in a `.sav` file alongside it.
     pub fn load(filename: &str) -> std::io::Result<Self> {
-        let file_rom = Box::new(Self::load_file(filename)?) as Box<dyn AddressSpace>;
-        let boot_rom = Box::new(create_boot_rom()) as Box<dyn AddressSpace>;
+        Self::load_with_save_path(filename, Path::new(filename).with_extension("sav"))
+    }
+
+    pub fn load_with_save_path(filename: &str, save_path: PathBuf) -> std::io::Result<Self> {
+        let contents = Self::load_file(filename)?;
+        let mut cartridge = Self::from_rom(contents)?;
+        if cartridge.header.has_battery() {
+            cartridge.load_save_file(save_path)?;
+        }
+        Ok(cartridge)
+    }
+
+    /// Creates a cartridge from ROM contents, without any save file.
+    pub fn from_rom(contents: Vec<u8>) -> std::io::Result<Self> {
+        let header = CartridgeHeader::parse(&contents)?;
+
+        // Real hardware only checks the logo and header checksum, so problems
+        // are reported without refusing to run the ROM.
+        if let Err(errors) = header.validate(&contents) {
+            for error in errors {
+                log::warn!("{:?}: {}", header.title, error);
+            }
+        }
+
+        log::debug!("Loaded cartridge {:?}", header.title);
 
-        let spaces = vec![boot_rom, file_rom];
         Ok(Cartridge {
-            spaces,
-            void: Box::new(Void {}),
+            boot_rom: Some(create_boot_rom()),
+            mbc: create_mbc(&header, contents)?,
+            header,
+            save_path: None,
+            ram_dirty: false,
         })
     }
 
-    fn load_file(filename: &str) -> std::io::Result<Rom> {
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    /// Replaces the embedded boot ROM, e.g. with a user supplied dump.
+    pub fn replace_boot_rom(&mut self, boot_rom: Rom) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    /// Exposes the cartridge ROM underneath the boot ROM.
+    pub fn unmap_boot_rom(&mut self) {
+        if self.boot_rom.take().is_some() {
+            log::debug!("Boot ROM unmapped");
+        }
+    }
+
+    fn load_file(filename: &str) -> std::io::Result<Vec<u8>> {
         log::debug!("Reading ROM File {}", filename);
 
         let mut file = File::open(filename)?;
@@ -33,27 +83,339 @@ impl Cartridge {
 
         log::debug!("Loaded {} bytes", contents.len());
 
-        Ok(Rom::new(0x0100, contents))
+        Ok(contents)
     }
 
-    fn get_space(&mut self, addr: u16) -> &mut Box<dyn AddressSpace> {
-        self.spaces
-            .iter_mut()
-            .find(|space| space.accepts(addr))
-            .unwrap_or(&mut self.void)
+    /// Selects the time source for the cartridge's real time clock, if any.
+    pub fn set_rtc_clock(&mut self, clock: RtcClock) {
+        if let Some(rtc) = self.mbc.rtc() {
+            rtc.set_clock(clock);
+        }
+    }
+
+    /// Restores external RAM, and the RTC footer if present, from `path` if
+    /// it exists, and saves to it from then on.
+    fn load_save_file(&mut self, path: PathBuf) -> std::io::Result<()> {
+        let ram_size = self.mbc.ram().len();
+        let has_rtc = self.mbc.rtc().is_some();
+        if ram_size == 0 && !has_rtc {
+            return Ok(());
+        }
+
+        match fs::read(&path) {
+            // Saves from emulators without RTC support omit the footer.
+            Ok(data) if data.len() == ram_size => {
+                log::debug!("Loaded save file {}", path.display());
+                self.mbc.ram_mut().copy_from_slice(&data);
+            }
+            Ok(data) if has_rtc && data.len() == ram_size + SAVE_FOOTER_SIZE => {
+                log::debug!("Loaded save file {} with RTC", path.display());
+                let (ram, footer) = data.split_at(ram_size);
+                self.mbc.ram_mut().copy_from_slice(ram);
+                if let Some(rtc) = self.mbc.rtc() {
+                    rtc.load_save_footer(footer.try_into().unwrap());
+                }
+            }
+            // Refuse to run rather than overwrite a save for another game.
+            Ok(data) => {
+                let expected = if has_rtc {
+                    format!("{} or {}", ram_size, ram_size + SAVE_FOOTER_SIZE)
+                } else {
+                    ram_size.to_string()
+                };
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Save file {} is {} bytes, expected {}",
+                        path.display(),
+                        data.len(),
+                        expected
+                    ),
+                ));
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                log::debug!("No save file at {}", path.display());
+            }
+            Err(error) => return Err(error),
+        }
+
+        self.save_path = Some(path);
+        Ok(())
+    }
+
+    /// Writes external RAM to the save file if it has changed, or always
+    /// when the cartridge has a real time clock.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
+        let footer = self.mbc.rtc().map(|rtc| rtc.save_footer());
+        if !self.ram_dirty && footer.is_none() {
+            return Ok(());
+        }
+
+        log::debug!("Writing save file {}", path.display());
+
+        let mut data = self.mbc.ram().to_vec();
+        if let Some(footer) = footer {
+            data.extend_from_slice(&footer);
+        }
+
+        // Write to a temporary file first, so a crash can't leave a partial save.
+        let temp_path = path.with_extension("sav.tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, path)?;
+
+        self.ram_dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        if let Err(error) = self.save() {
+            log::error!("Failed to write save file: {}", error);
+        }
     }
 }
 
 impl AddressSpace for Cartridge {
     fn accepts(&self, addr: u16) -> bool {
-        self.spaces.iter().any(|space| space.accepts(addr))
+        is_rom_addr(addr) || is_ram_addr(addr) || addr == BOOT_ROM_DISABLE_ADDR
     }
 
     fn set_byte(&mut self, addr: u16, byte: u8) {
-        self.get_space(addr).set_byte(addr, byte);
+        if addr == BOOT_ROM_DISABLE_ADDR {
+            if byte != 0 {
+                self.unmap_boot_rom();
+            }
+            return;
+        }
+        if is_ram_addr(addr) && self.mbc.ram_enabled() {
+            self.ram_dirty = true;
+        }
+        // Writes to ROM go to the MBC registers, even under the boot ROM.
+        self.mbc.set_byte(addr, byte);
     }
 
     fn get_byte(&mut self, addr: u16) -> u8 {
-        self.get_space(addr).get_byte(addr)
+        if addr == BOOT_ROM_DISABLE_ADDR {
+            return 0xFF;
+        }
+        if let Some(boot_rom) = self
+            .boot_rom
+            .as_mut()
+            .filter(|rom| rom.accepts(addr) && !is_header_gap(addr))
+        {
+            return boot_rom.get_byte(addr);
+        }
+        self.mbc.get_byte(addr)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.mbc.tick(cycles);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // MBC1+RAM+BATTERY with 8 KiB of RAM.
+    fn battery_rom() -> Vec<u8> {
+        let mut rom = vec![0x00; 0x8000];
+        rom[0x0147] = 0x03;
+        rom[0x0149] = 0x02;
+        rom
+    }
+
+    fn save_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rustboy-{}-{name}.sav", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn write_ram(cartridge: &mut Cartridge, addr: u16, byte: u8) {
+        cartridge.set_byte(0x0000, 0x0A);
+        cartridge.set_byte(addr, byte);
+    }
+
+    #[test]
+    fn boot_rom_overlays_cartridge_rom() {
+        let mut rom = battery_rom();
+        rom[0x0000] = 0x12;
+        rom[0x0100] = 0x34;
+        let mut cartridge = Cartridge::from_rom(rom).unwrap();
+        assert_eq!(cartridge.get_byte(0x0000), 0x31);
+        assert_eq!(cartridge.get_byte(0x0100), 0x34);
+    }
+
+    #[test]
+    fn writing_ff50_unmaps_boot_rom() {
+        let mut rom = battery_rom();
+        rom[0x0038] = 0xC9;
+        let mut cartridge = Cartridge::from_rom(rom).unwrap();
+
+        cartridge.set_byte(BOOT_ROM_DISABLE_ADDR, 0x00);
+        assert_eq!(cartridge.get_byte(0x0038), 0x08, "Zero is ignored");
+
+        cartridge.set_byte(BOOT_ROM_DISABLE_ADDR, 0x01);
+        assert_eq!(cartridge.get_byte(0x0000), 0x00);
+        assert_eq!(cartridge.get_byte(0x0038), 0xC9);
+
+        cartridge.set_byte(BOOT_ROM_DISABLE_ADDR, 0x00);
+        assert_eq!(cartridge.get_byte(0x0038), 0xC9, "Can't be remapped");
+    }
+
+    #[test]
+    fn replaces_boot_rom() {
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.replace_boot_rom(Rom::new(0, vec![0xAB; 0x100]));
+        assert_eq!(cartridge.get_byte(0x0000), 0xAB);
+        assert_eq!(cartridge.get_byte(0x00FF), 0xAB);
+        assert_eq!(cartridge.get_byte(0x0100), 0x00);
+    }
+
+    #[test]
+    fn cgb_boot_rom_leaves_header_visible() {
+        let mut rom = battery_rom();
+        rom[0x0100] = 0x12;
+        rom[0x0200] = 0x34;
+        let mut cartridge = Cartridge::from_rom(rom).unwrap();
+        cartridge.replace_boot_rom(Rom::new(0, vec![0xAB; 0x900]));
+        assert_eq!(cartridge.get_byte(0x00FF), 0xAB);
+        assert_eq!(cartridge.get_byte(0x0100), 0x12);
+        assert_eq!(cartridge.get_byte(0x01FF), 0x00);
+        assert_eq!(cartridge.get_byte(0x0200), 0xAB);
+        assert_eq!(cartridge.get_byte(0x08FF), 0xAB);
+    }
+
+    #[test]
+    fn writes_ram_to_save_file() {
+        let path = save_path("write");
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+
+        write_ram(&mut cartridge, 0xA001, 0x42);
+        cartridge.save().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(data.len(), 0x2000);
+        assert_eq!(data[1], 0x42);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn restores_ram_from_save_file() {
+        let path = save_path("restore");
+        let mut data = vec![0x00; 0x2000];
+        data[0x10] = 0x99;
+        fs::write(&path, data).unwrap();
+
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+        cartridge.set_byte(0x0000, 0x0A);
+        assert_eq!(cartridge.get_byte(0xA010), 0x99);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_save_file_of_wrong_size() {
+        let path = save_path("wrong-size");
+        fs::write(&path, [0x00; 0x800]).unwrap();
+
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        let error = cartridge.load_save_file(path.clone()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_saves_when_ram_changed() {
+        let path = save_path("unchanged");
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+
+        cartridge.save().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn ignores_writes_while_ram_disabled() {
+        let path = save_path("disabled");
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+
+        cartridge.set_byte(0xA000, 0x42);
+        cartridge.save().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn saves_when_dropped() {
+        let path = save_path("drop");
+        let mut cartridge = Cartridge::from_rom(battery_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+        write_ram(&mut cartridge, 0xA000, 0x24);
+        drop(cartridge);
+
+        assert_eq!(fs::read(&path).unwrap()[0], 0x24);
+        fs::remove_file(path).unwrap();
+    }
+
+    // MBC3+TIMER+RAM+BATTERY with 8 KiB of RAM.
+    fn rtc_rom() -> Vec<u8> {
+        let mut rom = battery_rom();
+        rom[0x0147] = 0x10;
+        rom
+    }
+
+    #[test]
+    fn saves_rtc_footer_after_ram() {
+        let path = save_path("rtc");
+        let mut cartridge = Cartridge::from_rom(rtc_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+        cartridge.set_byte(0x0000, 0x0A);
+        cartridge.set_byte(0x4000, 0x0A);
+        cartridge.set_byte(0xA000, 0x05);
+        cartridge.save().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(data.len(), 0x2000 + SAVE_FOOTER_SIZE);
+        assert_eq!(data[0x2000 + 8], 0x05, "Hours register");
+
+        let mut restored = Cartridge::from_rom(rtc_rom()).unwrap();
+        restored.load_save_file(path.clone()).unwrap();
+        restored.set_byte(0x0000, 0x0A);
+        restored.set_byte(0x4000, 0x0A);
+        assert_eq!(restored.get_byte(0xA000), 0x05);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rtc_size_mismatch_reports_both_sizes() {
+        let path = save_path("rtc-wrong-size");
+        fs::write(&path, [0x00; 0x800]).unwrap();
+
+        let mut cartridge = Cartridge::from_rom(rtc_rom()).unwrap();
+        let error = cartridge.load_save_file(path.clone()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        let expected = format!("expected 8192 or {}", 0x2000 + SAVE_FOOTER_SIZE);
+        assert!(error.to_string().ends_with(&expected), "{error}");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn loads_rtc_save_without_footer() {
+        let path = save_path("rtc-no-footer");
+        let mut data = vec![0x00; 0x2000];
+        data[0] = 0x77;
+        fs::write(&path, data).unwrap();
+
+        let mut cartridge = Cartridge::from_rom(rtc_rom()).unwrap();
+        cartridge.load_save_file(path.clone()).unwrap();
+        cartridge.set_byte(0x0000, 0x0A);
+        assert_eq!(cartridge.get_byte(0xA000), 0x77);
+        fs::remove_file(path).unwrap();
     }
 }
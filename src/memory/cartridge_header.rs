@@ -0,0 +1,504 @@
+use super::boot_rom::nintendo_logo;
+
+use std::fmt;
+
+// 0104-0133: Nintendo logo
+const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0134;
+
+// 0134-0143: Title, the last 5 bytes of which are reused on newer cartridges.
+const TITLE_START: usize = 0x0134;
+const TITLE_END: usize = 0x0144;
+
+// 013F-0142: Manufacturer code
+const MANUFACTURER_START: usize = 0x013F;
+const MANUFACTURER_END: usize = 0x0143;
+
+// 0143: CGB flag
+const CGB_FLAG_ADDR: usize = 0x0143;
+
+// 0144-0145: New licensee code
+const NEW_LICENSEE_START: usize = 0x0144;
+const NEW_LICENSEE_END: usize = 0x0146;
+
+// 0146: SGB flag
+const SGB_FLAG_ADDR: usize = 0x0146;
+
+// 0147: Cartridge type
+const CARTRIDGE_TYPE_ADDR: usize = 0x0147;
+
+// 0148: ROM size
+const ROM_SIZE_ADDR: usize = 0x0148;
+
+// 0149: RAM size
+const RAM_SIZE_ADDR: usize = 0x0149;
+
+// 014A: Destination code
+const DESTINATION_ADDR: usize = 0x014A;
+
+// 014B: Old licensee code
+const OLD_LICENSEE_ADDR: usize = 0x014B;
+
+// 014C: Mask ROM version number
+const VERSION_ADDR: usize = 0x014C;
+
+// 014D: Header checksum
+const HEADER_CHECKSUM_ADDR: usize = 0x014D;
+
+// 014E-014F: Global checksum
+const GLOBAL_CHECKSUM_ADDR: usize = 0x014E;
+
+/// Cartridges are at least large enough to contain the header.
+pub const HEADER_END: usize = 0x0150;
+
+// An old licensee code of 0x33 means the new licensee code is used instead.
+const USE_NEW_LICENSEE: u8 = 0x33;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CgbSupport {
+    /// Monochrome only.
+    None,
+    /// Enhanced on CGB, but still works on monochrome models.
+    Compatible,
+    /// Requires a CGB.
+    Only,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+/// Memory bank controller fitted to the cartridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MbcKind {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The file is smaller than the header, or than the declared ROM size.
+    Truncated {
+        len: usize,
+        expected: usize,
+    },
+    LogoMismatch,
+    HeaderChecksum {
+        expected: u8,
+        actual: u8,
+    },
+    GlobalChecksum {
+        expected: u16,
+        actual: u16,
+    },
+    UnsupportedCartridgeType(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated { len, expected } => {
+                write!(f, "ROM truncated: {len} bytes, expected {expected}")
+            }
+            Self::LogoMismatch => write!(f, "Nintendo logo does not match"),
+            Self::HeaderChecksum { expected, actual } => write!(
+                f,
+                "Header checksum mismatch: expected {expected:#04X}, got {actual:#04X}"
+            ),
+            Self::GlobalChecksum { expected, actual } => write!(
+                f,
+                "Global checksum mismatch: expected {expected:#06X}, got {actual:#06X}"
+            ),
+            Self::UnsupportedCartridgeType(code) => {
+                write!(f, "Unsupported cartridge type {code:#04X}")
+            }
+            Self::InvalidRomSize(code) => write!(f, "Invalid ROM size code {code:#04X}"),
+            Self::InvalidRamSize(code) => write!(f, "Invalid RAM size code {code:#04X}"),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+impl From<HeaderError> for std::io::Error {
+    fn from(error: HeaderError) -> Self {
+        let kind = match error {
+            HeaderError::UnsupportedCartridgeType(_) => std::io::ErrorKind::Unsupported,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+
+/// Metadata stored at 0100-014F of every cartridge ROM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_support: CgbSupport,
+    pub sgb_support: bool,
+    pub old_licensee_code: u8,
+    pub new_licensee_code: Option<String>,
+    pub cartridge_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination: Destination,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    /// Reads the header fields from `rom`. Only fails when the ROM is too
+    /// small to contain a header, use `validate` to check the contents.
+    pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
+        if rom.len() < HEADER_END {
+            return Err(HeaderError::Truncated {
+                len: rom.len(),
+                expected: HEADER_END,
+            });
+        }
+
+        let cgb_flag = rom[CGB_FLAG_ADDR];
+        let cgb_support = match cgb_flag {
+            0xC0 => CgbSupport::Only,
+            flag if flag & 0x80 != 0 => CgbSupport::Compatible,
+            _ => CgbSupport::None,
+        };
+
+        // Cartridges supporting CGB use the end of the title for other fields.
+        let (title_end, manufacturer_code) = if cgb_support == CgbSupport::None {
+            (TITLE_END, None)
+        } else {
+            let code = ascii(&rom[MANUFACTURER_START..MANUFACTURER_END]);
+            (
+                MANUFACTURER_START,
+                Some(code).filter(|code| code.len() == 4),
+            )
+        };
+
+        let old_licensee_code = rom[OLD_LICENSEE_ADDR];
+        let new_licensee_code = (old_licensee_code == USE_NEW_LICENSEE)
+            .then(|| ascii(&rom[NEW_LICENSEE_START..NEW_LICENSEE_END]));
+
+        let destination = if rom[DESTINATION_ADDR] == 0x00 {
+            Destination::Japan
+        } else {
+            Destination::Overseas
+        };
+
+        Ok(Self {
+            title: ascii(&rom[TITLE_START..title_end]),
+            manufacturer_code,
+            cgb_support,
+            sgb_support: rom[SGB_FLAG_ADDR] == 0x03,
+            old_licensee_code,
+            new_licensee_code,
+            cartridge_type: rom[CARTRIDGE_TYPE_ADDR],
+            rom_size_code: rom[ROM_SIZE_ADDR],
+            ram_size_code: rom[RAM_SIZE_ADDR],
+            destination,
+            version: rom[VERSION_ADDR],
+            header_checksum: rom[HEADER_CHECKSUM_ADDR],
+            global_checksum: u16::from_be_bytes([
+                rom[GLOBAL_CHECKSUM_ADDR],
+                rom[GLOBAL_CHECKSUM_ADDR + 1],
+            ]),
+        })
+    }
+
+    /// Checks the header against the ROM it was parsed from, returning
+    /// every problem found.
+    pub fn validate(&self, rom: &[u8]) -> Result<(), Vec<HeaderError>> {
+        let mut errors = vec![];
+
+        if rom.len() < HEADER_END {
+            return Err(vec![HeaderError::Truncated {
+                len: rom.len(),
+                expected: HEADER_END,
+            }]);
+        }
+
+        if &rom[LOGO_START..LOGO_END] != nintendo_logo() {
+            errors.push(HeaderError::LogoMismatch);
+        }
+
+        let actual = header_checksum(rom);
+        if actual != self.header_checksum {
+            errors.push(HeaderError::HeaderChecksum {
+                expected: self.header_checksum,
+                actual,
+            });
+        }
+
+        let actual = global_checksum(rom);
+        if actual != self.global_checksum {
+            errors.push(HeaderError::GlobalChecksum {
+                expected: self.global_checksum,
+                actual,
+            });
+        }
+
+        if self.mbc().is_none() {
+            errors.push(HeaderError::UnsupportedCartridgeType(self.cartridge_type));
+        }
+
+        match self.rom_size() {
+            Some(expected) if rom.len() < expected => errors.push(HeaderError::Truncated {
+                len: rom.len(),
+                expected,
+            }),
+            Some(_) => {}
+            None => errors.push(HeaderError::InvalidRomSize(self.rom_size_code)),
+        }
+
+        if self.ram_size().is_none() {
+            errors.push(HeaderError::InvalidRamSize(self.ram_size_code));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Memory bank controller declared by the cartridge type, if supported.
+    pub fn mbc(&self) -> Option<MbcKind> {
+        match self.cartridge_type {
+            0x00 | 0x08 | 0x09 => Some(MbcKind::RomOnly),
+            0x01..=0x03 => Some(MbcKind::Mbc1),
+            0x05 | 0x06 => Some(MbcKind::Mbc2),
+            0x0F..=0x13 => Some(MbcKind::Mbc3),
+            0x19..=0x1E => Some(MbcKind::Mbc5),
+            _ => None,
+        }
+    }
+
+    /// Whether external RAM is kept powered by a battery.
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self.cartridge_type,
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF
+        )
+    }
+
+    /// Whether the cartridge contains a real time clock.
+    pub fn has_timer(&self) -> bool {
+        matches!(self.cartridge_type, 0x0F | 0x10)
+    }
+
+    /// ROM size in bytes: 32 KiB << code.
+    pub fn rom_size(&self) -> Option<usize> {
+        match self.rom_size_code {
+            code @ 0x00..=0x08 => Some(0x8000 << code),
+            _ => None,
+        }
+    }
+
+    /// External RAM size in bytes.
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code {
+            0x00 => Some(0),
+            0x01 => Some(0x800),
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+}
+
+/// Checksum of 0134-014C, verified by the boot ROM.
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..HEADER_CHECKSUM_ADDR]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1))
+}
+
+/// Sum of every byte in the ROM, except the global checksum itself.
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(addr, _)| !(GLOBAL_CHECKSUM_ADDR..HEADER_END).contains(addr))
+        .fold(0u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16))
+}
+
+// Header strings are upper case ASCII, padded with zeros.
+fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|byte| **byte != 0x00)
+        .map(|byte| *byte as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rom() -> Vec<u8> {
+        let mut rom = vec![0x00; 0x8000];
+        rom[LOGO_START..LOGO_END].copy_from_slice(nintendo_logo());
+        rom[TITLE_START..TITLE_START + 6].copy_from_slice(b"TETRIS");
+        rom[OLD_LICENSEE_ADDR] = 0x01;
+        rom[DESTINATION_ADDR] = 0x01;
+        fix_checksums(&mut rom);
+        rom
+    }
+
+    fn fix_checksums(rom: &mut [u8]) {
+        rom[HEADER_CHECKSUM_ADDR] = header_checksum(rom);
+        let [high, low] = global_checksum(rom).to_be_bytes();
+        rom[GLOBAL_CHECKSUM_ADDR] = high;
+        rom[GLOBAL_CHECKSUM_ADDR + 1] = low;
+    }
+
+    #[test]
+    fn parses_fields() {
+        let header = CartridgeHeader::parse(&rom()).unwrap();
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.cgb_support, CgbSupport::None);
+        assert!(!header.sgb_support);
+        assert_eq!(header.old_licensee_code, 0x01);
+        assert_eq!(header.new_licensee_code, None);
+        assert_eq!(header.mbc(), Some(MbcKind::RomOnly));
+        assert_eq!(header.rom_size(), Some(0x8000));
+        assert_eq!(header.ram_size(), Some(0));
+        assert_eq!(header.destination, Destination::Overseas);
+        assert_eq!(header.version, 0x00);
+    }
+
+    #[test]
+    fn parses_cgb_header() {
+        let mut rom = rom();
+        rom[TITLE_START..MANUFACTURER_START].copy_from_slice(b"POKEMON GLD");
+        rom[MANUFACTURER_START..MANUFACTURER_END].copy_from_slice(b"AAUE");
+        rom[CGB_FLAG_ADDR] = 0x80;
+        rom[NEW_LICENSEE_START..NEW_LICENSEE_END].copy_from_slice(b"01");
+        rom[SGB_FLAG_ADDR] = 0x03;
+        rom[OLD_LICENSEE_ADDR] = USE_NEW_LICENSEE;
+        rom[CARTRIDGE_TYPE_ADDR] = 0x10;
+
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.title, "POKEMON GLD");
+        assert_eq!(header.manufacturer_code.as_deref(), Some("AAUE"));
+        assert_eq!(header.cgb_support, CgbSupport::Compatible);
+        assert!(header.sgb_support);
+        assert_eq!(header.new_licensee_code.as_deref(), Some("01"));
+        assert_eq!(header.mbc(), Some(MbcKind::Mbc3));
+        assert!(header.has_battery());
+        assert!(header.has_timer());
+    }
+
+    #[test]
+    fn parses_cgb_only_flag() {
+        let mut rom = rom();
+        rom[CGB_FLAG_ADDR] = 0xC0;
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.cgb_support, CgbSupport::Only);
+    }
+
+    #[test]
+    fn parse_rejects_truncated_header() {
+        let result = CartridgeHeader::parse(&[0x00; 0x14F]);
+        assert_eq!(
+            result,
+            Err(HeaderError::Truncated {
+                len: 0x14F,
+                expected: HEADER_END
+            })
+        );
+    }
+
+    #[test]
+    fn validates_correct_rom() {
+        let rom = rom();
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.validate(&rom), Ok(()));
+    }
+
+    #[test]
+    fn reports_checksum_mismatches() {
+        let mut rom = rom();
+        rom[HEADER_CHECKSUM_ADDR] = 0x12;
+        rom[GLOBAL_CHECKSUM_ADDR + 1] = 0x34;
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        let errors = header.validate(&rom).unwrap_err();
+        assert!(errors.contains(&HeaderError::HeaderChecksum {
+            expected: 0x12,
+            actual: header_checksum(&rom)
+        }));
+        assert!(matches!(
+            errors[1],
+            HeaderError::GlobalChecksum { expected, .. } if expected & 0xFF == 0x34
+        ));
+    }
+
+    #[test]
+    fn reports_logo_mismatch() {
+        let mut rom = rom();
+        rom[LOGO_START] = 0x00;
+        fix_checksums(&mut rom);
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.validate(&rom), Err(vec![HeaderError::LogoMismatch]));
+    }
+
+    #[test]
+    fn reports_rom_smaller_than_declared_size() {
+        let mut rom = rom();
+        rom[ROM_SIZE_ADDR] = 0x01;
+        fix_checksums(&mut rom);
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(
+            header.validate(&rom),
+            Err(vec![HeaderError::Truncated {
+                len: 0x8000,
+                expected: 0x10000
+            }])
+        );
+    }
+
+    #[test]
+    fn reports_invalid_codes() {
+        let mut rom = rom();
+        rom[CARTRIDGE_TYPE_ADDR] = 0xFC;
+        rom[ROM_SIZE_ADDR] = 0x52;
+        rom[RAM_SIZE_ADDR] = 0x07;
+        fix_checksums(&mut rom);
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(
+            header.validate(&rom),
+            Err(vec![
+                HeaderError::UnsupportedCartridgeType(0xFC),
+                HeaderError::InvalidRomSize(0x52),
+                HeaderError::InvalidRamSize(0x07),
+            ])
+        );
+    }
+
+    #[test]
+    fn header_checksum_subtracts_each_byte_plus_one() {
+        let mut rom = vec![0x00; HEADER_END];
+        assert_eq!(header_checksum(&rom), 0xE7, "25 bytes of 0x00");
+        rom[TITLE_START] = 0x19;
+        assert_eq!(header_checksum(&rom), 0xCE);
+    }
+
+    #[test]
+    fn global_checksum_skips_itself() {
+        let mut rom = vec![0x01; HEADER_END];
+        rom[GLOBAL_CHECKSUM_ADDR] = 0xFF;
+        rom[GLOBAL_CHECKSUM_ADDR + 1] = 0xFF;
+        assert_eq!(global_checksum(&rom), HEADER_END as u16 - 2);
+    }
+}
@@ -0,0 +1,335 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::address_space::AddressSpace;
+use crate::ppu::Ppu;
+
+// FF51-FF54: Source and destination addresses, which are write only
+pub const HDMA1_ADDR: u16 = 0xFF51;
+pub const HDMA2_ADDR: u16 = 0xFF52;
+pub const HDMA3_ADDR: u16 = 0xFF53;
+pub const HDMA4_ADDR: u16 = 0xFF54;
+// FF55: Length, mode and start, or the remaining length while running
+pub const HDMA5_ADDR: u16 = 0xFF55;
+
+const HBLANK_MODE: u8 = 0b1000_0000;
+const LENGTH_MASK: u8 = 0b0111_1111;
+
+const BLOCK_SIZE: u8 = 0x10;
+const VRAM_SIZE: u16 = 0x2000;
+
+// Sources in VRAM can't be read while writing to it.
+const VRAM_START: u16 = 0x8000;
+const VRAM_END: u16 = 0x9FFF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TransferMode {
+    /// Copies every block at once.
+    GeneralPurpose,
+    /// Copies one block at the start of each HBlank.
+    HBlank,
+}
+
+/// CGB VRAM DMA controller
+///
+/// Copies blocks of 16 bytes from ROM, external RAM or WRAM into the selected
+/// VRAM bank, either all at once or one per HBlank. The CPU is paused while
+/// a block is copied. Like OAM DMA, the copy is driven by the `Mmu`, which
+/// reads the source bytes over the bus.
+pub struct Hdma {
+    source: u16,
+    // Offset into VRAM, where the next byte goes.
+    destination: u16,
+    // Blocks left minus one, as read back from HDMA5.
+    remaining: u8,
+    transfer: Option<TransferMode>,
+    // Bytes copied of the block being copied, if any.
+    block_index: Option<u8>,
+    in_hblank: bool,
+    ppu: Rc<RefCell<Ppu>>,
+}
+
+impl Hdma {
+    pub fn new(ppu: Rc<RefCell<Ppu>>) -> Self {
+        Self {
+            source: 0x0000,
+            destination: 0x0000,
+            remaining: LENGTH_MASK,
+            transfer: None,
+            block_index: None,
+            in_hblank: false,
+            ppu,
+        }
+    }
+
+    /// Whether a block is being copied, which keeps the CPU paused.
+    pub fn is_copying(&self) -> bool {
+        self.block_index.is_some()
+    }
+
+    /// Advances by half an M-cycle at normal speed, returning the address of
+    /// the byte to copy during it, if any. This rate is the same in double
+    /// speed mode, where a block takes 16 M-cycles rather than 8.
+    pub fn step(&mut self) -> Option<u16> {
+        let in_hblank = self.ppu.borrow().in_hblank();
+        if in_hblank && !self.in_hblank && self.transfer == Some(TransferMode::HBlank) {
+            self.block_index = Some(0);
+        }
+        self.in_hblank = in_hblank;
+        self.block_index.map(|_| self.source)
+    }
+
+    /// Stores a byte read from `source` in VRAM, moving on to the next one.
+    pub fn copy(&mut self, source: u16, byte: u8) {
+        let byte = if (VRAM_START..=VRAM_END).contains(&source) {
+            0xFF
+        } else {
+            byte
+        };
+        self.ppu.borrow_mut().write_vram_dma(self.destination, byte);
+        self.source = self.source.wrapping_add(1);
+        self.destination += 1;
+
+        let Some(index) = self.block_index.as_mut() else {
+            return;
+        };
+        *index += 1;
+        if *index < BLOCK_SIZE {
+            return;
+        }
+        self.block_index = None;
+        // The transfer ends early when the destination passes the end of VRAM.
+        if self.remaining == 0 || self.destination == VRAM_SIZE {
+            self.transfer = None;
+            self.remaining = LENGTH_MASK;
+            self.destination %= VRAM_SIZE;
+            return;
+        }
+        self.remaining -= 1;
+        if self.transfer == Some(TransferMode::GeneralPurpose) {
+            self.block_index = Some(0);
+        }
+    }
+
+    fn start(&mut self, byte: u8) {
+        self.remaining = byte & LENGTH_MASK;
+        if byte & HBLANK_MODE != 0 {
+            self.transfer = Some(TransferMode::HBlank);
+            // Only a new HBlank starts a block.
+            self.in_hblank = self.ppu.borrow().in_hblank();
+        } else {
+            self.transfer = Some(TransferMode::GeneralPurpose);
+            self.block_index = Some(0);
+        }
+    }
+}
+
+impl AddressSpace for Hdma {
+    fn accepts(&self, addr: u16) -> bool {
+        (HDMA1_ADDR..=HDMA5_ADDR).contains(&addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            HDMA1_ADDR => self.source = (byte as u16) << 8 | (self.source & 0x00FF),
+            // The lowest four bits are ignored, so blocks are aligned.
+            HDMA2_ADDR => self.source = (self.source & 0xFF00) | (byte & 0xF0) as u16,
+            HDMA3_ADDR => {
+                self.destination = ((byte & 0x1F) as u16) << 8 | (self.destination & 0x00FF)
+            }
+            HDMA4_ADDR => self.destination = (self.destination & 0xFF00) | (byte & 0xF0) as u16,
+            // Clearing bit 7 during an HBlank transfer cancels it.
+            HDMA5_ADDR
+                if self.transfer == Some(TransferMode::HBlank) && byte & HBLANK_MODE == 0 =>
+            {
+                self.transfer = None;
+                self.block_index = None;
+            }
+            HDMA5_ADDR => self.start(byte),
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            HDMA1_ADDR..=HDMA4_ADDR => 0xFF,
+            // Bit 7 is clear while a transfer is running.
+            HDMA5_ADDR if self.transfer.is_some() => self.remaining,
+            HDMA5_ADDR => HBLANK_MODE | self.remaining,
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::InterruptController;
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+    use crate::model::Model;
+    use crate::ppu::{Renderer, LCDC_ADDR, VBK_ADDR};
+
+    fn mmu() -> (Mmu, Rc<RefCell<Ppu>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let ppu = Rc::new(RefCell::new(Ppu::with_model(
+            interrupts,
+            Renderer::default(),
+            Model::Cgb,
+        )));
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(ppu.clone());
+        mmu.add_hdma(Rc::new(RefCell::new(Hdma::new(ppu.clone()))));
+        mmu.add_address_space(Ram::new(0x0000, 0x8000));
+        mmu.add_address_space(Ram::new(0xC000, 0x2000));
+        (mmu, ppu)
+    }
+
+    fn set_addresses(mmu: &mut Mmu, source: u16, destination: u16) {
+        mmu.set_byte(HDMA1_ADDR, (source >> 8) as u8);
+        mmu.set_byte(HDMA2_ADDR, source as u8);
+        mmu.set_byte(HDMA3_ADDR, (destination >> 8) as u8);
+        mmu.set_byte(HDMA4_ADDR, destination as u8);
+    }
+
+    fn fill_source(mmu: &mut Mmu, source: u16, length: u16) {
+        for offset in 0..length {
+            mmu.set_byte(source + offset, offset as u8 ^ 0x5A);
+        }
+    }
+
+    fn run_m_cycles(mmu: &mut Mmu, count: u32) {
+        for _ in 0..count {
+            mmu.tick(4);
+        }
+    }
+
+    #[test]
+    fn general_purpose_copies_all_blocks() {
+        let (mut mmu, _) = mmu();
+        fill_source(&mut mmu, 0xC000, 0x30);
+        set_addresses(&mut mmu, 0xC000, 0x8100);
+        mmu.set_byte(HDMA5_ADDR, 0x02);
+        assert!(mmu.stalls_cpu());
+
+        run_m_cycles(&mut mmu, 23);
+        assert!(mmu.stalls_cpu(), "Takes 8 M-cycles per block");
+        run_m_cycles(&mut mmu, 1);
+        assert!(!mmu.stalls_cpu());
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0xFF);
+        for offset in 0..0x30 {
+            assert_eq!(mmu.get_byte(0x8100 + offset), offset as u8 ^ 0x5A);
+        }
+        assert_eq!(mmu.get_byte(0x8130), 0x00);
+    }
+
+    #[test]
+    fn takes_as_long_in_double_speed() {
+        let (mut mmu, _) = mmu();
+        mmu.set_double_speed(true);
+        set_addresses(&mut mmu, 0xC000, 0x8000);
+        mmu.set_byte(HDMA5_ADDR, 0x00);
+        // 16 double speed M-cycles of 2 T-cycles at normal speed.
+        for _ in 0..15 {
+            mmu.tick(2);
+        }
+        assert!(mmu.stalls_cpu());
+        mmu.tick(2);
+        assert!(!mmu.stalls_cpu());
+    }
+
+    #[test]
+    fn ignores_low_address_bits() {
+        let (mut mmu, _) = mmu();
+        fill_source(&mut mmu, 0x4000, 0x10);
+        set_addresses(&mut mmu, 0x400F, 0xE10F);
+        mmu.set_byte(HDMA5_ADDR, 0x00);
+        run_m_cycles(&mut mmu, 8);
+        assert_eq!(mmu.get_byte(0x8100), 0x5A);
+        assert_eq!(mmu.get_byte(0x810F), 0x0F ^ 0x5A);
+    }
+
+    #[test]
+    fn writes_selected_vram_bank() {
+        let (mut mmu, _) = mmu();
+        fill_source(&mut mmu, 0xC000, 0x10);
+        mmu.set_byte(VBK_ADDR, 1);
+        set_addresses(&mut mmu, 0xC000, 0x9000);
+        mmu.set_byte(HDMA5_ADDR, 0x00);
+        run_m_cycles(&mut mmu, 8);
+
+        assert_eq!(mmu.get_byte(0x9000), 0x5A);
+        mmu.set_byte(VBK_ADDR, 0);
+        assert_eq!(mmu.get_byte(0x9000), 0x00);
+    }
+
+    #[test]
+    fn stops_at_end_of_vram() {
+        let (mut mmu, _) = mmu();
+        fill_source(&mut mmu, 0xC000, 0x20);
+        set_addresses(&mut mmu, 0xC000, 0x9FF0);
+        mmu.set_byte(HDMA5_ADDR, 0x01);
+        run_m_cycles(&mut mmu, 8);
+        assert!(!mmu.stalls_cpu());
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0xFF);
+        assert_eq!(mmu.get_byte(0x8000), 0x00);
+    }
+
+    #[test]
+    fn hblank_copies_one_block_per_hblank() {
+        let (mut mmu, ppu) = mmu();
+        fill_source(&mut mmu, 0xC000, 0x20);
+        set_addresses(&mut mmu, 0xC000, 0x8000);
+        mmu.set_byte(LCDC_ADDR, 0x80);
+        mmu.set_byte(HDMA5_ADDR, 0x81);
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0x01);
+        assert!(!mmu.stalls_cpu());
+
+        // Mode 2 and 3 of the first line take 252 dots.
+        run_m_cycles(&mut mmu, 252 / 4);
+        assert!(ppu.borrow().in_hblank());
+        assert!(mmu.stalls_cpu());
+        run_m_cycles(&mut mmu, 8);
+        assert!(!mmu.stalls_cpu());
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0x00);
+        assert_eq!(ppu.borrow_mut().get_byte(0x800F), 0x0F ^ 0x5A);
+        assert_eq!(ppu.borrow_mut().get_byte(0x8010), 0x00);
+
+        run_m_cycles(&mut mmu, 456 / 4);
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0xFF);
+        assert_eq!(ppu.borrow_mut().get_byte(0x801F), 0x1F ^ 0x5A);
+    }
+
+    #[test]
+    fn hblank_transfer_can_be_cancelled() {
+        let (mut mmu, ppu) = mmu();
+        fill_source(&mut mmu, 0xC000, 0x30);
+        set_addresses(&mut mmu, 0xC000, 0x8000);
+        mmu.set_byte(LCDC_ADDR, 0x80);
+        mmu.set_byte(HDMA5_ADDR, 0x82);
+        run_m_cycles(&mut mmu, 456 / 4);
+
+        mmu.set_byte(HDMA5_ADDR, 0x00);
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0x81, "Two blocks were left");
+        run_m_cycles(&mut mmu, 456 / 4);
+        assert_eq!(ppu.borrow_mut().get_byte(0x8010), 0x00);
+    }
+
+    #[test]
+    fn hblank_transfer_waits_while_lcd_is_off() {
+        let (mut mmu, _) = mmu();
+        set_addresses(&mut mmu, 0xC000, 0x8000);
+        mmu.set_byte(HDMA5_ADDR, 0x80);
+        run_m_cycles(&mut mmu, 456);
+        assert_eq!(mmu.get_byte(HDMA5_ADDR), 0x00);
+    }
+
+    #[test]
+    fn address_registers_are_write_only() {
+        let (mut mmu, _) = mmu();
+        set_addresses(&mut mmu, 0x1234, 0x8560);
+        for addr in HDMA1_ADDR..=HDMA4_ADDR {
+            assert_eq!(mmu.get_byte(addr), 0xFF);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use super::address_space::AddressSpace;
+
+// FF00-FF7F: I/O Registers
+const IO_START: u16 = 0xFF00;
+const IO_END: u16 = 0xFF7F;
+
+/// I/O registers not claimed by a peripheral, which are unmapped, so read
+/// as 0xFF and ignore writes.
+pub struct IoRegisters;
+
+impl IoRegisters {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for IoRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddressSpace for IoRegisters {
+    fn accepts(&self, addr: u16) -> bool {
+        (IO_START..=IO_END).contains(&addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, _byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        0xFF
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_whole_io_region() {
+        let io = IoRegisters::new();
+        assert!(!io.accepts(0xFEFF));
+        assert!(io.accepts(0xFF00));
+        assert!(io.accepts(0xFF7F));
+        assert!(!io.accepts(0xFF80));
+    }
+
+    #[test]
+    fn unmapped_registers_read_ff() {
+        let mut io = IoRegisters::new();
+        for addr in [0xFF01, 0xFF03, 0xFF10, 0xFF27, 0xFF46, 0xFF4C, 0xFF7F] {
+            io.set_byte(addr, 0x00);
+            assert_eq!(io.get_byte(addr), 0xFF, "{addr:#06X}");
+        }
+    }
+}
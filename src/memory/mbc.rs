@@ -0,0 +1,143 @@
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rom_only;
+mod rtc;
+
+pub use mbc1::Mbc1;
+pub use mbc2::Mbc2;
+pub use mbc3::Mbc3;
+pub use mbc5::Mbc5;
+pub use rom_only::RomOnly;
+pub use rtc::{Rtc, RtcClock, SAVE_FOOTER_SIZE};
+
+use super::address_space::AddressSpace;
+use super::cartridge_header::{CartridgeHeader, HeaderError, MbcKind};
+
+/// A memory bank controller, along with any external RAM on the cartridge.
+pub trait Mbc: AddressSpace {
+    /// Contents of external RAM, which is persisted on battery-backed carts.
+    fn ram(&self) -> &[u8];
+
+    fn ram_mut(&mut self) -> &mut [u8];
+
+    /// Whether writes to A000-BFFF currently reach the cartridge, rather
+    /// than being ignored.
+    fn ram_enabled(&self) -> bool {
+        true
+    }
+
+    /// Real time clock, if the cartridge has one.
+    fn rtc(&mut self) -> Option<&mut Rtc> {
+        None
+    }
+}
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// 0000-7FFF: ROM, and writes to MBC registers.
+pub fn is_rom_addr(addr: u16) -> bool {
+    addr < 0x8000
+}
+
+/// A000-BFFF: External RAM
+pub fn is_ram_addr(addr: u16) -> bool {
+    (0xA000..=0xBFFF).contains(&addr)
+}
+
+/// Reads from the given bank, wrapping bank numbers beyond the end of the
+/// data as the unconnected upper address lines would. Reads from missing
+/// data return 0xFF.
+pub fn read_banked(data: &[u8], bank_size: usize, bank: usize, offset: usize) -> u8 {
+    if data.is_empty() {
+        return 0xFF;
+    }
+    let banks = data.len().div_ceil(bank_size);
+    let index = (bank % banks) * bank_size + offset;
+    data.get(index).copied().unwrap_or(0xFF)
+}
+
+pub fn write_banked(data: &mut [u8], bank_size: usize, bank: usize, offset: usize, byte: u8) {
+    if data.is_empty() {
+        return;
+    }
+    let banks = data.len().div_ceil(bank_size);
+    let index = (bank % banks) * bank_size + offset;
+    if let Some(slot) = data.get_mut(index) {
+        *slot = byte;
+    }
+}
+
+/// Creates the memory bank controller declared by the cartridge header.
+pub fn create_mbc(header: &CartridgeHeader, rom: Vec<u8>) -> Result<Box<dyn Mbc>, HeaderError> {
+    let mbc = header
+        .mbc()
+        .ok_or(HeaderError::UnsupportedCartridgeType(header.cartridge_type))?;
+    let ram_size = header
+        .ram_size()
+        .ok_or(HeaderError::InvalidRamSize(header.ram_size_code))?;
+
+    log::debug!("Cartridge type {mbc:?}, {ram_size} bytes of RAM");
+
+    Ok(match mbc {
+        MbcKind::RomOnly => Box::new(RomOnly::new(rom, ram_size)),
+        MbcKind::Mbc1 => Box::new(Mbc1::new(rom, ram_size)),
+        MbcKind::Mbc2 => Box::new(Mbc2::new(rom)),
+        MbcKind::Mbc3 => Box::new(Mbc3::new(rom, ram_size, header.has_timer())),
+        MbcKind::Mbc5 => Box::new(Mbc5::new(rom, ram_size)),
+    })
+}
+
+/// ROM where the first two bytes of each bank hold the bank number.
+#[cfg(test)]
+fn banked_rom(banks: usize) -> Vec<u8> {
+    let mut rom = vec![0x00; banks * ROM_BANK_SIZE];
+    for bank in 0..banks {
+        let [high, low] = (bank as u16).to_be_bytes();
+        rom[bank * ROM_BANK_SIZE] = low;
+        rom[bank * ROM_BANK_SIZE + 1] = high;
+    }
+    rom
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_banked_wraps_bank_number() {
+        let data = [0x00, 0x01, 0x10, 0x11];
+        assert_eq!(read_banked(&data, 2, 1, 0), 0x10);
+        assert_eq!(read_banked(&data, 2, 3, 1), 0x11);
+    }
+
+    #[test]
+    fn read_banked_returns_ff_without_data() {
+        assert_eq!(read_banked(&[], 2, 0, 0), 0xFF);
+        assert_eq!(read_banked(&[0x00, 0x01, 0x02], 2, 1, 1), 0xFF);
+    }
+
+    fn create(cartridge_type: u8) -> Result<Box<dyn Mbc>, HeaderError> {
+        let mut rom = vec![0x00; 0x8000];
+        rom[0x0147] = cartridge_type;
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        create_mbc(&header, rom)
+    }
+
+    #[test]
+    fn rejects_unsupported_cartridge_type() {
+        assert!(matches!(
+            create(0xFC),
+            Err(HeaderError::UnsupportedCartridgeType(0xFC))
+        ));
+    }
+
+    #[test]
+    fn creates_mbc_for_supported_types() {
+        for cartridge_type in [0x00, 0x01, 0x05, 0x0F, 0x13, 0x19, 0x1E] {
+            assert!(create(cartridge_type).is_ok(), "{cartridge_type:#04X}");
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use super::{is_ram_addr, read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use crate::memory::address_space::AddressSpace;
+use crate::memory::boot_rom::nintendo_logo;
+
+// Nintendo logo in the header, used to detect multicart compilations which
+// contain a complete game, with its own header, every 256 KiB.
+const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0134;
+const MULTICART_SIZE: usize = 0x100000;
+const MULTICART_GAME_SIZE: usize = 0x40000;
+
+/// MBC1: up to 2 MiB ROM and 32 KiB RAM.
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 5-bit ROM bank register (BANK1)
+    bank1: u8,
+    // 2-bit register (BANK2) selecting the RAM bank or upper ROM bank bits.
+    bank2: u8,
+    // Banking mode: false = simple, true = advanced
+    mode: bool,
+    // MBC1M wires BANK2 to ROM address bit 18 rather than 19.
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        let multicart = Self::is_multicart(&rom);
+        Self {
+            rom,
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            multicart,
+        }
+    }
+
+    fn is_multicart(rom: &[u8]) -> bool {
+        if rom.len() != MULTICART_SIZE {
+            return false;
+        }
+        let second_game = MULTICART_GAME_SIZE + LOGO_START..MULTICART_GAME_SIZE + LOGO_END;
+        &rom[second_game] == nintendo_logo()
+    }
+
+    fn bank2_shift(&self) -> usize {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    fn low_bank(&self) -> usize {
+        if self.mode {
+            (self.bank2 as usize) << self.bank2_shift()
+        } else {
+            0
+        }
+    }
+
+    fn high_bank(&self) -> usize {
+        let bank1 = if self.multicart {
+            self.bank1 & 0x0F
+        } else {
+            self.bank1
+        };
+        ((self.bank2 as usize) << self.bank2_shift()) | bank1 as usize
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.mode {
+            self.bank2 as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl AddressSpace for Mbc1 {
+    fn accepts(&self, addr: u16) -> bool {
+        addr < 0x8000 || is_ram_addr(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Bank 0 can't be selected here, and is treated as 1.
+                self.bank1 = (byte & 0x1F).max(1);
+            }
+            0x4000..=0x5FFF => self.bank2 = byte & 0x03,
+            0x6000..=0x7FFF => self.mode = byte & 0x01 != 0,
+            0xA000..=0xBFFF => {
+                if self.ram_enabled {
+                    let offset = (addr - 0xA000) as usize;
+                    let bank = self.ram_bank();
+                    write_banked(&mut self.ram, RAM_BANK_SIZE, bank, offset, byte);
+                }
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => {
+                read_banked(&self.rom, ROM_BANK_SIZE, self.low_bank(), addr as usize)
+            }
+            0x4000..=0x7FFF => {
+                let offset = (addr - 0x4000) as usize;
+                read_banked(&self.rom, ROM_BANK_SIZE, self.high_bank(), offset)
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                let offset = (addr - 0xA000) as usize;
+                read_banked(&self.ram, RAM_BANK_SIZE, self.ram_bank(), offset)
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+}
+
+impl Mbc for Mbc1 {
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::mbc::banked_rom;
+
+    #[test]
+    fn defaults_to_bank_1() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0);
+        assert_eq!(mbc.get_byte(0x0000), 0);
+        assert_eq!(mbc.get_byte(0x4000), 1);
+    }
+
+    #[test]
+    fn switches_rom_bank() {
+        let mut mbc = Mbc1::new(banked_rom(32), 0);
+        mbc.set_byte(0x2000, 0x1F);
+        assert_eq!(mbc.get_byte(0x4000), 0x1F);
+    }
+
+    #[test]
+    fn bank_0_selects_bank_1() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0);
+        mbc.set_byte(0x2000, 0x00);
+        assert_eq!(mbc.get_byte(0x4000), 1);
+        // Only the lower 5 bits are compared against 0.
+        mbc.set_byte(0x2000, 0x20);
+        assert_eq!(mbc.get_byte(0x4000), 1);
+    }
+
+    #[test]
+    fn rom_bank_is_masked_to_rom_size() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0);
+        mbc.set_byte(0x2000, 0x06);
+        assert_eq!(mbc.get_byte(0x4000), 2);
+    }
+
+    #[test]
+    fn bank2_selects_upper_rom_bits() {
+        let mut mbc = Mbc1::new(banked_rom(128), 0);
+        mbc.set_byte(0x2000, 0x02);
+        mbc.set_byte(0x4000, 0x03);
+        assert_eq!(mbc.get_byte(0x4000), 0x62);
+        assert_eq!(mbc.get_byte(0x0000), 0x00, "Mode 0 keeps bank 0 fixed");
+
+        mbc.set_byte(0x6000, 0x01);
+        assert_eq!(mbc.get_byte(0x0000), 0x60, "Mode 1 banks 0000-3FFF");
+    }
+
+    #[test]
+    fn ram_disabled_by_default() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0x2000);
+        mbc.set_byte(0xA000, 0x12);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn ram_enabled_with_0a() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0x2000);
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA000, 0x12);
+        assert_eq!(mbc.get_byte(0xA000), 0x12);
+
+        mbc.set_byte(0x0000, 0x00);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn ram_banking_only_in_mode_1() {
+        let mut mbc = Mbc1::new(banked_rom(4), 0x8000);
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA000, 0x01);
+
+        mbc.set_byte(0x4000, 0x02);
+        assert_eq!(mbc.get_byte(0xA000), 0x01, "Mode 0 always uses bank 0");
+
+        mbc.set_byte(0x6000, 0x01);
+        mbc.set_byte(0xA000, 0x03);
+        mbc.set_byte(0x4000, 0x00);
+        assert_eq!(mbc.get_byte(0xA000), 0x01);
+        mbc.set_byte(0x4000, 0x02);
+        assert_eq!(mbc.get_byte(0xA000), 0x03);
+    }
+
+    #[test]
+    fn detects_multicart() {
+        let mut data = banked_rom(64);
+        for game in 0..4 {
+            let start = game * MULTICART_GAME_SIZE;
+            data[start + LOGO_START..start + LOGO_END].copy_from_slice(nintendo_logo());
+        }
+        let mut mbc = Mbc1::new(data, 0);
+        assert!(mbc.multicart);
+
+        // BANK2 selects a 256 KiB game, and BANK1 only uses 4 bits.
+        mbc.set_byte(0x4000, 0x01);
+        mbc.set_byte(0x2000, 0x12);
+        assert_eq!(mbc.get_byte(0x4000), 0x12);
+        mbc.set_byte(0x6000, 0x01);
+        assert_eq!(mbc.get_byte(0x0000), 0x10);
+    }
+
+    #[test]
+    fn regular_1mib_rom_is_not_multicart() {
+        let mut data = banked_rom(64);
+        data[LOGO_START..LOGO_END].copy_from_slice(nintendo_logo());
+        let mbc = Mbc1::new(data, 0);
+        assert!(!mbc.multicart);
+    }
+}
@@ -0,0 +1,137 @@
+use super::{is_ram_addr, read_banked, Mbc, ROM_BANK_SIZE};
+use crate::memory::address_space::AddressSpace;
+
+// 512 half-bytes of RAM are built into the MBC2 chip.
+const RAM_SIZE: usize = 0x200;
+
+/// MBC2: up to 256 KiB ROM with built-in 512x4 bit RAM.
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: vec![0x00; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+
+    // Only the bottom 9 bits of the address are used, so RAM is echoed
+    // throughout A000-BFFF.
+    fn ram_index(addr: u16) -> usize {
+        (addr as usize) & (RAM_SIZE - 1)
+    }
+}
+
+impl AddressSpace for Mbc2 {
+    fn accepts(&self, addr: u16) -> bool {
+        addr < 0x8000 || is_ram_addr(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            // Bit 8 of the address selects between the two registers.
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => {
+                self.ram_enabled = byte & 0x0F == 0x0A;
+            }
+            0x0000..=0x3FFF => self.rom_bank = (byte & 0x0F).max(1),
+            0x4000..=0x7FFF => {}
+            0xA000..=0xBFFF => {
+                if self.ram_enabled {
+                    self.ram[Self::ram_index(addr)] = byte & 0x0F;
+                }
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => read_banked(&self.rom, ROM_BANK_SIZE, 0, addr as usize),
+            0x4000..=0x7FFF => {
+                let offset = (addr - 0x4000) as usize;
+                read_banked(&self.rom, ROM_BANK_SIZE, self.rom_bank as usize, offset)
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                // Upper nibble is not connected, and reads as 1s.
+                0xF0 | self.ram[Self::ram_index(addr)]
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+}
+
+impl Mbc for Mbc2 {
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::mbc::banked_rom;
+
+    #[test]
+    fn switches_rom_bank_when_address_bit_8_set() {
+        let mut mbc = Mbc2::new(banked_rom(16));
+        mbc.set_byte(0x2100, 0x0F);
+        assert_eq!(mbc.get_byte(0x4000), 0x0F);
+
+        // Address bit 8 clear writes to the RAM enable register instead.
+        mbc.set_byte(0x2000, 0x03);
+        assert_eq!(mbc.get_byte(0x4000), 0x0F);
+    }
+
+    #[test]
+    fn bank_0_selects_bank_1() {
+        let mut mbc = Mbc2::new(banked_rom(16));
+        mbc.set_byte(0x0100, 0x00);
+        assert_eq!(mbc.get_byte(0x4000), 1);
+    }
+
+    #[test]
+    fn ram_enabled_when_address_bit_8_clear() {
+        let mut mbc = Mbc2::new(banked_rom(2));
+        mbc.set_byte(0xA000, 0x05);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA000, 0x05);
+        assert_eq!(mbc.get_byte(0xA000), 0xF5);
+    }
+
+    #[test]
+    fn ram_stores_half_bytes() {
+        let mut mbc = Mbc2::new(banked_rom(2));
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA010, 0xAB);
+        assert_eq!(mbc.get_byte(0xA010), 0xFB);
+    }
+
+    #[test]
+    fn ram_is_echoed() {
+        let mut mbc = Mbc2::new(banked_rom(2));
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA001, 0x07);
+        assert_eq!(mbc.get_byte(0xA201), 0xF7);
+        assert_eq!(mbc.get_byte(0xBE01), 0xF7);
+    }
+}
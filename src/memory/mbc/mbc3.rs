@@ -0,0 +1,173 @@
+use super::{is_ram_addr, read_banked, write_banked, Mbc, Rtc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use crate::memory::address_space::AddressSpace;
+
+/// MBC3: up to 2 MiB ROM and 32 KiB RAM.
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    // 00-03 selects a RAM bank, 08-0C selects an RTC register.
+    ram_bank: u8,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rtc: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rtc: has_rtc.then(Rtc::new),
+        }
+    }
+
+    /// RTC register selected by the RAM bank register, if any.
+    fn selected_rtc(&mut self) -> Option<&mut Rtc> {
+        match self.ram_bank {
+            0x08..=0x0C => self.rtc.as_mut(),
+            _ => None,
+        }
+    }
+}
+
+impl AddressSpace for Mbc3 {
+    fn accepts(&self, addr: u16) -> bool {
+        addr < 0x8000 || is_ram_addr(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (byte & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x0F,
+            0x6000..=0x7FFF => {
+                if let Some(rtc) = self.rtc.as_mut() {
+                    rtc.write_latch(byte);
+                }
+            }
+            0xA000..=0xBFFF if !self.ram_enabled => {}
+            0xA000..=0xBFFF => {
+                let register = self.ram_bank;
+                if let Some(rtc) = self.selected_rtc() {
+                    rtc.write(register, byte);
+                } else if self.ram_bank <= 0x03 {
+                    let offset = (addr - 0xA000) as usize;
+                    let bank = self.ram_bank as usize;
+                    write_banked(&mut self.ram, RAM_BANK_SIZE, bank, offset, byte);
+                }
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => read_banked(&self.rom, ROM_BANK_SIZE, 0, addr as usize),
+            0x4000..=0x7FFF => {
+                let offset = (addr - 0x4000) as usize;
+                read_banked(&self.rom, ROM_BANK_SIZE, self.rom_bank as usize, offset)
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                let register = self.ram_bank;
+                if let Some(rtc) = self.selected_rtc() {
+                    return rtc.read(register);
+                }
+                if self.ram_bank > 0x03 {
+                    return 0xFF;
+                }
+                let offset = (addr - 0xA000) as usize;
+                read_banked(&self.ram, RAM_BANK_SIZE, self.ram_bank as usize, offset)
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        if let Some(rtc) = self.rtc.as_mut() {
+            rtc.tick(cycles);
+        }
+    }
+}
+
+impl Mbc for Mbc3 {
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn rtc(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::mbc::banked_rom;
+
+    #[test]
+    fn switches_rom_bank_with_7_bits() {
+        let mut mbc = Mbc3::new(banked_rom(128), 0, false);
+        mbc.set_byte(0x2000, 0x7F);
+        assert_eq!(mbc.get_byte(0x4000), 0x7F);
+        mbc.set_byte(0x2000, 0x00);
+        assert_eq!(mbc.get_byte(0x4000), 0x01);
+    }
+
+    #[test]
+    fn switches_ram_bank() {
+        let mut mbc = Mbc3::new(banked_rom(2), 0x8000, false);
+        mbc.set_byte(0x0000, 0x0A);
+        for bank in 0..4 {
+            mbc.set_byte(0x4000, bank);
+            mbc.set_byte(0xA000, bank + 0x10);
+        }
+        for bank in 0..4 {
+            mbc.set_byte(0x4000, bank);
+            assert_eq!(mbc.get_byte(0xA000), bank + 0x10);
+        }
+    }
+
+    #[test]
+    fn ram_disabled_reads_ff() {
+        let mut mbc = Mbc3::new(banked_rom(2), 0x2000, false);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn rtc_registers_selected_by_ram_bank() {
+        let mut mbc = Mbc3::new(banked_rom(2), 0x2000, true);
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0xA000, 0x11);
+
+        mbc.set_byte(0x4000, 0x09);
+        mbc.set_byte(0xA000, 0x2A);
+        mbc.set_byte(0x6000, 0x00);
+        mbc.set_byte(0x6000, 0x01);
+        assert_eq!(mbc.get_byte(0xA000), 0x2A);
+
+        mbc.set_byte(0x4000, 0x00);
+        assert_eq!(mbc.get_byte(0xA000), 0x11);
+    }
+
+    #[test]
+    fn rtc_registers_read_ff_without_rtc() {
+        let mut mbc = Mbc3::new(banked_rom(2), 0x2000, false);
+        mbc.set_byte(0x0000, 0x0A);
+        mbc.set_byte(0x4000, 0x08);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+    }
+}
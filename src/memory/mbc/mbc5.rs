@@ -0,0 +1,122 @@
+use super::{is_ram_addr, read_banked, write_banked, Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+use crate::memory::address_space::AddressSpace;
+
+/// MBC5: up to 8 MiB ROM and 128 KiB RAM.
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 9-bit ROM bank number. Unlike other MBCs, bank 0 can be selected.
+    rom_bank: u16,
+    ram_bank: u8,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0x00; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+}
+
+impl AddressSpace for Mbc5 {
+    fn accepts(&self, addr: u16) -> bool {
+        addr < 0x8000 || is_ram_addr(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | byte as u16,
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((byte as u16 & 0x01) << 8);
+            }
+            0x4000..=0x5FFF => self.ram_bank = byte & 0x0F,
+            0x6000..=0x7FFF => {}
+            0xA000..=0xBFFF => {
+                if self.ram_enabled {
+                    let offset = (addr - 0xA000) as usize;
+                    let bank = self.ram_bank as usize;
+                    write_banked(&mut self.ram, RAM_BANK_SIZE, bank, offset, byte);
+                }
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => read_banked(&self.rom, ROM_BANK_SIZE, 0, addr as usize),
+            0x4000..=0x7FFF => {
+                let offset = (addr - 0x4000) as usize;
+                read_banked(&self.rom, ROM_BANK_SIZE, self.rom_bank as usize, offset)
+            }
+            0xA000..=0xBFFF => {
+                if !self.ram_enabled {
+                    return 0xFF;
+                }
+                let offset = (addr - 0xA000) as usize;
+                read_banked(&self.ram, RAM_BANK_SIZE, self.ram_bank as usize, offset)
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+}
+
+impl Mbc for Mbc5 {
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::mbc::banked_rom;
+
+    #[test]
+    fn bank_0_can_be_selected() {
+        let mut mbc = Mbc5::new(banked_rom(4), 0);
+        mbc.set_byte(0x2000, 0x00);
+        assert_eq!(mbc.get_byte(0x4000), 0x00);
+    }
+
+    #[test]
+    fn uses_9_bit_rom_bank() {
+        let mut mbc = Mbc5::new(banked_rom(512), 0);
+        mbc.set_byte(0x2000, 0x23);
+        mbc.set_byte(0x3000, 0x01);
+        assert_eq!(mbc.get_byte(0x4000), 0x23);
+        assert_eq!(mbc.get_byte(0x4001), 0x01);
+
+        mbc.set_byte(0x3000, 0x00);
+        assert_eq!(mbc.get_byte(0x4000), 0x23);
+        assert_eq!(mbc.get_byte(0x4001), 0x00);
+    }
+
+    #[test]
+    fn switches_between_16_ram_banks() {
+        let mut mbc = Mbc5::new(banked_rom(2), 0x20000);
+        mbc.set_byte(0x0000, 0x0A);
+        for bank in 0..16 {
+            mbc.set_byte(0x4000, bank);
+            mbc.set_byte(0xA000, bank + 0x20);
+        }
+        for bank in 0..16 {
+            mbc.set_byte(0x4000, bank);
+            assert_eq!(mbc.get_byte(0xA000), bank + 0x20);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::{is_ram_addr, is_rom_addr, read_banked, write_banked, Mbc, RAM_BANK_SIZE};
+use crate::memory::address_space::AddressSpace;
+
+/// A cartridge with up to 32 KiB of ROM and, optionally, 8 KiB of RAM.
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0x00; ram_size.min(RAM_BANK_SIZE)],
+        }
+    }
+}
+
+impl AddressSpace for RomOnly {
+    fn accepts(&self, addr: u16) -> bool {
+        is_rom_addr(addr) || is_ram_addr(addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        if is_ram_addr(addr) {
+            write_banked(
+                &mut self.ram,
+                RAM_BANK_SIZE,
+                0,
+                (addr - 0xA000) as usize,
+                byte,
+            );
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        if is_ram_addr(addr) {
+            read_banked(&self.ram, RAM_BANK_SIZE, 0, (addr - 0xA000) as usize)
+        } else {
+            self.rom.get(addr as usize).copied().unwrap_or(0xFF)
+        }
+    }
+}
+
+impl Mbc for RomOnly {
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_rom() {
+        let mut rom = vec![0x00; 0x8000];
+        rom[0x7FFF] = 0x42;
+        let mut mbc = RomOnly::new(rom, 0);
+        assert_eq!(mbc.get_byte(0x7FFF), 0x42);
+    }
+
+    #[test]
+    fn ignores_writes_to_rom() {
+        let mut mbc = RomOnly::new(vec![0x00; 0x8000], 0);
+        mbc.set_byte(0x2000, 0x01);
+        assert_eq!(mbc.get_byte(0x2000), 0x00);
+    }
+
+    #[test]
+    fn missing_ram_reads_ff() {
+        let mut mbc = RomOnly::new(vec![0x00; 0x8000], 0);
+        mbc.set_byte(0xA000, 0x12);
+        assert_eq!(mbc.get_byte(0xA000), 0xFF);
+    }
+
+    #[test]
+    fn reads_and_writes_ram() {
+        let mut mbc = RomOnly::new(vec![0x00; 0x8000], 0x2000);
+        mbc.set_byte(0xBFFF, 0x12);
+        assert_eq!(mbc.get_byte(0xBFFF), 0x12);
+    }
+}
@@ -0,0 +1,388 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::CLOCK_SPEED;
+
+// The RTC is driven by its own 32.768 kHz crystal, but counting whole seconds
+// of CPU cycles keeps it in step with the emulated machine.
+const CYCLES_PER_SECOND: u32 = CLOCK_SPEED;
+
+/// Size of the footer appended to save RAM: the live and latched registers
+/// as 32-bit values, followed by a 64-bit UNIX timestamp.
+pub const SAVE_FOOTER_SIZE: usize = 48;
+
+// Registers are numbered by the RAM bank value used to select them, 08-0C.
+const SECONDS: usize = 0;
+const MINUTES: usize = 1;
+const HOURS: usize = 2;
+const DAYS_LOW: usize = 3;
+const DAYS_HIGH: usize = 4;
+const REGISTER_COUNT: usize = 5;
+
+// Bits of each register that exist in hardware.
+const REGISTER_MASKS: [u8; REGISTER_COUNT] = [0x3F, 0x3F, 0x1F, 0xFF, 0xC1];
+
+// Bits of the upper day counter register (DH).
+const DAY_BIT_8: u8 = 0b0000_0001;
+const HALT: u8 = 0b0100_0000;
+const DAY_CARRY: u8 = 0b1000_0000;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DAY_COUNTER_SIZE: u64 = 512;
+
+/// Time source used to advance the clock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RtcClock {
+    /// Counts emulated cycles, so time only passes while the game runs.
+    #[default]
+    Emulated,
+    /// Follows the host's clock, including while the emulator is closed.
+    WallClock,
+}
+
+/// MBC3 real time clock.
+pub struct Rtc {
+    clock: RtcClock,
+    registers: [u8; REGISTER_COUNT],
+    latched: [u8; REGISTER_COUNT],
+    // Cycles since the seconds register last incremented.
+    cycles: u32,
+    // Time up to which the registers have been advanced, in wall clock mode.
+    last_sync: SystemTime,
+    // A write of 0x00 to 6000-7FFF arms the latch, and 0x01 triggers it.
+    latch_armed: bool,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            clock: RtcClock::default(),
+            registers: [0x00; REGISTER_COUNT],
+            latched: [0x00; REGISTER_COUNT],
+            cycles: 0,
+            last_sync: SystemTime::now(),
+            latch_armed: false,
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: RtcClock) {
+        self.clock = clock;
+    }
+
+    fn is_halted(&self) -> bool {
+        self.registers[DAYS_HIGH] & HALT != 0
+    }
+
+    /// Reads the latched copy of register 08-0C.
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[Self::index(register)]
+    }
+
+    /// Writes register 08-0C of the running clock.
+    pub fn write(&mut self, register: u8, byte: u8) {
+        self.sync();
+        let index = Self::index(register);
+        let byte = byte & REGISTER_MASKS[index];
+        if index == SECONDS {
+            // Writing the seconds resets the sub-second counter.
+            self.cycles = 0;
+            self.last_sync = SystemTime::now();
+        }
+        self.registers[index] = byte;
+        self.latched[index] = byte;
+    }
+
+    /// Handles a write to 6000-7FFF, latching on 0x00 followed by 0x01.
+    pub fn write_latch(&mut self, byte: u8) {
+        if self.latch_armed && byte == 0x01 {
+            self.sync();
+            self.latched = self.registers;
+        }
+        self.latch_armed = byte == 0x00;
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if self.clock != RtcClock::Emulated || self.is_halted() {
+            return;
+        }
+        self.cycles += cycles as u32;
+        if self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.advance(1);
+        }
+    }
+
+    /// Catches up with the host's clock, in wall clock mode.
+    fn sync(&mut self) {
+        if self.clock != RtcClock::WallClock {
+            return;
+        }
+        let now = SystemTime::now();
+        let elapsed = now.duration_since(self.last_sync).unwrap_or_default();
+        if self.is_halted() {
+            self.last_sync = now;
+            return;
+        }
+        let seconds = elapsed.as_secs();
+        self.last_sync += Duration::from_secs(seconds);
+        self.advance(seconds);
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        // Registers set out of range count up to their bit width before
+        // wrapping, without carrying, so step through those one at a time.
+        while seconds > 0 && !self.is_in_range() {
+            self.advance_second();
+            seconds -= 1;
+        }
+        if seconds == 0 {
+            return;
+        }
+
+        let total = self.total_seconds() + seconds;
+        let days = total / SECONDS_PER_DAY;
+        let time = total % SECONDS_PER_DAY;
+
+        self.registers[SECONDS] = (time % 60) as u8;
+        self.registers[MINUTES] = (time / 60 % 60) as u8;
+        self.registers[HOURS] = (time / 3600) as u8;
+        self.set_days(days);
+    }
+
+    fn advance_second(&mut self) {
+        if self.registers[SECONDS] != 59 {
+            self.registers[SECONDS] = (self.registers[SECONDS] + 1) & REGISTER_MASKS[SECONDS];
+            return;
+        }
+        self.registers[SECONDS] = 0;
+        if self.registers[MINUTES] != 59 {
+            self.registers[MINUTES] = (self.registers[MINUTES] + 1) & REGISTER_MASKS[MINUTES];
+            return;
+        }
+        self.registers[MINUTES] = 0;
+        if self.registers[HOURS] != 23 {
+            self.registers[HOURS] = (self.registers[HOURS] + 1) & REGISTER_MASKS[HOURS];
+            return;
+        }
+        self.registers[HOURS] = 0;
+        self.set_days(self.days() + 1);
+    }
+
+    fn is_in_range(&self) -> bool {
+        self.registers[SECONDS] < 60 && self.registers[MINUTES] < 60 && self.registers[HOURS] < 24
+    }
+
+    fn days(&self) -> u64 {
+        let high = (self.registers[DAYS_HIGH] & DAY_BIT_8) as u64;
+        (high << 8) | self.registers[DAYS_LOW] as u64
+    }
+
+    /// Sets the 9-bit day counter, setting the carry bit if it overflows.
+    fn set_days(&mut self, days: u64) {
+        if days >= DAY_COUNTER_SIZE {
+            self.registers[DAYS_HIGH] |= DAY_CARRY;
+        }
+        let days = days % DAY_COUNTER_SIZE;
+        self.registers[DAYS_LOW] = days as u8;
+        self.registers[DAYS_HIGH] = (self.registers[DAYS_HIGH] & !DAY_BIT_8) | (days >> 8) as u8;
+    }
+
+    fn total_seconds(&self) -> u64 {
+        self.days() * SECONDS_PER_DAY
+            + self.registers[HOURS] as u64 * 3600
+            + self.registers[MINUTES] as u64 * 60
+            + self.registers[SECONDS] as u64
+    }
+
+    fn index(register: u8) -> usize {
+        match register {
+            0x08..=0x0C => (register - 0x08) as usize,
+            _ => panic!("Invalid RTC register {register:#04X}"),
+        }
+    }
+
+    /// Serialises the clock into the footer stored after save RAM.
+    pub fn save_footer(&mut self) -> [u8; SAVE_FOOTER_SIZE] {
+        self.sync();
+        let mut footer = [0x00; SAVE_FOOTER_SIZE];
+        let registers = self.registers.iter().chain(self.latched.iter());
+        for (chunk, register) in footer.chunks_exact_mut(4).zip(registers) {
+            chunk.copy_from_slice(&(*register as u32).to_le_bytes());
+        }
+        let timestamp = match self.clock {
+            RtcClock::WallClock => self.last_sync,
+            RtcClock::Emulated => SystemTime::now(),
+        };
+        let timestamp = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        footer[40..].copy_from_slice(&timestamp.to_le_bytes());
+        footer
+    }
+
+    /// Restores the clock from a save footer. In wall clock mode, the time
+    /// since the save was written is added on the next access.
+    pub fn load_save_footer(&mut self, footer: &[u8; SAVE_FOOTER_SIZE]) {
+        let mut values = footer[..40]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()) as u8);
+        for registers in [&mut self.registers, &mut self.latched] {
+            for (register, mask) in registers.iter_mut().zip(REGISTER_MASKS) {
+                *register = values.next().unwrap() & mask;
+            }
+        }
+        let timestamp = u64::from_le_bytes(footer[40..].try_into().unwrap());
+        self.last_sync = UNIX_EPOCH + Duration::from_secs(timestamp);
+        self.cycles = 0;
+    }
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn latch(rtc: &mut Rtc) {
+        rtc.write_latch(0x00);
+        rtc.write_latch(0x01);
+    }
+
+    fn set_time(rtc: &mut Rtc, days: u16, hours: u8, minutes: u8, seconds: u8) {
+        rtc.write(0x08, seconds);
+        rtc.write(0x09, minutes);
+        rtc.write(0x0A, hours);
+        rtc.write(0x0B, days as u8);
+        rtc.write(0x0C, (days >> 8) as u8);
+    }
+
+    fn read_all(rtc: &Rtc) -> [u8; REGISTER_COUNT] {
+        [0x08, 0x09, 0x0A, 0x0B, 0x0C].map(|register| rtc.read(register))
+    }
+
+    fn run_seconds(rtc: &mut Rtc, seconds: u32) {
+        for _ in 0..seconds {
+            for _ in 0..(CYCLES_PER_SECOND / 256) {
+                rtc.tick(128);
+                rtc.tick(128);
+            }
+        }
+    }
+
+    #[test]
+    fn advances_from_emulated_cycles() {
+        let mut rtc = Rtc::new();
+        rtc.tick(255);
+        run_seconds(&mut rtc, 2);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 2);
+    }
+
+    #[test]
+    fn reads_return_latched_values() {
+        let mut rtc = Rtc::new();
+        latch(&mut rtc);
+        run_seconds(&mut rtc, 1);
+        assert_eq!(rtc.read(0x08), 0);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 1);
+    }
+
+    #[test]
+    fn latch_requires_0_then_1() {
+        let mut rtc = Rtc::new();
+        run_seconds(&mut rtc, 1);
+        rtc.write_latch(0x01);
+        assert_eq!(rtc.read(0x08), 0);
+        rtc.write_latch(0x00);
+        rtc.write_latch(0x02);
+        rtc.write_latch(0x01);
+        assert_eq!(rtc.read(0x08), 0);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 1);
+    }
+
+    #[test]
+    fn carries_into_minutes_hours_and_days() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 0x0FF, 23, 59, 59);
+        run_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+        assert_eq!(read_all(&rtc), [0, 0, 0, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn day_counter_overflow_sets_carry() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 511, 23, 59, 59);
+        run_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+        assert_eq!(read_all(&rtc), [0, 0, 0, 0x00, DAY_CARRY]);
+    }
+
+    #[test]
+    fn halt_stops_the_clock() {
+        let mut rtc = Rtc::new();
+        rtc.write(0x0C, HALT);
+        run_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 0);
+    }
+
+    #[test]
+    fn out_of_range_values_wrap_without_carry() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 0, 0, 0, 63);
+        run_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+        assert_eq!(read_all(&rtc), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn advances_many_seconds_at_once() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 1, 2, 3, 4);
+        rtc.advance(SECONDS_PER_DAY * 300 + 3600 + 60 + 1);
+        latch(&mut rtc);
+        assert_eq!(read_all(&rtc), [5, 4, 3, 45, 0x01]);
+    }
+
+    #[test]
+    fn writes_mask_unused_bits() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 0xFFFF, 0xFF, 0xFF, 0xFF);
+        assert_eq!(read_all(&rtc), [0x3F, 0x3F, 0x1F, 0xFF, 0xC1]);
+    }
+
+    #[test]
+    fn save_footer_round_trips() {
+        let mut rtc = Rtc::new();
+        set_time(&mut rtc, 0x123, 4, 5, 6);
+        let footer = rtc.save_footer();
+        assert_eq!(&footer[..4], &[6, 0, 0, 0]);
+        assert_eq!(&footer[20..24], &[6, 0, 0, 0]);
+
+        let mut restored = Rtc::new();
+        restored.load_save_footer(&footer);
+        assert_eq!(read_all(&restored), [6, 5, 4, 0x23, 0x01]);
+    }
+
+    #[test]
+    fn wall_clock_catches_up_with_time_since_save() {
+        let mut rtc = Rtc::new();
+        let mut footer = rtc.save_footer();
+        let timestamp = SystemTime::now() - Duration::from_secs(SECONDS_PER_DAY + 90);
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        footer[40..].copy_from_slice(&timestamp.to_le_bytes());
+
+        rtc.set_clock(RtcClock::WallClock);
+        rtc.load_save_footer(&footer);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x09), 1);
+        assert_eq!(rtc.read(0x0B), 1);
+    }
+}
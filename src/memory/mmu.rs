@@ -1,9 +1,49 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use super::address_space::AddressSpace;
+use super::hdma::Hdma;
+use super::oam_dma::OamDma;
 use super::void::Void;
+use super::work_ram::WorkRam;
+
+const PAGE_COUNT: usize = 0x100;
+const PAGE_SIZE: usize = 0x100;
+
+// Page table entry for addresses no space accepts.
+const UNMAPPED: u8 = u8::MAX;
+
+// FF00-FFFF: I/O registers, HRAM and IE, on the CPU's internal bus.
+const INTERNAL_BUS_START: u16 = 0xFF00;
+
+// FF80-FFFE: High RAM
+const HRAM_START: u16 = 0xFF80;
+const HRAM_END: u16 = 0xFFFE;
+const HRAM_SIZE: usize = (HRAM_END - HRAM_START + 1) as usize;
 
+/// Which spaces handle the addresses in a 256 byte page.
+enum Page {
+    /// The whole page belongs to a single space, e.g. ROM and VRAM.
+    Owned(u8),
+    /// The page is shared by several spaces, e.g. the I/O registers and IE.
+    Split(Box<[u8; PAGE_SIZE]>),
+}
+
+/// Routes memory accesses to address spaces through a page table, built as
+/// spaces are added. As only the first space accepting an address receives
+/// it, `accepts` must not change once a space has been added.
+///
+/// Work RAM and HRAM, which most accesses outside ROM go to, are held
+/// directly rather than behind the page table, and take priority over it.
 pub struct Mmu {
     spaces: Vec<Box<dyn AddressSpace>>,
+    pages: Vec<Page>,
+    work_ram: Option<WorkRam>,
+    hram: Option<[u8; HRAM_SIZE]>,
     void: Box<dyn AddressSpace>,
+    oam_dma: Option<Rc<RefCell<OamDma>>>,
+    hdma: Option<Rc<RefCell<Hdma>>>,
+    double_speed: bool,
 }
 
 impl Mmu {
@@ -12,14 +52,114 @@ impl Mmu {
     }
 
     pub fn add_address_space<Space: AddressSpace + 'static>(&mut self, address_space: Space) {
+        let index = self.spaces.len();
+        assert!(index < UNMAPPED as usize, "Too many address spaces");
+        let index = index as u8;
+
+        for (page_number, page) in self.pages.iter_mut().enumerate() {
+            let base = (page_number * PAGE_SIZE) as u16;
+            let owners: [u8; PAGE_SIZE] = std::array::from_fn(|offset| {
+                let addr = base + offset as u16;
+                match Self::page_owner(page, addr) {
+                    UNMAPPED if address_space.accepts(addr) => index,
+                    owner => owner,
+                }
+            });
+            *page = if owners.iter().all(|owner| *owner == owners[0]) {
+                Page::Owned(owners[0])
+            } else {
+                Page::Split(Box::new(owners))
+            };
+        }
+
         self.spaces.push(Box::new(address_space));
     }
 
-    fn get_space(&mut self, addr: u16) -> &mut Box<dyn AddressSpace> {
-        self.spaces
-            .iter_mut()
-            .find(|space| space.accepts(addr))
-            .unwrap_or(&mut self.void)
+    /// Adds work RAM, along with echo RAM and SVBK on CGB.
+    pub fn add_work_ram(&mut self, work_ram: WorkRam) {
+        self.work_ram = Some(work_ram);
+    }
+
+    /// Adds high RAM at FF80-FFFE.
+    pub fn add_hram(&mut self) {
+        self.hram = Some([0x00; HRAM_SIZE]);
+    }
+
+    /// Adds the OAM DMA controller, which takes over the external bus while
+    /// it copies into OAM.
+    pub fn add_oam_dma(&mut self, oam_dma: Rc<RefCell<OamDma>>) {
+        self.add_address_space(oam_dma.clone());
+        self.oam_dma = Some(oam_dma);
+    }
+
+    /// Adds the CGB VRAM DMA controller, which pauses the CPU while it
+    /// copies into VRAM.
+    pub fn add_hdma(&mut self, hdma: Rc<RefCell<Hdma>>) {
+        self.add_address_space(hdma.clone());
+        self.hdma = Some(hdma);
+    }
+
+    /// Whether the CPU is kept off the bus by an OAM DMA transfer, leaving it
+    /// only the addresses on its internal bus.
+    fn is_blocked(&self, addr: u16) -> bool {
+        addr < INTERNAL_BUS_START
+            && self
+                .oam_dma
+                .as_ref()
+                .is_some_and(|oam_dma| oam_dma.borrow().is_active())
+    }
+
+    fn step_oam_dma(&mut self, oam_dma: &RefCell<OamDma>) {
+        let Some(source) = oam_dma.borrow_mut().step() else {
+            return;
+        };
+        let byte = self.read(source);
+        oam_dma.borrow_mut().copy(source, byte);
+    }
+
+    fn step_hdma(&mut self, hdma: &RefCell<Hdma>) {
+        let Some(source) = hdma.borrow_mut().step() else {
+            return;
+        };
+        let byte = self.read(source);
+        hdma.borrow_mut().copy(source, byte);
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        if let Some(work_ram) = self.work_ram.as_mut().filter(|ram| ram.accepts(addr)) {
+            return work_ram.get_byte(addr);
+        }
+        if let (Some(hram), HRAM_START..=HRAM_END) = (&self.hram, addr) {
+            return hram[(addr - HRAM_START) as usize];
+        }
+        self.get_space(addr).get_byte(addr)
+    }
+
+    fn write(&mut self, addr: u16, byte: u8) {
+        if let Some(work_ram) = self.work_ram.as_mut().filter(|ram| ram.accepts(addr)) {
+            work_ram.set_byte(addr, byte);
+            return;
+        }
+        if let (Some(hram), HRAM_START..=HRAM_END) = (&mut self.hram, addr) {
+            hram[(addr - HRAM_START) as usize] = byte;
+            return;
+        }
+        self.get_space(addr).set_byte(addr, byte);
+    }
+
+    fn page_owner(page: &Page, addr: u16) -> u8 {
+        match page {
+            Page::Owned(owner) => *owner,
+            Page::Split(owners) => owners[addr as usize % PAGE_SIZE],
+        }
+    }
+
+    fn get_space(&mut self, addr: u16) -> &mut dyn AddressSpace {
+        let page = &self.pages[addr as usize / PAGE_SIZE];
+        match Self::page_owner(page, addr) {
+            UNMAPPED => self.void.as_mut(),
+            owner => self.spaces[owner as usize].as_mut(),
+        }
     }
 }
 
@@ -27,7 +167,13 @@ impl Default for Mmu {
     fn default() -> Self {
         Self {
             spaces: Vec::new(),
+            pages: (0..PAGE_COUNT).map(|_| Page::Owned(UNMAPPED)).collect(),
+            work_ram: None,
+            hram: None,
             void: Box::new(Void {}),
+            oam_dma: None,
+            hdma: None,
+            double_speed: false,
         }
     }
 }
@@ -38,22 +184,58 @@ impl AddressSpace for Mmu {
     }
 
     fn set_byte(&mut self, addr: u16, byte: u8) {
-        log::trace!("set_byte ({addr:#06X}) <== {byte:#04X}");
-        self.get_space(addr).set_byte(addr, byte);
+        if !self.is_blocked(addr) {
+            self.write(addr, byte);
+        }
     }
 
     fn get_byte(&mut self, addr: u16) -> u8 {
-        let byte = self.get_space(addr).get_byte(addr);
-        log::trace!("get_byte ({addr:#06X}) ==> {byte:#04X}");
-        byte
+        if self.is_blocked(addr) {
+            return 0xFF;
+        }
+        self.read(addr)
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for space in self.spaces.iter_mut() {
+            space.tick(cycles);
+        }
+        if let Some(oam_dma) = self.oam_dma.clone() {
+            // One byte is copied per M-cycle of the CPU clock.
+            let m_cycle = if self.double_speed { 2 } else { 4 };
+            for _ in 0..cycles / m_cycle {
+                self.step_oam_dma(&oam_dma);
+            }
+        }
+        if let Some(hdma) = self.hdma.clone() {
+            // Two bytes are copied per M-cycle at normal speed, whatever the
+            // speed of the CPU.
+            for _ in 0..cycles / 2 {
+                self.step_hdma(&hdma);
+            }
+        }
+    }
+
+    fn set_double_speed(&mut self, enabled: bool) {
+        self.double_speed = enabled;
+        for space in self.spaces.iter_mut() {
+            space.set_double_speed(enabled);
+        }
+    }
+
+    fn stalls_cpu(&self) -> bool {
+        self.hdma
+            .as_ref()
+            .is_some_and(|hdma| hdma.borrow().is_copying())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Mmu;
+    use super::{Mmu, Page, UNMAPPED};
     use crate::memory::address_space::AddressSpace;
     use crate::memory::ram::Ram;
+    use crate::memory::work_ram::WorkRam;
 
     #[test]
     fn returns_null_byte_when_no_address_matches() {
@@ -100,4 +282,72 @@ mod test {
         let byte = mmu.get_byte(ADDRESS);
         assert_eq!(byte, 0xFF);
     }
+
+    #[test]
+    fn first_space_accepting_an_address_wins() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0xC000, 0x0010));
+        mmu.add_address_space(Ram::new(0xC000, 0x1000));
+
+        mmu.set_byte(0xC000, 0x12);
+        mmu.set_byte(0xC010, 0x34);
+
+        assert!(matches!(mmu.pages[0xC0], Page::Split(_)));
+        assert_eq!(mmu.spaces[0].get_byte(0xC000), 0x12);
+        assert_eq!(mmu.spaces[1].get_byte(0xC000), 0x00);
+        assert_eq!(mmu.spaces[1].get_byte(0xC010), 0x34);
+    }
+
+    #[test]
+    fn dispatches_within_shared_pages() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0xFF00, 0x0080));
+        mmu.add_address_space(Ram::new(0xFF80, 0x007F));
+
+        mmu.set_byte(0xFF7F, 0x01);
+        mmu.set_byte(0xFF80, 0x02);
+        mmu.set_byte(0xFFFF, 0x03);
+
+        assert_eq!(mmu.get_byte(0xFF7F), 0x01);
+        assert_eq!(mmu.get_byte(0xFF80), 0x02);
+        assert_eq!(mmu.get_byte(0xFFFF), 0x00, "Unmapped");
+    }
+
+    #[test]
+    fn whole_pages_are_owned_by_one_space() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0xC000, 0x2000));
+        assert!(matches!(mmu.pages[0xC0], Page::Owned(0)));
+        assert!(matches!(mmu.pages[0xDF], Page::Owned(0)));
+        assert!(matches!(mmu.pages[0xE0], Page::Owned(UNMAPPED)));
+    }
+
+    #[test]
+    fn work_ram_bypasses_page_table() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        mmu.add_work_ram(WorkRam::new());
+
+        mmu.set_byte(0xC123, 0x12);
+
+        assert_eq!(mmu.get_byte(0xC123), 0x12);
+        assert_eq!(mmu.get_byte(0xE123), 0x12, "Echo RAM");
+        assert_eq!(mmu.spaces[0].get_byte(0xC123), 0x00);
+    }
+
+    #[test]
+    fn hram_bypasses_page_table() {
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(Ram::new(0x0000, 0xFFFF));
+        mmu.add_hram();
+
+        mmu.set_byte(0xFF7F, 0x01);
+        mmu.set_byte(0xFF80, 0x02);
+        mmu.set_byte(0xFFFE, 0x03);
+
+        assert_eq!(mmu.get_byte(0xFF80), 0x02);
+        assert_eq!(mmu.get_byte(0xFFFE), 0x03);
+        assert_eq!(mmu.spaces[0].get_byte(0xFF7F), 0x01, "I/O is not HRAM");
+        assert_eq!(mmu.spaces[0].get_byte(0xFFFE), 0x00);
+    }
 }
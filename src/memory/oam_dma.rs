@@ -0,0 +1,229 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::address_space::AddressSpace;
+use crate::ppu::Ppu;
+
+// FF46: OAM DMA source address and start
+pub const DMA_ADDR: u16 = 0xFF46;
+
+// Bytes copied into OAM, one per M-cycle.
+const TRANSFER_LENGTH: u16 = 0xA0;
+
+// Sources from E000 upwards read from WRAM, like the echo RAM does.
+const ECHO_START: u16 = 0xE000;
+const ECHO_OFFSET: u16 = 0x2000;
+
+struct Transfer {
+    source: u16,
+    // Bytes copied so far.
+    index: u16,
+}
+
+/// OAM DMA controller
+///
+/// Writing XX to FF46 copies XX00-XX9F into OAM, one byte per M-cycle after
+/// an M-cycle of start-up delay. The copy is driven by the `Mmu`, which owns
+/// the bus and keeps the CPU off it while a transfer is running.
+pub struct OamDma {
+    register: u8,
+    // Source of a transfer which starts on the next M-cycle.
+    starting: Option<u16>,
+    transfer: Option<Transfer>,
+    ppu: Rc<RefCell<Ppu>>,
+}
+
+impl OamDma {
+    pub fn new(ppu: Rc<RefCell<Ppu>>) -> Self {
+        Self {
+            register: 0xFF,
+            starting: None,
+            transfer: None,
+            ppu,
+        }
+    }
+
+    /// Whether a transfer currently holds the bus.
+    pub fn is_active(&self) -> bool {
+        self.transfer.is_some()
+    }
+
+    /// Advances by one M-cycle, returning the address of the byte to copy
+    /// during it, if any.
+    ///
+    /// Restarting during a transfer lets the old one run for one more
+    /// M-cycle, so the bus is never released in between.
+    pub fn step(&mut self) -> Option<u16> {
+        let source = self.transfer.as_mut().map(|transfer| {
+            let source = transfer.source + transfer.index;
+            transfer.index += 1;
+            source
+        });
+        if self
+            .transfer
+            .as_ref()
+            .is_some_and(|transfer| transfer.index == TRANSFER_LENGTH)
+        {
+            self.transfer = None;
+        }
+        if let Some(source) = self.starting.take() {
+            self.transfer = Some(Transfer { source, index: 0 });
+        }
+        source
+    }
+
+    /// Stores a byte read from `source` in the matching OAM entry.
+    pub fn copy(&mut self, source: u16, byte: u8) {
+        self.ppu
+            .borrow_mut()
+            .write_oam_dma((source & 0xFF) as usize, byte);
+    }
+}
+
+impl AddressSpace for OamDma {
+    fn accepts(&self, addr: u16) -> bool {
+        addr == DMA_ADDR
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        self.register = byte;
+        let source = (byte as u16) << 8;
+        self.starting = Some(if source >= ECHO_START {
+            source - ECHO_OFFSET
+        } else {
+            source
+        });
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        self.register
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::InterruptController;
+    use crate::memory::mmu::Mmu;
+    use crate::memory::ram::Ram;
+
+    const OAM_START: u16 = 0xFE00;
+    const HRAM_START: u16 = 0xFF80;
+
+    fn mmu() -> (Mmu, Rc<RefCell<Ppu>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let ppu = Rc::new(RefCell::new(Ppu::new(interrupts)));
+        let mut mmu = Mmu::new();
+        mmu.add_address_space(ppu.clone());
+        mmu.add_oam_dma(Rc::new(RefCell::new(OamDma::new(ppu.clone()))));
+        mmu.add_address_space(Ram::new(0x0000, 0x8000));
+        mmu.add_address_space(Ram::new(0xC000, 0x2000));
+        mmu.add_address_space(Ram::new(HRAM_START, 0x007F));
+        (mmu, ppu)
+    }
+
+    fn run_m_cycles(mmu: &mut Mmu, count: u32) {
+        for _ in 0..count {
+            mmu.tick(4);
+        }
+    }
+
+    #[test]
+    fn copies_160_bytes_into_oam() {
+        let (mut mmu, ppu) = mmu();
+        for offset in 0..0xA1 {
+            mmu.set_byte(0xC100 + offset, offset as u8 ^ 0x5A);
+        }
+        mmu.set_byte(DMA_ADDR, 0xC1);
+        assert_eq!(mmu.get_byte(DMA_ADDR), 0xC1);
+
+        run_m_cycles(&mut mmu, 161);
+        let mut ppu = ppu.borrow_mut();
+        for offset in 0..0xA0 {
+            assert_eq!(ppu.get_byte(OAM_START + offset), offset as u8 ^ 0x5A);
+        }
+    }
+
+    #[test]
+    fn takes_one_byte_per_m_cycle_after_startup() {
+        let (mut mmu, ppu) = mmu();
+        mmu.set_byte(0x4000, 0x11);
+        mmu.set_byte(0x4001, 0x22);
+        mmu.set_byte(DMA_ADDR, 0x40);
+
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START), 0x00);
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START), 0x11);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START + 1), 0x00);
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START + 1), 0x22);
+    }
+
+    #[test]
+    fn restricts_cpu_to_hram_and_io_during_transfer() {
+        let (mut mmu, _) = mmu();
+        mmu.set_byte(0xC000, 0x12);
+        mmu.set_byte(HRAM_START, 0x34);
+        mmu.set_byte(DMA_ADDR, 0xC0);
+        assert_eq!(mmu.get_byte(0xC000), 0x12, "Not started yet");
+
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(mmu.get_byte(0xC000), 0xFF);
+        mmu.set_byte(0xC000, 0x56);
+        assert_eq!(mmu.get_byte(HRAM_START), 0x34);
+        assert_eq!(mmu.get_byte(DMA_ADDR), 0xC0);
+
+        run_m_cycles(&mut mmu, 159);
+        assert_eq!(mmu.get_byte(0xC000), 0xFF);
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(mmu.get_byte(0xC000), 0x12, "Write was ignored");
+    }
+
+    #[test]
+    fn restart_copies_from_new_source_without_releasing_bus() {
+        let (mut mmu, ppu) = mmu();
+        mmu.set_byte(0xC000, 0xAA);
+        mmu.set_byte(0xC001, 0xBB);
+        mmu.set_byte(0xD000, 0x11);
+        mmu.set_byte(0xD001, 0x22);
+        mmu.set_byte(DMA_ADDR, 0xC0);
+        run_m_cycles(&mut mmu, 2);
+
+        mmu.set_byte(DMA_ADDR, 0xD0);
+        // The old transfer copies one more byte while the new one starts.
+        run_m_cycles(&mut mmu, 1);
+        assert_eq!(mmu.get_byte(0xC000), 0xFF);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START + 1), 0xBB);
+
+        run_m_cycles(&mut mmu, 160);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START), 0x11);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START + 1), 0x22);
+        assert_eq!(mmu.get_byte(0xC000), 0xAA);
+    }
+
+    #[test]
+    fn sources_above_wram_read_from_wram() {
+        let (mut mmu, ppu) = mmu();
+        mmu.set_byte(0xDE05, 0x77);
+        mmu.set_byte(DMA_ADDR, 0xFE);
+        run_m_cycles(&mut mmu, 161);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START + 5), 0x77);
+    }
+
+    #[test]
+    fn writes_oam_while_ppu_is_using_it() {
+        let (mut mmu, ppu) = mmu();
+        mmu.set_byte(0xC000, 0x42);
+        ppu.borrow_mut().set_byte(0xFF40, 0x80);
+        mmu.set_byte(DMA_ADDR, 0xC0);
+        // Stops in mode 2, when the CPU can't access OAM.
+        mmu.tick(4);
+        mmu.tick(4);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START), 0xFF);
+        ppu.borrow_mut().set_byte(0xFF40, 0x00);
+        assert_eq!(ppu.borrow_mut().get_byte(OAM_START), 0x42);
+    }
+}
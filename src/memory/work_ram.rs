@@ -0,0 +1,137 @@
+use super::address_space::AddressSpace;
+use crate::model::Model;
+
+// C000-CFFF: 4 KiB Work RAM bank 0
+// D000-DFFF: 4 KiB Work RAM bank 1, switchable to banks 1-7 on CGB
+const WRAM_START: u16 = 0xC000;
+const BANK_SIZE: usize = 0x1000;
+
+// E000-FDFF: Mirror of C000-DDFF (Echo RAM)
+const ECHO_END: u16 = 0xFDFF;
+
+// FF70: WRAM bank select (SVBK), CGB only
+pub const SVBK_ADDR: u16 = 0xFF70;
+
+const DMG_BANKS: usize = 2;
+const CGB_BANKS: usize = 8;
+
+/// Work RAM, along with the echo RAM mirroring it.
+pub struct WorkRam {
+    space: Vec<u8>,
+    model: Model,
+    // Bank mapped at D000-DFFF.
+    bank: u8,
+}
+
+impl WorkRam {
+    pub fn new() -> Self {
+        Self::with_model(Model::Dmg)
+    }
+
+    pub fn with_model(model: Model) -> Self {
+        let banks = if model.is_cgb() { CGB_BANKS } else { DMG_BANKS };
+        Self {
+            space: vec![0x00; banks * BANK_SIZE],
+            model,
+            bank: 1,
+        }
+    }
+
+    // Echo RAM is an artifact of the address decoding ignoring bit 13.
+    fn get_index(&self, addr: u16) -> usize {
+        let offset = (addr - WRAM_START) as usize % (2 * BANK_SIZE);
+        if offset < BANK_SIZE {
+            offset
+        } else {
+            self.bank as usize * BANK_SIZE + offset - BANK_SIZE
+        }
+    }
+}
+
+impl Default for WorkRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddressSpace for WorkRam {
+    fn accepts(&self, addr: u16) -> bool {
+        (WRAM_START..=ECHO_END).contains(&addr) || (self.model.is_cgb() && addr == SVBK_ADDR)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        if addr == SVBK_ADDR {
+            // Selecting bank 0 maps bank 1 instead.
+            self.bank = (byte & 0b111).max(1);
+            return;
+        }
+        let index = self.get_index(addr);
+        self.space[index] = byte;
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        if addr == SVBK_ADDR {
+            return 0xF8 | self.bank;
+        }
+        self.space[self.get_index(addr)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_work_and_echo_ram() {
+        let wram = WorkRam::new();
+        assert!(!wram.accepts(0xBFFF));
+        assert!(wram.accepts(0xC000));
+        assert!(wram.accepts(0xDFFF));
+        assert!(wram.accepts(0xE000));
+        assert!(wram.accepts(0xFDFF));
+        assert!(!wram.accepts(0xFE00));
+    }
+
+    #[test]
+    fn echo_ram_mirrors_work_ram() {
+        let mut wram = WorkRam::new();
+        wram.set_byte(0xC123, 0x42);
+        assert_eq!(wram.get_byte(0xE123), 0x42);
+
+        wram.set_byte(0xFDFF, 0x24);
+        assert_eq!(wram.get_byte(0xDDFF), 0x24);
+    }
+
+    #[test]
+    fn cgb_switches_upper_bank() {
+        let mut wram = WorkRam::with_model(Model::Cgb);
+        wram.set_byte(0xC000, 0x10);
+        wram.set_byte(0xD000, 0x11);
+        wram.set_byte(SVBK_ADDR, 0x07);
+        assert_eq!(wram.get_byte(SVBK_ADDR), 0xFF);
+        assert_eq!(wram.get_byte(0xD000), 0x00);
+        wram.set_byte(0xD000, 0x77);
+        assert_eq!(wram.get_byte(0xF000), 0x77, "Echo follows the bank");
+        assert_eq!(wram.get_byte(0xC000), 0x10, "Bank 0 is fixed");
+
+        wram.set_byte(SVBK_ADDR, 0x00);
+        assert_eq!(wram.get_byte(SVBK_ADDR), 0xF9, "Bank 0 selects bank 1");
+        assert_eq!(wram.get_byte(0xD000), 0x11);
+    }
+
+    #[test]
+    fn dmg_has_no_bank_register() {
+        let wram = WorkRam::new();
+        assert!(!wram.accepts(SVBK_ADDR));
+        assert!(WorkRam::with_model(Model::Cgb).accepts(SVBK_ADDR));
+    }
+
+    #[test]
+    #[should_panic(expected = "OutOfBoundsError")]
+    fn panics_outside_work_ram() {
+        let mut wram = WorkRam::new();
+        wram.get_byte(0xFE00);
+    }
+}
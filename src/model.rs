@@ -0,0 +1,74 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::memory::cartridge_header::{CartridgeHeader, CgbSupport};
+
+/// Game Boy hardware being emulated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Model {
+    #[default]
+    Dmg,
+    Cgb,
+}
+
+impl Model {
+    /// Model a cartridge runs on by default, picking CGB mode for any
+    /// cartridge which supports it, as a CGB would.
+    pub fn for_cartridge(header: &CartridgeHeader) -> Self {
+        match header.cgb_support {
+            CgbSupport::None => Self::Dmg,
+            CgbSupport::Compatible | CgbSupport::Only => Self::Cgb,
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        *self == Self::Cgb
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Dmg => "dmg",
+            Self::Cgb => "cgb",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::Dmg, Self::Cgb]
+            .into_iter()
+            .find(|model| model.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown model '{s}'"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::cartridge_header::HEADER_END;
+
+    fn header(cgb_flag: u8) -> CartridgeHeader {
+        let mut rom = vec![0x00; HEADER_END];
+        rom[0x0143] = cgb_flag;
+        CartridgeHeader::parse(&rom).unwrap()
+    }
+
+    #[test]
+    fn picks_cgb_for_cartridges_supporting_it() {
+        assert_eq!(Model::for_cartridge(&header(0x00)), Model::Dmg);
+        assert_eq!(Model::for_cartridge(&header(0x80)), Model::Cgb);
+        assert_eq!(Model::for_cartridge(&header(0xC0)), Model::Cgb);
+    }
+
+    #[test]
+    fn parses_model_names() {
+        assert_eq!("CGB".parse(), Ok(Model::Cgb));
+        assert_eq!("dmg".parse(), Ok(Model::Dmg));
+        assert!("sgb".parse::<Model>().is_err());
+    }
+}
@@ -0,0 +1,858 @@
+mod fifo;
+mod lcd_control;
+mod palette;
+mod scanline;
+mod sprite;
+mod tile_attributes;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::memory::address_space::AddressSpace;
+use crate::model::Model;
+use fifo::PixelFifo;
+use lcd_control::LcdControl;
+use palette::PaletteRam;
+use scanline::apply_palette;
+use sprite::Sprite;
+use tile_attributes::TileAttributes;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// 8000-9FFF: Video RAM (VRAM), with a second bank on CGB
+const VRAM_START: u16 = 0x8000;
+const VRAM_END: u16 = 0x9FFF;
+const VRAM_BANK_SIZE: usize = 0x2000;
+
+// FE00-FE9F: Sprite attribute table (OAM)
+const OAM_START: u16 = 0xFE00;
+const OAM_END: u16 = 0xFE9F;
+
+// FEA0-FEFF: Not usable. Decoded alongside OAM, so it is blocked at the same
+// times, but otherwise reads 0x00 on DMG. CGB (revision E) repeats the high
+// nibble of the address' low byte instead, e.g. 0xAA at FEA0-FEAF.
+const UNUSABLE_START: u16 = 0xFEA0;
+const UNUSABLE_END: u16 = 0xFEFF;
+
+pub const LCDC_ADDR: u16 = 0xFF40;
+pub const STAT_ADDR: u16 = 0xFF41;
+pub const SCY_ADDR: u16 = 0xFF42;
+pub const SCX_ADDR: u16 = 0xFF43;
+pub const LY_ADDR: u16 = 0xFF44;
+pub const LYC_ADDR: u16 = 0xFF45;
+pub const BGP_ADDR: u16 = 0xFF47;
+pub const OBP0_ADDR: u16 = 0xFF48;
+pub const OBP1_ADDR: u16 = 0xFF49;
+pub const WY_ADDR: u16 = 0xFF4A;
+pub const WX_ADDR: u16 = 0xFF4B;
+
+// CGB only registers
+pub const VBK_ADDR: u16 = 0xFF4F;
+pub const BCPS_ADDR: u16 = 0xFF68;
+pub const BCPD_ADDR: u16 = 0xFF69;
+pub const OCPS_ADDR: u16 = 0xFF6A;
+pub const OCPD_ADDR: u16 = 0xFF6B;
+
+// DMG shades as RGB555 greys, from white to black.
+const DMG_COLORS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS: u16 = 172;
+const VBLANK_LINE: u8 = 144;
+const LINES_PER_FRAME: u8 = 154;
+const SPRITES_PER_LINE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Drawing,
+}
+
+impl Mode {
+    /// Value reported in the lower two bits of STAT.
+    fn bits(&self) -> u8 {
+        match self {
+            Self::HBlank => 0,
+            Self::VBlank => 1,
+            Self::OamScan => 2,
+            Self::Drawing => 3,
+        }
+    }
+}
+
+/// Palette used to color a pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Palette {
+    /// BGP on DMG, or one of the eight BG palettes on CGB.
+    Bg(u8),
+    /// OBP0 or OBP1 on DMG, or one of the eight OBJ palettes on CGB.
+    Obj(u8),
+}
+
+/// A BG or window pixel, before its palette is applied.
+#[derive(Clone, Copy, Debug, Default)]
+struct BgPixel {
+    color: u8,
+    palette: u8,
+    // Drawn over sprites, from the CGB tile attributes.
+    priority: bool,
+}
+
+/// Strategy used to draw pixels during mode 3.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Renderer {
+    /// Draws each line in one go at the end of a fixed length mode 3. Fast,
+    /// but ignores register changes made part way through a line.
+    #[default]
+    Scanline,
+    /// Emulates the background and sprite fetchers feeding the pixel FIFOs
+    /// dot by dot, with a variable length mode 3.
+    Fifo,
+}
+
+/// DMG shades (0 = white, 3 = black) and RGB555 colors of every pixel on the
+/// screen, row by row.
+struct Frame {
+    shades: Vec<u8>,
+    colors: Vec<u16>,
+}
+
+impl Frame {
+    fn new() -> Self {
+        Self {
+            shades: vec![0x00; SCREEN_WIDTH * SCREEN_HEIGHT],
+            colors: vec![DMG_COLORS[0]; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+}
+
+/// Pixel Processing Unit
+///
+/// Owns VRAM, OAM and the LCD registers, and renders each scanline into a
+/// frame which is presented once VBlank begins.
+pub struct Ppu {
+    model: Model,
+    vram: Vec<u8>,
+    // VRAM bank accessed by the CPU, selected through VBK.
+    vram_bank: u8,
+    oam: Vec<u8>,
+    lcdc: LcdControl,
+    // Only the interrupt select bits (3-6) are stored.
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    bg_palettes: PaletteRam,
+    obj_palettes: PaletteRam,
+    mode: Mode,
+    // Dots elapsed on the current line.
+    dots: u16,
+    // Internal line counter of the window, which only advances on lines
+    // where the window was drawn.
+    window_line: u8,
+    // STAT interrupts are only requested on a rising edge of this line.
+    stat_line: bool,
+    line_sprites: Vec<Sprite>,
+    // Drawn into line by line, then swapped with `last_frame` at VBlank so
+    // that readers never see a partly drawn frame.
+    frame: Frame,
+    last_frame: Frame,
+    renderer: Renderer,
+    fifo: PixelFifo,
+    interrupts: Rc<RefCell<InterruptController>>,
+}
+
+impl Ppu {
+    pub fn new(interrupts: Rc<RefCell<InterruptController>>) -> Self {
+        Self::with_renderer(interrupts, Renderer::default())
+    }
+
+    pub fn with_renderer(interrupts: Rc<RefCell<InterruptController>>, renderer: Renderer) -> Self {
+        Self::with_model(interrupts, renderer, Model::Dmg)
+    }
+
+    pub fn with_model(
+        interrupts: Rc<RefCell<InterruptController>>,
+        renderer: Renderer,
+        model: Model,
+    ) -> Self {
+        let vram_banks = if model.is_cgb() { 2 } else { 1 };
+        Self {
+            model,
+            vram: vec![0x00; vram_banks * VRAM_BANK_SIZE],
+            vram_bank: 0,
+            oam: vec![0x00; 0xA0],
+            lcdc: LcdControl::new(0x00),
+            stat: 0x00,
+            scy: 0x00,
+            scx: 0x00,
+            ly: 0x00,
+            lyc: 0x00,
+            bgp: 0x00,
+            obp0: 0x00,
+            obp1: 0x00,
+            wy: 0x00,
+            wx: 0x00,
+            bg_palettes: PaletteRam::new(),
+            obj_palettes: PaletteRam::new(),
+            mode: Mode::HBlank,
+            dots: 0,
+            window_line: 0,
+            stat_line: false,
+            line_sprites: Vec::with_capacity(SPRITES_PER_LINE),
+            frame: Frame::new(),
+            last_frame: Frame::new(),
+            renderer,
+            fifo: PixelFifo::new(),
+            interrupts,
+        }
+    }
+
+    /// Shades of the last rendered frame, row by row. In CGB mode, these are
+    /// the color indices within each pixel's palette instead.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.last_frame.shades
+    }
+
+    /// RGB555 colors of the last rendered frame, row by row, with red in the
+    /// lowest bits. DMG shades are shown as greys.
+    pub fn color_framebuffer(&self) -> &[u16] {
+        &self.last_frame.colors
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn vram_accessible(&self) -> bool {
+        self.mode != Mode::Drawing
+    }
+
+    fn oam_accessible(&self) -> bool {
+        self.mode != Mode::Drawing && self.mode != Mode::OamScan
+    }
+
+    /// Writes OAM on behalf of the DMA controller, which takes priority
+    /// over the PPU.
+    pub fn write_oam_dma(&mut self, index: usize, byte: u8) {
+        self.oam[index] = byte;
+    }
+
+    /// Writes VRAM on behalf of HDMA, which ignores the PPU mode. `offset`
+    /// is relative to 0x8000, in the bank selected through VBK.
+    pub fn write_vram_dma(&mut self, offset: u16, byte: u8) {
+        let index = self.cpu_vram_index(VRAM_START + offset);
+        self.vram[index] = byte;
+    }
+
+    /// Whether the LCD is on and the PPU is in mode 0, when HBlank DMA
+    /// copies a block.
+    pub fn in_hblank(&self) -> bool {
+        self.lcdc.lcd_enabled() && self.mode == Mode::HBlank
+    }
+
+    fn read_vram(&self, addr: u16) -> u8 {
+        self.read_vram_bank(0, addr)
+    }
+
+    fn read_vram_bank(&self, bank: u8, addr: u16) -> u8 {
+        self.vram[bank as usize * VRAM_BANK_SIZE + (addr - VRAM_START) as usize]
+    }
+
+    fn cpu_vram_index(&self, addr: u16) -> usize {
+        self.vram_bank as usize * VRAM_BANK_SIZE + (addr - VRAM_START) as usize
+    }
+
+    /// Attributes of the tile at `map_addr` in a BG tile map, which are all
+    /// clear on DMG.
+    fn tile_attributes(&self, map_addr: u16) -> TileAttributes {
+        if self.model.is_cgb() {
+            TileAttributes(self.read_vram_bank(1, map_addr))
+        } else {
+            TileAttributes(0)
+        }
+    }
+
+    /// VRAM bank holding a sprite's tile data.
+    fn sprite_bank(&self, sprite: &Sprite) -> u8 {
+        (self.model.is_cgb() && sprite.flags.cgb_bank()) as u8
+    }
+
+    fn sprite_palette(&self, sprite: &Sprite) -> u8 {
+        if self.model.is_cgb() {
+            sprite.flags.cgb_palette()
+        } else {
+            sprite.flags.dmg_palette() as u8
+        }
+    }
+
+    /// On DMG, clearing LCDC bit 0 blanks the BG and window. On CGB, they
+    /// are still drawn, but lose priority over sprites.
+    fn bg_window_visible(&self) -> bool {
+        self.model.is_cgb() || self.lcdc.bg_window_enabled()
+    }
+
+    /// Whether an opaque sprite pixel is drawn over the BG pixel beneath it.
+    fn obj_over_bg(&self, obj_bg_priority: bool, bg: BgPixel) -> bool {
+        if bg.color == 0 {
+            return true;
+        }
+        if self.model.is_cgb() && !self.lcdc.bg_window_enabled() {
+            return true;
+        }
+        !obj_bg_priority && !bg.priority
+    }
+
+    /// Colors a pixel on the current line and writes it to the frame being
+    /// drawn.
+    fn put_pixel(&mut self, x: usize, color: u8, palette: Palette) {
+        let (shade, rgb) = if self.model.is_cgb() {
+            let rgb = match palette {
+                Palette::Bg(number) => self.bg_palettes.color(number, color),
+                Palette::Obj(number) => self.obj_palettes.color(number, color),
+            };
+            (color, rgb)
+        } else {
+            let register = match palette {
+                Palette::Bg(_) => self.bgp,
+                Palette::Obj(0) => self.obp0,
+                Palette::Obj(_) => self.obp1,
+            };
+            let shade = apply_palette(register, color);
+            (shade, DMG_COLORS[shade as usize])
+        };
+        let index = self.ly as usize * SCREEN_WIDTH + x;
+        self.frame.shades[index] = shade;
+        self.frame.colors[index] = rgb;
+    }
+
+    fn set_lcdc(&mut self, byte: u8) {
+        let was_enabled = self.lcdc.lcd_enabled();
+        self.lcdc = LcdControl::new(byte);
+        match (was_enabled, self.lcdc.lcd_enabled()) {
+            (true, false) => {
+                self.ly = 0;
+                self.dots = 0;
+                self.window_line = 0;
+                self.mode = Mode::HBlank;
+                self.stat_line = false;
+            }
+            (false, true) => {
+                self.set_mode(Mode::OamScan);
+            }
+            _ => {}
+        }
+    }
+
+    fn read_stat(&self) -> u8 {
+        let coincidence = if self.ly == self.lyc { 0b100 } else { 0 };
+        let mode = if self.lcdc.lcd_enabled() {
+            self.mode.bits()
+        } else {
+            0
+        };
+        0b1000_0000 | self.stat | coincidence | mode
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.update_stat_line();
+    }
+
+    fn update_stat_line(&mut self) {
+        let line = (self.stat & 0b0100_0000 != 0 && self.ly == self.lyc)
+            || match self.mode {
+                Mode::HBlank => self.stat & 0b0000_1000 != 0,
+                Mode::VBlank => self.stat & 0b0001_0000 != 0,
+                Mode::OamScan => self.stat & 0b0010_0000 != 0,
+                Mode::Drawing => false,
+            };
+        if line && !self.stat_line {
+            self.interrupts.borrow_mut().request(Interrupt::Stat);
+        }
+        self.stat_line = line;
+    }
+
+    /// Selects the first ten sprites in OAM that overlap the current line.
+    fn scan_oam(&mut self) {
+        let height = self.lcdc.obj_height();
+        self.line_sprites.clear();
+        for index in 0..40 {
+            let sprite = Sprite::from_oam(&self.oam, index);
+            if sprite.is_on_line(self.ly, height) {
+                self.line_sprites.push(sprite);
+                if self.line_sprites.len() == SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn next_line(&mut self) {
+        self.dots = 0;
+        self.ly += 1;
+        if self.ly == VBLANK_LINE {
+            std::mem::swap(&mut self.frame, &mut self.last_frame);
+            self.interrupts.borrow_mut().request(Interrupt::VBlank);
+            self.set_mode(Mode::VBlank);
+        } else if self.ly == LINES_PER_FRAME {
+            self.ly = 0;
+            self.window_line = 0;
+            self.set_mode(Mode::OamScan);
+        } else if self.ly < VBLANK_LINE {
+            self.set_mode(Mode::OamScan);
+        } else {
+            self.update_stat_line();
+        }
+    }
+
+    fn step(&mut self) {
+        if !self.lcdc.lcd_enabled() {
+            return;
+        }
+        self.dots += 1;
+        match self.mode {
+            Mode::OamScan if self.dots == OAM_SCAN_DOTS => {
+                self.scan_oam();
+                if self.renderer == Renderer::Fifo {
+                    self.start_fifo_line();
+                }
+                self.set_mode(Mode::Drawing);
+            }
+            Mode::Drawing => match self.renderer {
+                Renderer::Scanline if self.dots == OAM_SCAN_DOTS + DRAWING_DOTS => {
+                    self.render_scanline();
+                    self.set_mode(Mode::HBlank);
+                }
+                Renderer::Scanline => {}
+                Renderer::Fifo => {
+                    self.step_fifo();
+                    if self.fifo.line_complete() {
+                        self.finish_fifo_line();
+                        self.set_mode(Mode::HBlank);
+                    }
+                }
+            },
+            Mode::HBlank | Mode::VBlank if self.dots == DOTS_PER_LINE => {
+                self.next_line();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl AddressSpace for Ppu {
+    fn accepts(&self, addr: u16) -> bool {
+        (VRAM_START..=VRAM_END).contains(&addr)
+            || (OAM_START..=UNUSABLE_END).contains(&addr)
+            || (LCDC_ADDR..=LYC_ADDR).contains(&addr)
+            || (BGP_ADDR..=WX_ADDR).contains(&addr)
+            || (self.model.is_cgb()
+                && (addr == VBK_ADDR || (BCPS_ADDR..=OCPD_ADDR).contains(&addr)))
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            VRAM_START..=VRAM_END => {
+                if self.vram_accessible() {
+                    let index = self.cpu_vram_index(addr);
+                    self.vram[index] = byte;
+                }
+            }
+            OAM_START..=OAM_END => {
+                if self.oam_accessible() {
+                    self.oam[(addr - OAM_START) as usize] = byte;
+                }
+            }
+            UNUSABLE_START..=UNUSABLE_END => {}
+            LCDC_ADDR => self.set_lcdc(byte),
+            STAT_ADDR => {
+                self.stat = byte & 0b0111_1000;
+                if self.lcdc.lcd_enabled() {
+                    self.update_stat_line();
+                }
+            }
+            SCY_ADDR => self.scy = byte,
+            SCX_ADDR => self.scx = byte,
+            // LY is read-only.
+            LY_ADDR => {}
+            LYC_ADDR => {
+                self.lyc = byte;
+                if self.lcdc.lcd_enabled() {
+                    self.update_stat_line();
+                }
+            }
+            BGP_ADDR => self.bgp = byte,
+            OBP0_ADDR => self.obp0 = byte,
+            OBP1_ADDR => self.obp1 = byte,
+            WY_ADDR => self.wy = byte,
+            WX_ADDR => self.wx = byte,
+            VBK_ADDR => self.vram_bank = byte & 1,
+            BCPS_ADDR => self.bg_palettes.write_spec(byte),
+            OCPS_ADDR => self.obj_palettes.write_spec(byte),
+            // Palette RAM is inaccessible while drawing, like VRAM.
+            BCPD_ADDR if self.vram_accessible() => self.bg_palettes.write_data(byte),
+            BCPD_ADDR => self.bg_palettes.increment(),
+            OCPD_ADDR if self.vram_accessible() => self.obj_palettes.write_data(byte),
+            OCPD_ADDR => self.obj_palettes.increment(),
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            VRAM_START..=VRAM_END => {
+                if self.vram_accessible() {
+                    self.vram[self.cpu_vram_index(addr)]
+                } else {
+                    0xFF
+                }
+            }
+            OAM_START..=OAM_END => {
+                if self.oam_accessible() {
+                    self.oam[(addr - OAM_START) as usize]
+                } else {
+                    0xFF
+                }
+            }
+            UNUSABLE_START..=UNUSABLE_END => {
+                if !self.oam_accessible() {
+                    0xFF
+                } else if self.model.is_cgb() {
+                    let nibble = (addr as u8) >> 4;
+                    nibble << 4 | nibble
+                } else {
+                    0x00
+                }
+            }
+            LCDC_ADDR => self.lcdc.bits(),
+            STAT_ADDR => self.read_stat(),
+            SCY_ADDR => self.scy,
+            SCX_ADDR => self.scx,
+            LY_ADDR => self.ly,
+            LYC_ADDR => self.lyc,
+            BGP_ADDR => self.bgp,
+            OBP0_ADDR => self.obp0,
+            OBP1_ADDR => self.obp1,
+            WY_ADDR => self.wy,
+            WX_ADDR => self.wx,
+            VBK_ADDR => 0xFE | self.vram_bank,
+            BCPS_ADDR => self.bg_palettes.read_spec(),
+            OCPS_ADDR => self.obj_palettes.read_spec(),
+            BCPD_ADDR | OCPD_ADDR if !self.vram_accessible() => 0xFF,
+            BCPD_ADDR => self.bg_palettes.read_data(),
+            OCPD_ADDR => self.obj_palettes.read_data(),
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::IF_ADDR;
+
+    fn ppu() -> (Ppu, Rc<RefCell<InterruptController>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        (Ppu::new(interrupts.clone()), interrupts)
+    }
+
+    fn cgb_ppu() -> Ppu {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        Ppu::with_model(interrupts, Renderer::default(), Model::Cgb)
+    }
+
+    fn requested(interrupts: &Rc<RefCell<InterruptController>>, interrupt: Interrupt) -> bool {
+        interrupts.borrow_mut().get_byte(IF_ADDR) & interrupt.bit() != 0
+    }
+
+    fn clear_requests(interrupts: &Rc<RefCell<InterruptController>>) {
+        interrupts.borrow_mut().set_byte(IF_ADDR, 0x00);
+    }
+
+    fn run_dots(ppu: &mut Ppu, dots: u32) {
+        for _ in 0..dots {
+            ppu.tick(1);
+        }
+    }
+
+    #[test]
+    fn does_not_accept_dma_register() {
+        let (ppu, _) = ppu();
+        assert!(ppu.accepts(LYC_ADDR));
+        assert!(!ppu.accepts(0xFF46));
+        assert!(ppu.accepts(BGP_ADDR));
+    }
+
+    #[test]
+    fn lcd_off_keeps_ly_at_zero() {
+        let (mut ppu, _) = ppu();
+        run_dots(&mut ppu, 1000);
+        assert_eq!(ppu.get_byte(LY_ADDR), 0);
+        assert_eq!(ppu.get_byte(STAT_ADDR) & 0b11, 0);
+    }
+
+    #[test]
+    fn walks_modes_with_correct_dot_counts() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+
+        run_dots(&mut ppu, 79);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+        run_dots(&mut ppu, 1);
+        assert_eq!(ppu.mode(), Mode::Drawing);
+
+        run_dots(&mut ppu, 171);
+        assert_eq!(ppu.mode(), Mode::Drawing);
+        run_dots(&mut ppu, 1);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+
+        run_dots(&mut ppu, 203);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        assert_eq!(ppu.get_byte(LY_ADDR), 0);
+        run_dots(&mut ppu, 1);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+        assert_eq!(ppu.get_byte(LY_ADDR), 1);
+    }
+
+    #[test]
+    fn enters_vblank_after_144_lines_and_requests_interrupt() {
+        let (mut ppu, interrupts) = ppu();
+        ppu.set_byte(LCDC_ADDR, 0x80);
+
+        run_dots(&mut ppu, 144 * 456 - 1);
+        assert!(!requested(&interrupts, Interrupt::VBlank));
+
+        run_dots(&mut ppu, 1);
+        assert_eq!(ppu.get_byte(LY_ADDR), 144);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+        assert!(requested(&interrupts, Interrupt::VBlank));
+    }
+
+    #[test]
+    fn presents_frame_once_vblank_begins() {
+        let (mut ppu, _) = ppu();
+        // Every background pixel is shade 3.
+        ppu.set_byte(BGP_ADDR, 0xFF);
+        ppu.set_byte(LCDC_ADDR, 0x91);
+
+        run_dots(&mut ppu, 144 * 456 - 1);
+        assert!(ppu.framebuffer().iter().all(|shade| *shade == 0));
+
+        run_dots(&mut ppu, 1);
+        assert!(ppu.framebuffer().iter().all(|shade| *shade == 3));
+        assert!(ppu
+            .color_framebuffer()
+            .iter()
+            .all(|rgb| *rgb == DMG_COLORS[3]));
+    }
+
+    #[test]
+    fn frame_takes_70224_dots() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(LCDC_ADDR, 0x80);
+
+        run_dots(&mut ppu, 70224 - 1);
+        assert_eq!(ppu.get_byte(LY_ADDR), 153);
+
+        run_dots(&mut ppu, 1);
+        assert_eq!(ppu.get_byte(LY_ADDR), 0);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+    }
+
+    #[test]
+    fn stat_reports_mode_and_coincidence() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        ppu.set_byte(LYC_ADDR, 0);
+        assert_eq!(ppu.get_byte(STAT_ADDR), 0b1000_0110);
+        ppu.set_byte(LYC_ADDR, 1);
+        assert_eq!(ppu.get_byte(STAT_ADDR), 0b1000_0010);
+    }
+
+    #[test]
+    fn lyc_stat_interrupt() {
+        let (mut ppu, interrupts) = ppu();
+        ppu.set_byte(STAT_ADDR, 0b0100_0000);
+        ppu.set_byte(LYC_ADDR, 2);
+        ppu.set_byte(LCDC_ADDR, 0x80);
+
+        run_dots(&mut ppu, 2 * 456 - 1);
+        assert!(!requested(&interrupts, Interrupt::Stat));
+
+        run_dots(&mut ppu, 1);
+        assert!(requested(&interrupts, Interrupt::Stat));
+    }
+
+    #[test]
+    fn hblank_stat_interrupt_only_on_rising_edge() {
+        let (mut ppu, interrupts) = ppu();
+        ppu.set_byte(STAT_ADDR, 0b0000_1000);
+        ppu.set_byte(LCDC_ADDR, 0x80);
+
+        run_dots(&mut ppu, 252);
+        assert!(requested(&interrupts, Interrupt::Stat));
+        clear_requests(&interrupts);
+
+        run_dots(&mut ppu, 100);
+        assert!(!requested(&interrupts, Interrupt::Stat));
+    }
+
+    #[test]
+    fn vram_inaccessible_while_drawing() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(0x8000, 0x12);
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        run_dots(&mut ppu, 80);
+        assert_eq!(ppu.mode(), Mode::Drawing);
+
+        assert_eq!(ppu.get_byte(0x8000), 0xFF);
+        ppu.set_byte(0x8000, 0x34);
+
+        run_dots(&mut ppu, 172);
+        assert_eq!(ppu.get_byte(0x8000), 0x12);
+    }
+
+    #[test]
+    fn unusable_region_reads_zero_unless_oam_blocked() {
+        let (mut ppu, _) = ppu();
+        assert!(ppu.accepts(0xFEA0));
+        assert!(ppu.accepts(0xFEFF));
+        ppu.set_byte(0xFEA0, 0x12);
+        assert_eq!(ppu.get_byte(0xFEA0), 0x00);
+
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        assert_eq!(ppu.get_byte(0xFEFF), 0xFF);
+
+        let mut ppu = cgb_ppu();
+        assert_eq!(ppu.get_byte(0xFEA0), 0xAA);
+        assert_eq!(ppu.get_byte(0xFEAF), 0xAA);
+        assert_eq!(ppu.get_byte(0xFEB3), 0xBB);
+        assert_eq!(ppu.get_byte(0xFEFF), 0xFF);
+
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        assert_eq!(ppu.get_byte(0xFEA0), 0xFF);
+    }
+
+    #[test]
+    fn oam_inaccessible_during_oam_scan_and_drawing() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(0xFE00, 0x12);
+        ppu.set_byte(LCDC_ADDR, 0x80);
+
+        assert_eq!(ppu.get_byte(0xFE00), 0xFF);
+        run_dots(&mut ppu, 80);
+        assert_eq!(ppu.get_byte(0xFE00), 0xFF);
+        run_dots(&mut ppu, 172);
+        assert_eq!(ppu.get_byte(0xFE00), 0x12);
+    }
+
+    #[test]
+    fn ly_is_read_only() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(LY_ADDR, 0x42);
+        assert_eq!(ppu.get_byte(LY_ADDR), 0x00);
+    }
+
+    #[test]
+    fn turning_lcd_off_resets_ly() {
+        let (mut ppu, _) = ppu();
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        run_dots(&mut ppu, 456 * 3 + 10);
+        assert_eq!(ppu.get_byte(LY_ADDR), 3);
+        ppu.set_byte(LCDC_ADDR, 0x00);
+        assert_eq!(ppu.get_byte(LY_ADDR), 0);
+    }
+
+    #[test]
+    fn scans_at_most_ten_sprites_per_line() {
+        let (mut ppu, _) = ppu();
+        for i in 0..12 {
+            ppu.set_byte(0xFE00 + i * 4, 16);
+            ppu.set_byte(0xFE00 + i * 4 + 1, i as u8 * 8);
+        }
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        run_dots(&mut ppu, 80);
+        assert_eq!(ppu.line_sprites.len(), 10);
+        assert_eq!(ppu.line_sprites[9].index, 9);
+    }
+
+    #[test]
+    fn cgb_registers_only_exist_on_cgb() {
+        let (ppu, _) = ppu();
+        assert!(!ppu.accepts(VBK_ADDR));
+        assert!(!ppu.accepts(BCPS_ADDR));
+        let ppu = cgb_ppu();
+        assert!(ppu.accepts(VBK_ADDR));
+        assert!((BCPS_ADDR..=OCPD_ADDR).all(|addr| ppu.accepts(addr)));
+    }
+
+    #[test]
+    fn vbk_selects_vram_bank() {
+        let mut ppu = cgb_ppu();
+        assert_eq!(ppu.get_byte(VBK_ADDR), 0xFE);
+        ppu.set_byte(0x8000, 0x12);
+        ppu.set_byte(VBK_ADDR, 0xFF);
+        assert_eq!(ppu.get_byte(VBK_ADDR), 0xFF);
+        assert_eq!(ppu.get_byte(0x8000), 0x00);
+        ppu.set_byte(0x9FFF, 0x34);
+
+        ppu.set_byte(VBK_ADDR, 0x00);
+        assert_eq!(ppu.get_byte(0x8000), 0x12);
+        assert_eq!(ppu.get_byte(0x9FFF), 0x00);
+        assert_eq!(ppu.read_vram_bank(1, 0x9FFF), 0x34);
+    }
+
+    #[test]
+    fn palette_data_written_through_auto_incrementing_index() {
+        let mut ppu = cgb_ppu();
+        // Color 1 of BG palette 2.
+        ppu.set_byte(BCPS_ADDR, 0x80 | 0x12);
+        ppu.set_byte(BCPD_ADDR, 0x1F);
+        ppu.set_byte(BCPD_ADDR, 0x7C);
+        assert_eq!(ppu.get_byte(BCPS_ADDR), 0xC0 | 0x14);
+        assert_eq!(ppu.bg_palettes.color(2, 1), 0x7C1F);
+
+        ppu.set_byte(OCPS_ADDR, 0x02);
+        ppu.set_byte(OCPD_ADDR, 0x55);
+        assert_eq!(ppu.get_byte(OCPS_ADDR), 0x42, "No auto increment");
+        assert_eq!(ppu.get_byte(OCPD_ADDR), 0x55);
+    }
+
+    #[test]
+    fn palette_data_inaccessible_while_drawing() {
+        let mut ppu = cgb_ppu();
+        ppu.set_byte(BCPS_ADDR, 0x80);
+        ppu.set_byte(BCPD_ADDR, 0x12);
+        ppu.set_byte(BCPS_ADDR, 0x80);
+        ppu.set_byte(LCDC_ADDR, 0x80);
+        run_dots(&mut ppu, 80);
+        assert_eq!(ppu.mode(), Mode::Drawing);
+
+        assert_eq!(ppu.get_byte(BCPD_ADDR), 0xFF);
+        ppu.set_byte(BCPD_ADDR, 0x34);
+        assert_eq!(ppu.get_byte(BCPS_ADDR), 0xC1, "Index still increments");
+
+        run_dots(&mut ppu, 172);
+        ppu.set_byte(BCPS_ADDR, 0x00);
+        assert_eq!(ppu.get_byte(BCPD_ADDR), 0x12);
+    }
+}
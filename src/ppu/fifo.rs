@@ -0,0 +1,515 @@
+use std::collections::VecDeque;
+
+use super::tile_attributes::TileAttributes;
+use super::{BgPixel, Palette, Ppu, SCREEN_WIDTH};
+
+// The first tile of every line is fetched twice, delaying the first pixel.
+const STARTUP_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Clone, Copy)]
+struct ObjPixel {
+    color: u8,
+    // OBP0 or OBP1 on DMG, or one of the eight OBJ palettes on CGB.
+    palette: u8,
+    bg_priority: bool,
+    // OAM index of the sprite, which decides priority on CGB.
+    index: u8,
+}
+
+/// State of the pixel FIFOs and fetcher for the line being drawn.
+pub struct PixelFifo {
+    bg: VecDeque<BgPixel>,
+    obj: VecDeque<ObjPixel>,
+    step: FetcherStep,
+    // Dots spent in the current fetcher step. Every step except Push takes two.
+    step_dots: u8,
+    // Tile column of the next fetch, relative to the start of the line or window.
+    fetcher_x: u8,
+    tile: u8,
+    attributes: TileAttributes,
+    low: u8,
+    high: u8,
+    // Pixels pushed to the LCD on this line.
+    lcd_x: u8,
+    // Pixels still to be dropped, for fine scrolling.
+    discard: u8,
+    startup: u8,
+    in_window: bool,
+    // Set once LY has matched WY during the current frame.
+    wy_triggered: bool,
+    // Bitmask of the line's sprites that have already been fetched.
+    fetched_sprites: u16,
+    pending_sprite: Option<usize>,
+    sprite_dots: u8,
+}
+
+impl PixelFifo {
+    pub fn new() -> Self {
+        Self {
+            bg: VecDeque::with_capacity(16),
+            obj: VecDeque::with_capacity(8),
+            step: FetcherStep::Tile,
+            step_dots: 0,
+            fetcher_x: 0,
+            tile: 0,
+            attributes: TileAttributes(0),
+            low: 0,
+            high: 0,
+            lcd_x: 0,
+            discard: 0,
+            startup: 0,
+            in_window: false,
+            wy_triggered: false,
+            fetched_sprites: 0,
+            pending_sprite: None,
+            sprite_dots: 0,
+        }
+    }
+
+    pub fn line_complete(&self) -> bool {
+        self.lcd_x as usize == SCREEN_WIDTH
+    }
+
+    fn reset_fetcher(&mut self) {
+        self.bg.clear();
+        self.step = FetcherStep::Tile;
+        self.step_dots = 0;
+        self.fetcher_x = 0;
+    }
+}
+
+impl Ppu {
+    /// Prepares the FIFOs at the start of mode 3.
+    pub(super) fn start_fifo_line(&mut self) {
+        let fifo = &mut self.fifo;
+        if self.ly == 0 {
+            fifo.wy_triggered = false;
+        }
+        if self.ly == self.wy {
+            fifo.wy_triggered = true;
+        }
+        fifo.reset_fetcher();
+        fifo.obj.clear();
+        fifo.lcd_x = 0;
+        fifo.discard = self.scx % 8;
+        fifo.startup = STARTUP_DOTS;
+        fifo.in_window = false;
+        fifo.fetched_sprites = 0;
+        fifo.pending_sprite = None;
+        fifo.sprite_dots = 0;
+    }
+
+    /// Advances the fetcher and FIFOs by a single dot of mode 3.
+    pub(super) fn step_fifo(&mut self) {
+        if self.fifo.startup > 0 {
+            self.fifo.startup -= 1;
+            return;
+        }
+
+        if self.fifo.pending_sprite.is_none() && self.lcdc.obj_enabled() {
+            self.fifo.pending_sprite = self.next_sprite();
+        }
+
+        if let Some(index) = self.fifo.pending_sprite {
+            // The BG fetcher finishes its current tile before the sprite is
+            // fetched, and no pixels are output in the meantime.
+            if self.fifo.step != FetcherStep::Push || self.fifo.bg.is_empty() {
+                self.step_fetcher();
+                if self.fifo.step != FetcherStep::Push || self.fifo.bg.is_empty() {
+                    return;
+                }
+            }
+            self.fifo.sprite_dots += 1;
+            if self.fifo.sprite_dots == SPRITE_FETCH_DOTS {
+                self.merge_sprite(index);
+                self.fifo.pending_sprite = None;
+                self.fifo.sprite_dots = 0;
+            }
+            return;
+        }
+
+        if self.window_reached() {
+            self.fifo.in_window = true;
+            self.fifo.reset_fetcher();
+            self.fifo.discard = 7u8.saturating_sub(self.wx);
+        }
+
+        self.step_fetcher();
+        self.output_pixel();
+    }
+
+    /// Called at the end of mode 3.
+    pub(super) fn finish_fifo_line(&mut self) {
+        if self.fifo.in_window {
+            self.window_line += 1;
+        }
+    }
+
+    fn window_reached(&self) -> bool {
+        !self.fifo.in_window
+            && self.lcdc.window_enabled()
+            && self.fifo.wy_triggered
+            && self.fifo.lcd_x as u16 + 7 >= self.wx as u16
+    }
+
+    fn next_sprite(&mut self) -> Option<usize> {
+        let screen_x = self.fifo.lcd_x as u16 + 8;
+        let index = self
+            .line_sprites
+            .iter()
+            .enumerate()
+            .position(|(i, sprite)| {
+                self.fifo.fetched_sprites & (1 << i) == 0 && sprite.x as u16 <= screen_x
+            })?;
+        self.fifo.fetched_sprites |= 1 << index;
+        Some(index)
+    }
+
+    fn step_fetcher(&mut self) {
+        if self.fifo.step == FetcherStep::Push {
+            if self.fifo.bg.is_empty() {
+                let attributes = self.fifo.attributes;
+                for col in 0..8 {
+                    let bit = if attributes.x_flip() { col } else { 7 - col };
+                    let color = (((self.fifo.high >> bit) & 1) << 1) | ((self.fifo.low >> bit) & 1);
+                    self.fifo.bg.push_back(BgPixel {
+                        color,
+                        palette: attributes.palette(),
+                        priority: attributes.priority(),
+                    });
+                }
+                self.fifo.fetcher_x = self.fifo.fetcher_x.wrapping_add(1);
+                self.fifo.step = FetcherStep::Tile;
+            }
+            return;
+        }
+
+        self.fifo.step_dots += 1;
+        if self.fifo.step_dots < 2 {
+            return;
+        }
+        self.fifo.step_dots = 0;
+
+        match self.fifo.step {
+            FetcherStep::Tile => {
+                let tile_addr = self.fetcher_tile_addr();
+                self.fifo.tile = self.read_vram(tile_addr);
+                self.fifo.attributes = self.tile_attributes(tile_addr);
+                self.fifo.step = FetcherStep::DataLow;
+            }
+            FetcherStep::DataLow => {
+                let bank = self.fifo.attributes.bank() as u8;
+                self.fifo.low = self.read_vram_bank(bank, self.fetcher_row_addr());
+                self.fifo.step = FetcherStep::DataHigh;
+            }
+            FetcherStep::DataHigh => {
+                let bank = self.fifo.attributes.bank() as u8;
+                self.fifo.high = self.read_vram_bank(bank, self.fetcher_row_addr() + 1);
+                self.fifo.step = FetcherStep::Push;
+            }
+            FetcherStep::Push => unreachable!(),
+        }
+    }
+
+    fn fetcher_y(&self) -> u8 {
+        if self.fifo.in_window {
+            self.window_line
+        } else {
+            self.ly.wrapping_add(self.scy)
+        }
+    }
+
+    fn fetcher_tile_addr(&self) -> u16 {
+        let y = self.fetcher_y() as u16;
+        let (map_addr, x) = if self.fifo.in_window {
+            (self.lcdc.window_tile_map_addr(), self.fifo.fetcher_x)
+        } else {
+            // SCX is re-read on every fetch, so coarse scrolling can change mid-line.
+            (
+                self.lcdc.bg_tile_map_addr(),
+                (self.scx / 8).wrapping_add(self.fifo.fetcher_x),
+            )
+        };
+        map_addr + (y / 8) * 32 + (x as u16 & 31)
+    }
+
+    fn fetcher_row_addr(&self) -> u16 {
+        let mut row = self.fetcher_y() % 8;
+        if self.fifo.attributes.y_flip() {
+            row = 7 - row;
+        }
+        self.lcdc.bg_window_tile_addr(self.fifo.tile) + row as u16 * 2
+    }
+
+    /// Mixes a fetched sprite into the object FIFO. On DMG, pixels already in
+    /// the FIFO belong to higher priority sprites, so only transparent ones
+    /// are replaced. On CGB, sprites earlier in OAM also replace opaque ones.
+    fn merge_sprite(&mut self, index: usize) {
+        let sprite = self.line_sprites[index];
+        let height = self.lcdc.obj_height();
+        let row_addr = sprite.row_addr(self.ly, height);
+        let bank = self.sprite_bank(&sprite);
+        let cgb = self.model.is_cgb();
+
+        // Pixels of sprites partially off the left of the screen are dropped.
+        let skip = 8u8.saturating_sub(sprite.x);
+        for i in skip..8 {
+            let col = if sprite.flags.x_flip() { 7 - i } else { i };
+            let pixel = ObjPixel {
+                color: self.tile_pixel(bank, row_addr, col),
+                palette: self.sprite_palette(&sprite),
+                bg_priority: sprite.flags.bg_priority(),
+                index: sprite.index,
+            };
+            let slot = (i - skip) as usize;
+            match self.fifo.obj.get_mut(slot) {
+                Some(existing) if existing.color == 0 => *existing = pixel,
+                Some(existing) if cgb && pixel.color != 0 && pixel.index < existing.index => {
+                    *existing = pixel
+                }
+                Some(_) => {}
+                None => self.fifo.obj.push_back(pixel),
+            }
+        }
+    }
+
+    fn output_pixel(&mut self) {
+        let Some(bg) = self.fifo.bg.pop_front() else {
+            return;
+        };
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+
+        // The object FIFO is aligned with the next pixel sent to the LCD.
+        let obj = self.fifo.obj.pop_front();
+
+        let bg = if self.bg_window_visible() {
+            bg
+        } else {
+            BgPixel::default()
+        };
+
+        let x = self.fifo.lcd_x as usize;
+        match obj {
+            Some(obj)
+                if self.lcdc.obj_enabled()
+                    && obj.color != 0
+                    && self.obj_over_bg(obj.bg_priority, bg) =>
+            {
+                self.put_pixel(x, obj.color, Palette::Obj(obj.palette))
+            }
+            _ => self.put_pixel(x, bg.color, Palette::Bg(bg.palette)),
+        }
+        self.fifo.lcd_x += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::interrupts::InterruptController;
+    use crate::memory::address_space::AddressSpace;
+    use crate::model::Model;
+    use crate::ppu::{
+        Mode, Renderer, BCPS_ADDR, BGP_ADDR, LCDC_ADDR, OBP0_ADDR, OCPS_ADDR, SCX_ADDR, SCY_ADDR,
+        VBK_ADDR, WX_ADDR, WY_ADDR,
+    };
+
+    const PALETTE: u8 = 0b1110_0100;
+
+    fn ppu(renderer: Renderer) -> Ppu {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let mut ppu = Ppu::with_renderer(interrupts, renderer);
+        ppu.set_byte(BGP_ADDR, PALETTE);
+        ppu.set_byte(OBP0_ADDR, PALETTE);
+        ppu
+    }
+
+    fn set_sprite(ppu: &mut Ppu, index: u16, y: u8, x: u8, tile: u8, flags: u8) {
+        let addr = 0xFE00 + index * 4;
+        ppu.set_byte(addr, y);
+        ppu.set_byte(addr + 1, x);
+        ppu.set_byte(addr + 2, tile);
+        ppu.set_byte(addr + 3, flags);
+    }
+
+    /// Fills VRAM with a pattern where every tile and row differs.
+    fn fill_scene(ppu: &mut Ppu) {
+        for i in 0..0x1800u16 {
+            ppu.set_byte(0x8000 + i, (i.wrapping_mul(37) >> 3) as u8 ^ i as u8);
+        }
+        for i in 0..0x800u16 {
+            ppu.set_byte(0x9800 + i, (i * 7) as u8);
+        }
+    }
+
+    /// Length in dots of mode 3 on the first line.
+    fn drawing_dots(ppu: &mut Ppu) -> u32 {
+        let mut dots = 0;
+        while ppu.mode() != Mode::Drawing {
+            ppu.tick(1);
+        }
+        while ppu.mode() == Mode::Drawing {
+            ppu.tick(1);
+            dots += 1;
+        }
+        dots
+    }
+
+    fn run_frame(ppu: &mut Ppu) {
+        for _ in 0..70224 {
+            ppu.tick(1);
+        }
+    }
+
+    #[test]
+    fn mode_3_takes_172_dots_without_scroll_or_sprites() {
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.set_byte(LCDC_ADDR, 0x91);
+        assert_eq!(drawing_dots(&mut ppu), 172);
+    }
+
+    #[test]
+    fn fine_scroll_lengthens_mode_3() {
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.set_byte(SCX_ADDR, 3);
+        ppu.set_byte(LCDC_ADDR, 0x91);
+        assert_eq!(drawing_dots(&mut ppu), 175);
+    }
+
+    #[test]
+    fn sprites_lengthen_mode_3() {
+        let mut ppu = ppu(Renderer::Fifo);
+        set_sprite(&mut ppu, 0, 16, 8 + 40, 0, 0);
+        ppu.set_byte(LCDC_ADDR, 0x93);
+        let dots = drawing_dots(&mut ppu);
+        // 6 dots for the fetch, plus up to 6 waiting for the BG fetcher.
+        assert!((178..=184).contains(&dots), "Took {dots} dots");
+    }
+
+    #[test]
+    fn window_lengthens_mode_3() {
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.set_byte(WY_ADDR, 0);
+        ppu.set_byte(WX_ADDR, 7 + 80);
+        ppu.set_byte(LCDC_ADDR, 0xB1);
+        assert_eq!(drawing_dots(&mut ppu), 178);
+    }
+
+    #[test]
+    fn matches_scanline_renderer_for_static_scene() {
+        let mut scanline = ppu(Renderer::Scanline);
+        let mut fifo = ppu(Renderer::Fifo);
+        for ppu in [&mut scanline, &mut fifo] {
+            fill_scene(ppu);
+            set_sprite(ppu, 0, 20, 3, 5, 0);
+            set_sprite(ppu, 1, 30, 50, 6, 0b0010_0000);
+            set_sprite(ppu, 2, 30, 54, 7, 0b1000_0000);
+            set_sprite(ppu, 3, 60, 100, 8, 0b0100_0000);
+            set_sprite(ppu, 4, 90, 165, 9, 0);
+            ppu.set_byte(SCX_ADDR, 13);
+            ppu.set_byte(SCY_ADDR, 7);
+            ppu.set_byte(WY_ADDR, 100);
+            ppu.set_byte(WX_ADDR, 60);
+            ppu.set_byte(LCDC_ADDR, 0xB3);
+            run_frame(ppu);
+        }
+        assert!(scanline.framebuffer() == fifo.framebuffer());
+    }
+
+    #[test]
+    fn matches_scanline_renderer_for_static_cgb_scene() {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let mut scanline = Ppu::with_model(interrupts.clone(), Renderer::Scanline, Model::Cgb);
+        let mut fifo = Ppu::with_model(interrupts, Renderer::Fifo, Model::Cgb);
+        for ppu in [&mut scanline, &mut fifo] {
+            fill_scene(ppu);
+            ppu.set_byte(VBK_ADDR, 1);
+            fill_scene(ppu);
+            ppu.set_byte(VBK_ADDR, 0);
+            for spec_addr in [BCPS_ADDR, OCPS_ADDR] {
+                ppu.set_byte(spec_addr, 0x80);
+                for i in 0..64u8 {
+                    ppu.set_byte(spec_addr + 1, i.wrapping_mul(97));
+                }
+            }
+            set_sprite(ppu, 0, 20, 3, 5, 0b0000_1011);
+            set_sprite(ppu, 1, 30, 54, 6, 0b0010_0100);
+            set_sprite(ppu, 2, 30, 50, 7, 0b1000_1001);
+            set_sprite(ppu, 3, 60, 100, 8, 0b0100_0111);
+            ppu.set_byte(SCX_ADDR, 13);
+            ppu.set_byte(SCY_ADDR, 7);
+            ppu.set_byte(WY_ADDR, 100);
+            ppu.set_byte(WX_ADDR, 60);
+            ppu.set_byte(LCDC_ADDR, 0xB3);
+            run_frame(ppu);
+        }
+        assert!(scanline.framebuffer() == fifo.framebuffer());
+        assert!(scanline.color_framebuffer() == fifo.color_framebuffer());
+    }
+
+    #[test]
+    fn palette_change_mid_line_affects_remaining_pixels() {
+        let mut ppu = ppu(Renderer::Fifo);
+        // Every background pixel is color 3.
+        for i in 0..16 {
+            ppu.set_byte(0x8000 + i, 0xFF);
+        }
+        ppu.set_byte(LCDC_ADDR, 0x91);
+
+        // Halfway through drawing the first line.
+        for _ in 0..(80 + 6 + 80) {
+            ppu.tick(1);
+        }
+        ppu.set_byte(BGP_ADDR, 0x00);
+        for _ in 0..456 {
+            ppu.tick(1);
+        }
+
+        let line = &ppu.frame.shades[..SCREEN_WIDTH];
+        assert_eq!(line[0], 3);
+        assert_eq!(line[159], 0);
+        let changed = line.iter().position(|shade| *shade == 0).unwrap();
+        assert!((70..90).contains(&changed), "Changed at {changed}");
+    }
+
+    #[test]
+    fn scx_change_mid_line_affects_later_tiles() {
+        let mut ppu = ppu(Renderer::Fifo);
+        // Tile 1 is solid color 3, placed in map column 12 only.
+        for i in 0..16 {
+            ppu.set_byte(0x8010 + i, 0xFF);
+        }
+        ppu.set_byte(0x980C, 1);
+        ppu.set_byte(LCDC_ADDR, 0x91);
+
+        for _ in 0..(80 + 6 + 40) {
+            ppu.tick(1);
+        }
+        // Scroll by two tiles, so column 12 appears at x = 80 rather than 96.
+        ppu.set_byte(SCX_ADDR, 16);
+        for _ in 0..456 {
+            ppu.tick(1);
+        }
+
+        let line = &ppu.frame.shades[..SCREEN_WIDTH];
+        assert_eq!(line[79], 0);
+        assert_eq!(line[80], 3);
+        assert_eq!(line[87], 3);
+        assert_eq!(line[96], 0);
+    }
+}
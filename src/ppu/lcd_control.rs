@@ -0,0 +1,112 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// FF40: LCD Control register (LCDC)
+    #[derive(Clone, Copy)]
+    pub struct LcdControl(u8);
+    impl Debug;
+
+    // LCD & PPU enable: 0 = Off; 1 = On
+    pub lcd_enabled, _: 7;
+    // Window tile map area: 0 = 9800–9BFF; 1 = 9C00–9FFF
+    pub window_tile_map, _: 6;
+    // Window enable: 0 = Off; 1 = On
+    pub window_enabled, _: 5;
+    // BG & Window tile data area: 0 = 8800–97FF; 1 = 8000–8FFF
+    pub bg_window_tile_data, _: 4;
+    // BG tile map area: 0 = 9800–9BFF; 1 = 9C00–9FFF
+    pub bg_tile_map, _: 3;
+    // OBJ size: 0 = 8×8; 1 = 8×16
+    pub obj_size, _: 2;
+    // OBJ enable: 0 = Off; 1 = On
+    pub obj_enabled, _: 1;
+    // BG & Window enable: 0 = Off; 1 = On
+    pub bg_window_enabled, _: 0;
+}
+
+impl LcdControl {
+    pub fn new(bits: u8) -> Self {
+        LcdControl(bits)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn obj_height(&self) -> u8 {
+        if self.obj_size() {
+            16
+        } else {
+            8
+        }
+    }
+
+    pub fn window_tile_map_addr(&self) -> u16 {
+        if self.window_tile_map() {
+            0x9C00
+        } else {
+            0x9800
+        }
+    }
+
+    pub fn bg_tile_map_addr(&self) -> u16 {
+        if self.bg_tile_map() {
+            0x9C00
+        } else {
+            0x9800
+        }
+    }
+
+    /// Address of the given BG or window tile's data, taking into account
+    /// the signed addressing used by the 8800 method.
+    pub fn bg_window_tile_addr(&self, tile: u8) -> u16 {
+        if self.bg_window_tile_data() {
+            0x8000 + tile as u16 * 16
+        } else {
+            0x9000u16.wrapping_add((tile as i8 as i16 * 16) as u16)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LcdControl;
+
+    #[test]
+    fn flags_correspond_to_bits() {
+        let lcdc = LcdControl(0b1001_0001);
+        assert!(lcdc.lcd_enabled());
+        assert!(!lcdc.window_tile_map());
+        assert!(!lcdc.window_enabled());
+        assert!(lcdc.bg_window_tile_data());
+        assert!(!lcdc.bg_tile_map());
+        assert!(!lcdc.obj_size());
+        assert!(!lcdc.obj_enabled());
+        assert!(lcdc.bg_window_enabled());
+    }
+
+    #[test]
+    fn unsigned_tile_addressing() {
+        let lcdc = LcdControl(0b0001_0000);
+        assert_eq!(lcdc.bg_window_tile_addr(0x00), 0x8000);
+        assert_eq!(lcdc.bg_window_tile_addr(0x80), 0x8800);
+        assert_eq!(lcdc.bg_window_tile_addr(0xFF), 0x8FF0);
+    }
+
+    #[test]
+    fn signed_tile_addressing() {
+        let lcdc = LcdControl(0b0000_0000);
+        assert_eq!(lcdc.bg_window_tile_addr(0x00), 0x9000);
+        assert_eq!(lcdc.bg_window_tile_addr(0x7F), 0x97F0);
+        assert_eq!(lcdc.bg_window_tile_addr(0x80), 0x8800);
+        assert_eq!(lcdc.bg_window_tile_addr(0xFF), 0x8FF0);
+    }
+
+    #[test]
+    fn tile_maps() {
+        assert_eq!(LcdControl(0b0000_0000).bg_tile_map_addr(), 0x9800);
+        assert_eq!(LcdControl(0b0000_1000).bg_tile_map_addr(), 0x9C00);
+        assert_eq!(LcdControl(0b0000_0000).window_tile_map_addr(), 0x9800);
+        assert_eq!(LcdControl(0b0100_0000).window_tile_map_addr(), 0x9C00);
+    }
+}
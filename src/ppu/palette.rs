@@ -0,0 +1,97 @@
+// Eight palettes of four colors, each a little-endian RGB555 word.
+const PALETTE_RAM_SIZE: usize = 64;
+
+const AUTO_INCREMENT: u8 = 0b1000_0000;
+const INDEX_MASK: u8 = 0b0011_1111;
+
+/// CGB color palette memory, accessed a byte at a time through an index
+/// register (BCPS/OCPS) and a data register (BCPD/OCPD).
+pub struct PaletteRam {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl PaletteRam {
+    pub fn new() -> Self {
+        Self {
+            data: [0x00; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    pub fn read_spec(&self) -> u8 {
+        let mut value = 0b0100_0000 | self.index;
+        if self.auto_increment {
+            value |= AUTO_INCREMENT;
+        }
+        value
+    }
+
+    pub fn write_spec(&mut self, byte: u8) {
+        self.index = byte & INDEX_MASK;
+        self.auto_increment = byte & AUTO_INCREMENT != 0;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    pub fn write_data(&mut self, byte: u8) {
+        self.data[self.index as usize] = byte;
+        self.increment();
+    }
+
+    /// Advances the index after a write to the data register, if set to do
+    /// so. This happens even when the write itself is blocked during mode 3.
+    pub fn increment(&mut self) {
+        if self.auto_increment {
+            self.index = (self.index + 1) & INDEX_MASK;
+        }
+    }
+
+    /// RGB555 value of a color (0-3) in one of the eight palettes, with red
+    /// in the lowest bits.
+    pub fn color(&self, palette: u8, color: u8) -> u16 {
+        let offset = (palette as usize * 4 + color as usize) * 2;
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]]) & 0x7FFF
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auto_increments_after_writes() {
+        let mut palettes = PaletteRam::new();
+        palettes.write_spec(0x80 | 0x3E);
+        palettes.write_data(0x1F);
+        palettes.write_data(0x7C);
+        palettes.write_data(0xAA);
+        assert_eq!(palettes.read_spec(), 0xC1, "Index wraps around");
+        assert_eq!(palettes.color(7, 3), 0x7C1F);
+
+        palettes.write_spec(0x00);
+        assert_eq!(palettes.read_data(), 0xAA);
+        assert_eq!(palettes.read_spec(), 0x40);
+    }
+
+    #[test]
+    fn reads_do_not_increment() {
+        let mut palettes = PaletteRam::new();
+        palettes.write_spec(0x82);
+        palettes.read_data();
+        assert_eq!(palettes.read_spec(), 0xC2);
+    }
+
+    #[test]
+    fn colors_ignore_unused_bit() {
+        let mut palettes = PaletteRam::new();
+        palettes.write_spec(0x80 | 10);
+        palettes.write_data(0xFF);
+        palettes.write_data(0xFF);
+        assert_eq!(palettes.color(1, 1), 0x7FFF);
+    }
+}
@@ -0,0 +1,567 @@
+use super::{BgPixel, Palette, Ppu, SCREEN_WIDTH};
+
+/// Maps a 2-bit color index to a shade using a DMG palette register.
+pub fn apply_palette(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0b11
+}
+
+impl Ppu {
+    /// Color index (0-3) of a pixel in the tile row starting at `row_addr`.
+    pub(super) fn tile_pixel(&self, bank: u8, row_addr: u16, col: u8) -> u8 {
+        let low = self.read_vram_bank(bank, row_addr);
+        let high = self.read_vram_bank(bank, row_addr + 1);
+        let bit = 7 - col;
+        (((high >> bit) & 1) << 1) | ((low >> bit) & 1)
+    }
+
+    /// BG or window pixel at the given position in a tile map.
+    pub(super) fn map_pixel(&self, map_addr: u16, x: u8, y: u8) -> BgPixel {
+        let tile_addr = map_addr + (y as u16 / 8) * 32 + x as u16 / 8;
+        let tile = self.read_vram(tile_addr);
+        let attributes = self.tile_attributes(tile_addr);
+        let mut row = y % 8;
+        if attributes.y_flip() {
+            row = 7 - row;
+        }
+        let mut col = x % 8;
+        if attributes.x_flip() {
+            col = 7 - col;
+        }
+        let row_addr = self.lcdc.bg_window_tile_addr(tile) + row as u16 * 2;
+        BgPixel {
+            color: self.tile_pixel(attributes.bank() as u8, row_addr, col),
+            palette: attributes.palette(),
+            priority: attributes.priority(),
+        }
+    }
+
+    /// Renders the whole of the current line in one go, at the end of mode 3.
+    pub(super) fn render_scanline(&mut self) {
+        let mut bg_pixels = [BgPixel::default(); SCREEN_WIDTH];
+
+        if self.bg_window_visible() {
+            self.render_background(&mut bg_pixels);
+            self.render_window(&mut bg_pixels);
+        }
+
+        for (x, pixel) in bg_pixels.iter().enumerate() {
+            self.put_pixel(x, pixel.color, Palette::Bg(pixel.palette));
+        }
+
+        if self.lcdc.obj_enabled() {
+            self.render_sprites(&bg_pixels);
+        }
+    }
+
+    fn render_background(&self, bg_pixels: &mut [BgPixel; SCREEN_WIDTH]) {
+        let map_addr = self.lcdc.bg_tile_map_addr();
+        let y = self.ly.wrapping_add(self.scy);
+        for (x, pixel) in bg_pixels.iter_mut().enumerate() {
+            let x = (x as u8).wrapping_add(self.scx);
+            *pixel = self.map_pixel(map_addr, x, y);
+        }
+    }
+
+    fn render_window(&mut self, bg_pixels: &mut [BgPixel; SCREEN_WIDTH]) {
+        // The window is positioned at WX-7, and hidden for WX > 166.
+        if !self.lcdc.window_enabled() || self.ly < self.wy || self.wx > 166 {
+            return;
+        }
+        let map_addr = self.lcdc.window_tile_map_addr();
+        let start = self.wx.saturating_sub(7) as usize;
+        let offset = 7u8.saturating_sub(self.wx);
+        for (x, pixel) in bg_pixels.iter_mut().enumerate().skip(start) {
+            let window_x = (x - start) as u8 + offset;
+            *pixel = self.map_pixel(map_addr, window_x, self.window_line);
+        }
+        self.window_line += 1;
+    }
+
+    fn render_sprites(&mut self, bg_pixels: &[BgPixel; SCREEN_WIDTH]) {
+        let height = self.lcdc.obj_height();
+
+        // On DMG the sprite with the smallest X coordinate has priority,
+        // falling back to OAM order. On CGB only OAM order counts.
+        let mut sprites = self.line_sprites.clone();
+        if !self.model.is_cgb() {
+            sprites.sort_by_key(|sprite| (sprite.x, sprite.index));
+        }
+
+        for (x, bg) in bg_pixels.iter().enumerate() {
+            let screen_x = x as u16 + 8;
+            let pixel = sprites.iter().find_map(|sprite| {
+                let left = sprite.x as u16;
+                if screen_x < left || screen_x >= left + 8 {
+                    return None;
+                }
+                let mut col = (screen_x - left) as u8;
+                if sprite.flags.x_flip() {
+                    col = 7 - col;
+                }
+                let row_addr = sprite.row_addr(self.ly, height);
+                let color = self.tile_pixel(self.sprite_bank(sprite), row_addr, col);
+                // Color 0 is transparent for sprites.
+                (color != 0).then_some((sprite, color))
+            });
+
+            if let Some((sprite, color)) = pixel {
+                if !self.obj_over_bg(sprite.flags.bg_priority(), *bg) {
+                    continue;
+                }
+                let palette = self.sprite_palette(sprite);
+                self.put_pixel(x, color, Palette::Obj(palette));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::interrupts::InterruptController;
+    use crate::memory::address_space::AddressSpace;
+    use crate::model::Model;
+    use crate::ppu::lcd_control::LcdControl;
+    use crate::ppu::{
+        Renderer, BCPS_ADDR, BGP_ADDR, OBP0_ADDR, OBP1_ADDR, OCPS_ADDR, SCX_ADDR, SCY_ADDR,
+        VBK_ADDR, WX_ADDR, WY_ADDR,
+    };
+
+    // Identity palette, so shades equal color indices.
+    const PALETTE: u8 = 0b1110_0100;
+
+    fn ppu() -> Ppu {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        let mut ppu = Ppu::new(interrupts);
+        ppu.set_byte(BGP_ADDR, PALETTE);
+        ppu.set_byte(OBP0_ADDR, PALETTE);
+        ppu
+    }
+
+    fn cgb_ppu() -> Ppu {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        Ppu::with_model(interrupts, Renderer::Scanline, Model::Cgb)
+    }
+
+    /// Sets a color in one of the CGB palettes through BCPS/BCPD or OCPS/OCPD.
+    fn set_cgb_color(ppu: &mut Ppu, spec_addr: u16, palette: u8, color: u8, rgb: u16) {
+        ppu.set_byte(spec_addr, 0x80 | ((palette * 4 + color) * 2));
+        for byte in rgb.to_le_bytes() {
+            ppu.set_byte(spec_addr + 1, byte);
+        }
+    }
+
+    fn color_pixel(ppu: &Ppu, x: usize, y: usize) -> u16 {
+        ppu.frame.colors[y * SCREEN_WIDTH + x]
+    }
+
+    /// Fills a tile with a single color index.
+    fn solid_tile(ppu: &mut Ppu, tile: u16, color: u8) {
+        let low = if color & 1 != 0 { 0xFF } else { 0x00 };
+        let high = if color & 2 != 0 { 0xFF } else { 0x00 };
+        for row in 0..8 {
+            ppu.set_byte(0x8000 + tile * 16 + row * 2, low);
+            ppu.set_byte(0x8000 + tile * 16 + row * 2 + 1, high);
+        }
+    }
+
+    fn set_sprite(ppu: &mut Ppu, index: u16, y: u8, x: u8, tile: u8, flags: u8) {
+        let addr = 0xFE00 + index * 4;
+        ppu.set_byte(addr, y);
+        ppu.set_byte(addr + 1, x);
+        ppu.set_byte(addr + 2, tile);
+        ppu.set_byte(addr + 3, flags);
+    }
+
+    /// Renders the given line without running the PPU's timing.
+    fn render_line(ppu: &mut Ppu, ly: u8) {
+        ppu.ly = ly;
+        ppu.scan_oam();
+        ppu.render_scanline();
+    }
+
+    fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+        ppu.frame.shades[y * SCREEN_WIDTH + x]
+    }
+
+    #[test]
+    fn apply_palette_maps_color_to_shade() {
+        assert_eq!(apply_palette(0b1110_0100, 0), 0);
+        assert_eq!(apply_palette(0b1110_0100, 3), 3);
+        assert_eq!(apply_palette(0b0001_1011, 0), 3);
+        assert_eq!(apply_palette(0b0001_1011, 3), 0);
+    }
+
+    #[test]
+    fn renders_background_tiles() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        // Second tile in the first row of the map.
+        ppu.set_byte(0x9801, 1);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 7, 0), 0);
+        assert_eq!(pixel(&ppu, 8, 0), 3);
+        assert_eq!(pixel(&ppu, 15, 0), 3);
+        assert_eq!(pixel(&ppu, 16, 0), 0);
+    }
+
+    #[test]
+    fn renders_tile_row_bits() {
+        let mut ppu = ppu();
+        // Row 0 of tile 0: colors 0, 1, 2, 3, 0, 1, 2, 3
+        ppu.set_byte(0x8000, 0b0101_0101);
+        ppu.set_byte(0x8001, 0b0011_0011);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+
+        render_line(&mut ppu, 0);
+
+        let row: Vec<u8> = (0..8).map(|x| pixel(&ppu, x, 0)).collect();
+        assert_eq!(row, vec![0, 1, 2, 3, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn scrolls_background() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        // Tile at map row 1, column 1.
+        ppu.set_byte(0x9821, 1);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+        ppu.set_byte(SCX_ADDR, 4);
+        ppu.set_byte(SCY_ADDR, 8);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 3, 0), 0);
+        assert_eq!(pixel(&ppu, 4, 0), 3);
+        assert_eq!(pixel(&ppu, 11, 0), 3);
+        assert_eq!(pixel(&ppu, 12, 0), 0);
+    }
+
+    #[test]
+    fn background_disabled_renders_white() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 0, 3);
+        ppu.lcdc = LcdControl::new(0b1001_0000);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0);
+    }
+
+    #[test]
+    fn uses_signed_tile_addressing() {
+        let mut ppu = ppu();
+        // Tile 0 relative to 0x9000.
+        for row in 0..8 {
+            ppu.set_byte(0x9000 + row * 2, 0xFF);
+        }
+        ppu.lcdc = LcdControl::new(0b1000_0001);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+    }
+
+    #[test]
+    fn renders_window_over_background() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 2);
+        // Window uses the 9C00 map, filled with tile 1.
+        for i in 0..0x400 {
+            ppu.set_byte(0x9C00 + i, 1);
+        }
+        ppu.lcdc = LcdControl::new(0b1111_0001);
+        ppu.set_byte(WY_ADDR, 10);
+        ppu.set_byte(WX_ADDR, 7 + 20);
+
+        render_line(&mut ppu, 9);
+        assert_eq!(pixel(&ppu, 20, 9), 0, "Window not yet reached");
+        assert_eq!(ppu.window_line, 0);
+
+        render_line(&mut ppu, 10);
+        assert_eq!(pixel(&ppu, 19, 10), 0);
+        assert_eq!(pixel(&ppu, 20, 10), 2);
+        assert_eq!(pixel(&ppu, 159, 10), 2);
+        assert_eq!(ppu.window_line, 1);
+    }
+
+    #[test]
+    fn renders_sprites() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8 + 10, 1, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 9, 0), 0);
+        assert_eq!(pixel(&ppu, 10, 0), 3);
+        assert_eq!(pixel(&ppu, 17, 0), 3);
+        assert_eq!(pixel(&ppu, 18, 0), 0);
+    }
+
+    #[test]
+    fn sprites_use_selected_palette() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        ppu.set_byte(OBP1_ADDR, 0b0100_0000);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0b0001_0000);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+    }
+
+    #[test]
+    fn sprites_disabled_are_not_rendered() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.lcdc = LcdControl::new(0b1000_0001);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0);
+    }
+
+    #[test]
+    fn sprite_with_smaller_x_has_priority() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 1);
+        solid_tile(&mut ppu, 2, 2);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8 + 4, 1, 0);
+        set_sprite(&mut ppu, 1, 16, 8, 2, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 4, 0), 2, "Lower X wins despite later OAM index");
+        assert_eq!(pixel(&ppu, 8, 0), 1);
+    }
+
+    #[test]
+    fn sprite_with_lower_oam_index_wins_tie() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 1);
+        solid_tile(&mut ppu, 2, 2);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0);
+        set_sprite(&mut ppu, 1, 16, 8, 2, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+    }
+
+    #[test]
+    fn transparent_sprite_pixels_show_lower_priority_sprite() {
+        let mut ppu = ppu();
+        // Tile 1: left half transparent, right half color 1.
+        for row in 0..8 {
+            ppu.set_byte(0x8010 + row * 2, 0x0F);
+        }
+        solid_tile(&mut ppu, 2, 2);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0);
+        set_sprite(&mut ppu, 1, 16, 8, 2, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 2);
+        assert_eq!(pixel(&ppu, 4, 0), 1);
+    }
+
+    #[test]
+    fn only_ten_sprites_per_line_are_drawn() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        for i in 0..11 {
+            set_sprite(&mut ppu, i, 16, 8 + i as u8 * 8, 1, 0);
+        }
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 72, 0), 3);
+        assert_eq!(pixel(&ppu, 80, 0), 0, "Eleventh sprite is dropped");
+    }
+
+    #[test]
+    fn background_priority_hides_sprite_behind_non_zero_colors() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        // Background tile 2 is color 1 in the left half only.
+        for row in 0..8 {
+            ppu.set_byte(0x8020 + row * 2, 0xF0);
+        }
+        ppu.set_byte(0x9800, 2);
+        ppu.lcdc = LcdControl::new(0b1001_0011);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0b1000_0000);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 1, "Background drawn over sprite");
+        assert_eq!(pixel(&ppu, 4, 0), 3, "Sprite drawn over color 0");
+    }
+
+    #[test]
+    fn renders_8x16_sprites() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 2, 1);
+        solid_tile(&mut ppu, 3, 2);
+        ppu.lcdc = LcdControl::new(0b1000_0111);
+        // Odd tile index is ignored in 8x16 mode.
+        set_sprite(&mut ppu, 0, 16, 8, 3, 0);
+
+        render_line(&mut ppu, 0);
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+
+        render_line(&mut ppu, 8);
+        assert_eq!(pixel(&ppu, 0, 8), 2);
+    }
+
+    #[test]
+    fn flips_sprites_horizontally() {
+        let mut ppu = ppu();
+        // Only leftmost pixel set.
+        for row in 0..8 {
+            ppu.set_byte(0x8010 + row * 2, 0x80);
+        }
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0b0010_0000);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0);
+        assert_eq!(pixel(&ppu, 7, 0), 1);
+    }
+
+    #[test]
+    fn partially_offscreen_sprites() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 4, 1, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(pixel(&ppu, 3, 0), 3);
+        assert_eq!(pixel(&ppu, 4, 0), 0);
+    }
+
+    #[test]
+    fn dmg_shades_are_shown_as_greys() {
+        let mut ppu = ppu();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.set_byte(0x9801, 1);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(color_pixel(&ppu, 0, 0), 0x7FFF);
+        assert_eq!(color_pixel(&ppu, 8, 0), 0x0000);
+    }
+
+    #[test]
+    fn cgb_background_uses_palette_from_attributes() {
+        let mut ppu = cgb_ppu();
+        solid_tile(&mut ppu, 1, 2);
+        set_cgb_color(&mut ppu, BCPS_ADDR, 0, 2, 0x1234);
+        set_cgb_color(&mut ppu, BCPS_ADDR, 5, 2, 0x001F);
+        ppu.set_byte(0x9800, 1);
+        ppu.set_byte(0x9801, 1);
+        ppu.set_byte(VBK_ADDR, 1);
+        ppu.set_byte(0x9801, 5);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 2, "Color index kept as the shade");
+        assert_eq!(color_pixel(&ppu, 0, 0), 0x1234);
+        assert_eq!(color_pixel(&ppu, 8, 0), 0x001F);
+    }
+
+    #[test]
+    fn cgb_background_tiles_from_bank_1_and_flipped() {
+        let mut ppu = cgb_ppu();
+        // Tile 0 in bank 1: only the top left pixel set.
+        ppu.set_byte(VBK_ADDR, 1);
+        ppu.set_byte(0x8000, 0x80);
+        // Tile map attributes: bank 1, then bank 1 with both flips.
+        ppu.set_byte(0x9800, 0b0000_1000);
+        ppu.set_byte(0x9801, 0b0110_1000);
+        ppu.lcdc = LcdControl::new(0b1001_0001);
+
+        render_line(&mut ppu, 0);
+        assert_eq!(pixel(&ppu, 0, 0), 1);
+        assert_eq!(pixel(&ppu, 15, 0), 0);
+
+        render_line(&mut ppu, 7);
+        assert_eq!(pixel(&ppu, 0, 7), 0);
+        assert_eq!(pixel(&ppu, 15, 7), 1);
+    }
+
+    #[test]
+    fn cgb_sprites_use_palette_and_bank_from_flags() {
+        let mut ppu = cgb_ppu();
+        ppu.set_byte(VBK_ADDR, 1);
+        solid_tile(&mut ppu, 1, 3);
+        set_cgb_color(&mut ppu, OCPS_ADDR, 6, 3, 0x03E0);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0b0000_1110);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(color_pixel(&ppu, 0, 0), 0x03E0);
+    }
+
+    #[test]
+    fn cgb_sprite_with_lower_oam_index_has_priority() {
+        let mut ppu = cgb_ppu();
+        solid_tile(&mut ppu, 1, 1);
+        solid_tile(&mut ppu, 2, 2);
+        ppu.lcdc = LcdControl::new(0b1000_0011);
+        set_sprite(&mut ppu, 0, 16, 8 + 4, 1, 0);
+        set_sprite(&mut ppu, 1, 16, 8, 2, 0);
+
+        render_line(&mut ppu, 0);
+
+        assert_eq!(pixel(&ppu, 3, 0), 2);
+        assert_eq!(
+            pixel(&ppu, 4, 0),
+            1,
+            "Lower OAM index wins despite higher X"
+        );
+    }
+
+    #[test]
+    fn cgb_background_priority_attribute_hides_sprites() {
+        let mut ppu = cgb_ppu();
+        solid_tile(&mut ppu, 1, 3);
+        solid_tile(&mut ppu, 2, 1);
+        ppu.set_byte(0x9800, 2);
+        ppu.set_byte(0x9801, 2);
+        ppu.set_byte(VBK_ADDR, 1);
+        ppu.set_byte(0x9800, 0b1000_0000);
+        set_sprite(&mut ppu, 0, 16, 8, 1, 0);
+
+        ppu.lcdc = LcdControl::new(0b1001_0011);
+        render_line(&mut ppu, 0);
+        assert_eq!(pixel(&ppu, 0, 0), 1, "Background drawn over sprite");
+
+        // Clearing LCDC bit 0 gives sprites priority, but keeps the background.
+        ppu.lcdc = LcdControl::new(0b1001_0010);
+        render_line(&mut ppu, 0);
+        assert_eq!(pixel(&ppu, 0, 0), 3);
+        assert_eq!(pixel(&ppu, 8, 0), 1);
+    }
+}
@@ -0,0 +1,115 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// Byte 3 of an OAM entry.
+    #[derive(Clone, Copy)]
+    pub struct SpriteFlags(u8);
+    impl Debug;
+
+    // BG and Window over OBJ: 0 = No, 1 = BG and Window colors 1–3 are drawn over the OBJ
+    pub bg_priority, _: 7;
+    pub y_flip, _: 6;
+    pub x_flip, _: 5;
+    // DMG palette: 0 = OBP0, 1 = OBP1
+    pub dmg_palette, _: 4;
+    // Tile data bank on CGB: 0 = VRAM bank 0; 1 = VRAM bank 1
+    pub cgb_bank, _: 3;
+    // CGB palette number (0-7)
+    pub cgb_palette, _: 2, 0;
+}
+
+/// A single entry in the sprite attribute table (OAM).
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    // Vertical position on screen plus 16.
+    pub y: u8,
+    // Horizontal position on screen plus 8.
+    pub x: u8,
+    pub tile: u8,
+    pub flags: SpriteFlags,
+    // Position in OAM, used to break ties in priority.
+    pub index: u8,
+}
+
+impl Sprite {
+    pub fn from_oam(oam: &[u8], index: u8) -> Self {
+        let offset = index as usize * 4;
+        Sprite {
+            y: oam[offset],
+            x: oam[offset + 1],
+            tile: oam[offset + 2],
+            flags: SpriteFlags(oam[offset + 3]),
+            index,
+        }
+    }
+
+    pub fn is_on_line(&self, ly: u8, height: u8) -> bool {
+        let line = ly as u16 + 16;
+        let top = self.y as u16;
+        line >= top && line < top + height as u16
+    }
+
+    /// Address of the tile data row for the given line, taking vertical
+    /// flipping and 8x16 objects into account.
+    pub fn row_addr(&self, ly: u8, height: u8) -> u16 {
+        let mut row = (ly as u16 + 16 - self.y as u16) as u8;
+        if self.flags.y_flip() {
+            row = height - 1 - row;
+        }
+        let tile = if height == 16 {
+            self.tile & 0xFE
+        } else {
+            self.tile
+        };
+        0x8000 + tile as u16 * 16 + row as u16 * 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sprite;
+
+    #[test]
+    fn reads_attributes_from_oam() {
+        let oam = [0x00, 0x00, 0x00, 0x00, 0x10, 0x08, 0x42, 0b1010_0000];
+        let sprite = Sprite::from_oam(&oam, 1);
+        assert_eq!(sprite.y, 0x10);
+        assert_eq!(sprite.x, 0x08);
+        assert_eq!(sprite.tile, 0x42);
+        assert!(sprite.flags.bg_priority());
+        assert!(!sprite.flags.y_flip());
+        assert!(sprite.flags.x_flip());
+        assert!(!sprite.flags.dmg_palette());
+        assert_eq!(sprite.index, 1);
+    }
+
+    #[test]
+    fn is_on_line_for_8x8() {
+        let sprite = Sprite::from_oam(&[0x10, 0x08, 0x00, 0x00], 0);
+        assert!(sprite.is_on_line(0, 8));
+        assert!(sprite.is_on_line(7, 8));
+        assert!(!sprite.is_on_line(8, 8));
+    }
+
+    #[test]
+    fn is_on_line_for_8x16() {
+        let sprite = Sprite::from_oam(&[0x08, 0x08, 0x00, 0x00], 0);
+        assert!(sprite.is_on_line(0, 16));
+        assert!(sprite.is_on_line(7, 16));
+        assert!(!sprite.is_on_line(8, 16));
+    }
+
+    #[test]
+    fn row_addr_uses_even_tile_for_8x16() {
+        let sprite = Sprite::from_oam(&[0x10, 0x08, 0x03, 0x00], 0);
+        assert_eq!(sprite.row_addr(0, 16), 0x8020);
+        assert_eq!(sprite.row_addr(8, 16), 0x8030);
+    }
+
+    #[test]
+    fn row_addr_with_y_flip() {
+        let sprite = Sprite::from_oam(&[0x10, 0x08, 0x01, 0b0100_0000], 0);
+        assert_eq!(sprite.row_addr(0, 8), 0x801E);
+        assert_eq!(sprite.row_addr(0, 16), 0x801E);
+    }
+}
@@ -0,0 +1,33 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// CGB BG map attributes, stored in VRAM bank 1 at the same address as
+    /// the tile index in bank 0.
+    #[derive(Clone, Copy, Default)]
+    pub struct TileAttributes(u8);
+    impl Debug;
+
+    // BG-to-OBJ priority: 1 = BG colors 1–3 are drawn over sprites
+    pub priority, _: 7;
+    pub y_flip, _: 6;
+    pub x_flip, _: 5;
+    // Tile data bank: 0 = VRAM bank 0; 1 = VRAM bank 1
+    pub bank, _: 3;
+    // BG palette number (0-7)
+    pub palette, _: 2, 0;
+}
+
+#[cfg(test)]
+mod test {
+    use super::TileAttributes;
+
+    #[test]
+    fn reads_fields() {
+        let attributes = TileAttributes(0b1010_1101);
+        assert!(attributes.priority());
+        assert!(!attributes.y_flip());
+        assert!(attributes.x_flip());
+        assert!(attributes.bank());
+        assert_eq!(attributes.palette(), 5);
+    }
+}
@@ -0,0 +1,364 @@
+mod capture;
+mod local;
+mod socket;
+
+pub use capture::SerialCapture;
+pub use local::LocalLink;
+pub use socket::SocketLink;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::memory::address_space::AddressSpace;
+
+// FF01: Serial transfer data (SB)
+pub const SB_ADDR: u16 = 0xFF01;
+
+// FF02: Serial transfer control (SC)
+pub const SC_ADDR: u16 = 0xFF02;
+
+const TRANSFER_START: u8 = 0b1000_0000;
+const INTERNAL_CLOCK: u8 = 0b0000_0001;
+
+// Only the start and clock select bits are implemented on DMG.
+const SC_MASK: u8 = TRANSFER_START | INTERNAL_CLOCK;
+
+// The internal clock runs at 8192 Hz.
+const CYCLES_PER_BIT: u16 = 512;
+
+// How long to wait for the other side to reply once all eight bits have been
+// clocked out, before treating the cable as disconnected.
+const REPLY_TIMEOUT: u32 = 2 * crate::clock::CYCLES_PER_FRAME;
+
+/// The other end of a link cable.
+///
+/// Bytes are exchanged whole: the side driving the clock sends its byte, and
+/// the other side replies with its own once it has a transfer enabled.
+pub trait SerialLink {
+    /// Sends a byte clocked out on this side's internal clock.
+    fn send(&mut self, byte: u8);
+
+    /// Returns the other side's reply to the last byte sent, if it arrived.
+    fn receive_reply(&mut self) -> Option<u8>;
+
+    /// Returns a byte clocked in on the other side's clock, if one arrived.
+    fn receive(&mut self) -> Option<u8>;
+
+    /// Replies to a byte returned by `receive`.
+    fn reply(&mut self, byte: u8);
+}
+
+/// Serial port
+///
+/// Shifts SB out one bit at a time while SC selects the internal clock, or
+/// waits for the other side of the link to drive the clock otherwise.
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    // Bits left to shift out on the internal clock.
+    bits: u8,
+    // T-cycles since the last bit was shifted, or spent waiting for a reply.
+    cycles: u32,
+    double_speed: bool,
+    link: Option<Box<dyn SerialLink>>,
+    // Replies still due for transfers which timed out. They are discarded
+    // when they arrive, rather than taken as the reply to a later transfer.
+    stale_replies: u32,
+    interrupts: Rc<RefCell<InterruptController>>,
+}
+
+impl Serial {
+    pub fn new(interrupts: Rc<RefCell<InterruptController>>) -> Self {
+        Self {
+            sb: 0x00,
+            sc: 0x00,
+            bits: 0,
+            cycles: 0,
+            double_speed: false,
+            link: None,
+            stale_replies: 0,
+            interrupts,
+        }
+    }
+
+    /// Connects a link cable, replacing any existing connection.
+    pub fn connect(&mut self, link: Box<dyn SerialLink>) {
+        self.link = Some(link);
+        self.stale_replies = 0;
+    }
+
+    pub fn disconnect(&mut self) {
+        self.link = None;
+        self.stale_replies = 0;
+    }
+
+    fn transferring(&self) -> bool {
+        self.sc & TRANSFER_START != 0
+    }
+
+    fn internal_clock(&self) -> bool {
+        self.sc & INTERNAL_CLOCK != 0
+    }
+
+    fn start_transfer(&mut self) {
+        self.bits = 8;
+        self.cycles = 0;
+        if let Some(link) = &mut self.link {
+            link.send(self.sb);
+        }
+    }
+
+    fn finish_transfer(&mut self, received: u8) {
+        self.sb = received;
+        self.sc &= !TRANSFER_START;
+        self.interrupts.borrow_mut().request(Interrupt::Serial);
+    }
+
+    fn step_internal(&mut self, cycles: u32) {
+        self.cycles += cycles;
+        while self.bits > 0 && self.cycles >= CYCLES_PER_BIT as u32 {
+            self.cycles -= CYCLES_PER_BIT as u32;
+            self.bits -= 1;
+            // Bits read as 1 until the other side's byte arrives.
+            self.sb = self.sb << 1 | 1;
+        }
+        if self.bits > 0 {
+            return;
+        }
+        let reply = match &mut self.link {
+            Some(link) => loop {
+                match link.receive_reply() {
+                    Some(_) if self.stale_replies > 0 => self.stale_replies -= 1,
+                    reply => break reply,
+                }
+            },
+            // Nothing drives the input line, so it reads high.
+            None => Some(0xFF),
+        };
+        match reply {
+            Some(byte) => self.finish_transfer(byte),
+            None if self.cycles >= REPLY_TIMEOUT => {
+                log::warn!("Serial link timed out waiting for a reply");
+                self.stale_replies += 1;
+                self.finish_transfer(0xFF);
+            }
+            None => {}
+        }
+    }
+
+    fn step_external(&mut self) {
+        let Some(link) = &mut self.link else {
+            // Without a link cable the transfer never completes.
+            return;
+        };
+        if let Some(byte) = link.receive() {
+            link.reply(self.sb);
+            self.finish_transfer(byte);
+        }
+    }
+}
+
+impl AddressSpace for Serial {
+    fn accepts(&self, addr: u16) -> bool {
+        addr == SB_ADDR || addr == SC_ADDR
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            SB_ADDR => self.sb = byte,
+            SC_ADDR => {
+                self.sc = byte & SC_MASK;
+                if self.transferring() && self.internal_clock() {
+                    self.start_transfer();
+                }
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            SB_ADDR => self.sb,
+            // Unused bits of SC always read back as 1.
+            SC_ADDR => self.sc | !SC_MASK,
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        if !self.transferring() {
+            return;
+        }
+        if self.internal_clock() {
+            // The internal clock is derived from the CPU clock.
+            let cycles = if self.double_speed {
+                cycles as u32 * 2
+            } else {
+                cycles as u32
+            };
+            self.step_internal(cycles);
+        } else {
+            self.step_external();
+        }
+    }
+
+    fn set_double_speed(&mut self, enabled: bool) {
+        self.double_speed = enabled;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::IF_ADDR;
+
+    fn serial() -> (Serial, Rc<RefCell<InterruptController>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        (Serial::new(interrupts.clone()), interrupts)
+    }
+
+    fn serial_interrupt_requested(interrupts: &Rc<RefCell<InterruptController>>) -> bool {
+        interrupts.borrow_mut().get_byte(IF_ADDR) & Interrupt::Serial.bit() != 0
+    }
+
+    fn tick(serial: &mut Serial, cycles: u32) {
+        for _ in 0..cycles / 4 {
+            serial.tick(4);
+        }
+    }
+
+    #[test]
+    fn unused_control_bits_read_as_set() {
+        let (mut serial, _) = serial();
+        serial.set_byte(SC_ADDR, 0x00);
+        assert_eq!(serial.get_byte(SC_ADDR), 0x7E);
+    }
+
+    #[test]
+    fn disconnected_transfer_shifts_in_ones() {
+        let (mut serial, interrupts) = serial();
+        serial.set_byte(SB_ADDR, 0x00);
+        serial.set_byte(SC_ADDR, 0x81);
+
+        tick(&mut serial, 8 * 512 - 4);
+        assert_eq!(serial.get_byte(SB_ADDR), 0x7F, "Seven bits shifted");
+        assert!(!serial_interrupt_requested(&interrupts));
+
+        tick(&mut serial, 4);
+        assert_eq!(serial.get_byte(SB_ADDR), 0xFF);
+        assert_eq!(serial.get_byte(SC_ADDR), 0x7F, "Start flag cleared");
+        assert!(serial_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn external_clock_waits_without_link() {
+        let (mut serial, interrupts) = serial();
+        serial.set_byte(SC_ADDR, 0x80);
+        tick(&mut serial, 100_000);
+        assert_eq!(serial.get_byte(SC_ADDR), 0xFE);
+        assert!(!serial_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn transfer_sends_byte_to_link() {
+        let (mut serial, _) = serial();
+        let capture = SerialCapture::new();
+        serial.connect(Box::new(capture.clone()));
+        for byte in b"ok" {
+            serial.set_byte(SB_ADDR, *byte);
+            serial.set_byte(SC_ADDR, 0x81);
+            tick(&mut serial, 8 * 512);
+        }
+        assert_eq!(capture.text(), "ok");
+    }
+
+    #[test]
+    fn linked_serial_ports_exchange_bytes() {
+        let (mut master, master_interrupts) = serial();
+        let (mut slave, slave_interrupts) = serial();
+        let (master_link, slave_link) = LocalLink::pair();
+        master.connect(Box::new(master_link));
+        slave.connect(Box::new(slave_link));
+
+        slave.set_byte(SB_ADDR, 0x42);
+        slave.set_byte(SC_ADDR, 0x80);
+        master.set_byte(SB_ADDR, 0x99);
+        master.set_byte(SC_ADDR, 0x81);
+
+        tick(&mut slave, 4);
+        assert_eq!(slave.get_byte(SB_ADDR), 0x99);
+        assert!(serial_interrupt_requested(&slave_interrupts));
+
+        tick(&mut master, 8 * 512);
+        assert_eq!(master.get_byte(SB_ADDR), 0x42);
+        assert!(serial_interrupt_requested(&master_interrupts));
+    }
+
+    #[test]
+    fn master_waits_for_slave_to_enable_transfer() {
+        let (mut master, master_interrupts) = serial();
+        let (mut slave, _) = serial();
+        let (master_link, slave_link) = LocalLink::pair();
+        master.connect(Box::new(master_link));
+        slave.connect(Box::new(slave_link));
+
+        master.set_byte(SB_ADDR, 0x01);
+        master.set_byte(SC_ADDR, 0x81);
+        tick(&mut master, 8 * 512);
+        assert!(!serial_interrupt_requested(&master_interrupts));
+
+        slave.set_byte(SB_ADDR, 0x02);
+        slave.set_byte(SC_ADDR, 0x80);
+        tick(&mut slave, 4);
+        tick(&mut master, 4);
+        assert_eq!(master.get_byte(SB_ADDR), 0x02);
+        assert!(serial_interrupt_requested(&master_interrupts));
+    }
+
+    #[test]
+    fn master_times_out_without_reply() {
+        let (mut master, master_interrupts) = serial();
+        let (master_link, _slave_link) = LocalLink::pair();
+        master.connect(Box::new(master_link));
+
+        master.set_byte(SB_ADDR, 0x01);
+        master.set_byte(SC_ADDR, 0x81);
+        tick(&mut master, 8 * 512 + REPLY_TIMEOUT);
+        assert_eq!(master.get_byte(SB_ADDR), 0xFF);
+        assert!(serial_interrupt_requested(&master_interrupts));
+    }
+
+    #[test]
+    fn discards_reply_arriving_after_timeout() {
+        let (mut master, master_interrupts) = serial();
+        let (mut slave, _) = serial();
+        let (master_link, slave_link) = LocalLink::pair();
+        master.connect(Box::new(master_link));
+        slave.connect(Box::new(slave_link));
+
+        master.set_byte(SB_ADDR, 0x01);
+        master.set_byte(SC_ADDR, 0x81);
+        tick(&mut master, 8 * 512 + REPLY_TIMEOUT);
+        assert_eq!(master.get_byte(SB_ADDR), 0xFF);
+
+        // The slave replies to the first byte only after the master gave up.
+        slave.set_byte(SB_ADDR, 0x02);
+        slave.set_byte(SC_ADDR, 0x80);
+        tick(&mut slave, 4);
+        master_interrupts.borrow_mut().set_byte(IF_ADDR, 0x00);
+
+        master.set_byte(SB_ADDR, 0x03);
+        master.set_byte(SC_ADDR, 0x81);
+        tick(&mut master, 8 * 512);
+        assert!(!serial_interrupt_requested(&master_interrupts));
+
+        slave.set_byte(SB_ADDR, 0x04);
+        slave.set_byte(SC_ADDR, 0x80);
+        tick(&mut slave, 4);
+        assert_eq!(slave.get_byte(SB_ADDR), 0x03);
+        tick(&mut master, 4);
+        assert_eq!(master.get_byte(SB_ADDR), 0x04);
+        assert!(serial_interrupt_requested(&master_interrupts));
+    }
+}
@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use super::SerialLink;
+
+/// Records every byte sent over the serial port, with nothing on the other
+/// end of the cable. Test ROMs such as blargg's print their results this way.
+///
+/// Clones share the same buffer, so one can be kept to read the output.
+#[derive(Clone, Default)]
+pub struct SerialCapture {
+    output: Rc<RefCell<Vec<u8>>>,
+    echo: bool,
+}
+
+impl SerialCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also writes each byte to stdout as it is sent.
+    pub fn stdout() -> Self {
+        Self {
+            echo: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.output.borrow().clone()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.output.borrow()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.output.borrow_mut().clear();
+    }
+}
+
+impl SerialLink for SerialCapture {
+    fn send(&mut self, byte: u8) {
+        self.output.borrow_mut().push(byte);
+        if self.echo {
+            let mut stdout = io::stdout();
+            if let Err(error) = stdout.write_all(&[byte]).and_then(|_| stdout.flush()) {
+                log::error!("Failed to write serial output: {}", error);
+            }
+        }
+    }
+
+    fn receive_reply(&mut self) -> Option<u8> {
+        // Nothing drives the input line, so it reads high.
+        Some(0xFF)
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        None
+    }
+
+    fn reply(&mut self, _byte: u8) {}
+}
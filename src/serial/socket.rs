@@ -0,0 +1,192 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use super::SerialLink;
+
+// Each message is a tag byte followed by the byte transferred.
+const SEND_TAG: u8 = 0x01;
+const REPLY_TAG: u8 = 0x02;
+
+/// One end of a link cable to another rustboy process, over a TCP or Unix
+/// domain socket.
+///
+/// Incoming messages are read on a background thread, so polling the link
+/// never blocks emulation.
+pub struct SocketLink {
+    writer: Box<dyn Write>,
+    bytes: Receiver<u8>,
+    replies: Receiver<u8>,
+}
+
+impl SocketLink {
+    pub fn connect_tcp(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_tcp(TcpStream::connect(addr)?)
+    }
+
+    /// Waits for the other side to connect.
+    pub fn listen_tcp(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let (stream, peer) = TcpListener::bind(addr)?.accept()?;
+        log::info!("Link cable connected to {peer}");
+        Self::from_tcp(stream)
+    }
+
+    pub fn from_tcp(stream: TcpStream) -> io::Result<Self> {
+        // Messages are tiny and latency sensitive.
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix(path: &Path) -> io::Result<Self> {
+        Self::from_unix(UnixStream::connect(path)?)
+    }
+
+    /// Waits for the other side to connect.
+    #[cfg(unix)]
+    pub fn listen_unix(path: &Path) -> io::Result<Self> {
+        // A socket left behind by an earlier run would make binding fail.
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let accepted = listener.accept();
+        // Only one connection is accepted, so the socket file is done with.
+        std::fs::remove_file(path)?;
+        let (stream, _) = accepted?;
+        log::info!("Link cable connected on {}", path.display());
+        Self::from_unix(stream)
+    }
+
+    #[cfg(unix)]
+    pub fn from_unix(stream: UnixStream) -> io::Result<Self> {
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+
+    fn new(reader: impl Read + Send + 'static, writer: impl Write + 'static) -> Self {
+        let (bytes_sender, bytes) = mpsc::channel();
+        let (replies_sender, replies) = mpsc::channel();
+        thread::spawn(move || read_messages(reader, bytes_sender, replies_sender));
+        Self {
+            writer: Box::new(writer),
+            bytes,
+            replies,
+        }
+    }
+
+    fn write_message(&mut self, tag: u8, byte: u8) {
+        if let Err(error) = self.writer.write_all(&[tag, byte]) {
+            log::error!("Failed to write to link cable: {}", error);
+        }
+    }
+}
+
+fn read_messages(mut reader: impl Read, bytes: Sender<u8>, replies: Sender<u8>) {
+    let mut message = [0; 2];
+    loop {
+        if let Err(error) = reader.read_exact(&mut message) {
+            if error.kind() != io::ErrorKind::UnexpectedEof {
+                log::error!("Failed to read from link cable: {}", error);
+            }
+            log::info!("Link cable disconnected");
+            return;
+        }
+        let [tag, byte] = message;
+        let sender = match tag {
+            SEND_TAG => &bytes,
+            REPLY_TAG => &replies,
+            _ => {
+                log::warn!("Ignoring unknown link cable message {tag:#04X}");
+                continue;
+            }
+        };
+        if sender.send(byte).is_err() {
+            // The link has been dropped.
+            return;
+        }
+    }
+}
+
+impl SerialLink for SocketLink {
+    fn send(&mut self, byte: u8) {
+        self.write_message(SEND_TAG, byte);
+    }
+
+    fn receive_reply(&mut self) -> Option<u8> {
+        self.replies.try_recv().ok()
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.bytes.try_recv().ok()
+    }
+
+    fn reply(&mut self, byte: u8) {
+        self.write_message(REPLY_TAG, byte);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn wait_for(mut poll: impl FnMut() -> Option<u8>) -> Option<u8> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(byte) = poll() {
+                return Some(byte);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        None
+    }
+
+    #[test]
+    fn exchanges_bytes_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || TcpStream::connect(addr).unwrap());
+        let (server, _) = listener.accept().unwrap();
+
+        let mut master = SocketLink::from_tcp(client.join().unwrap()).unwrap();
+        let mut slave = SocketLink::from_tcp(server).unwrap();
+
+        master.send(0x12);
+        assert_eq!(wait_for(|| slave.receive()), Some(0x12));
+        slave.reply(0x34);
+        assert_eq!(wait_for(|| master.receive_reply()), Some(0x34));
+        assert_eq!(master.receive(), None);
+        assert_eq!(slave.receive_reply(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listens_on_unix_socket_left_by_earlier_run() {
+        let path = std::env::temp_dir().join(format!("rustboy-{}-link.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists(), "Stale socket");
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || loop {
+            match UnixStream::connect(&client_path) {
+                Ok(stream) => return stream,
+                Err(_) => thread::sleep(Duration::from_millis(1)),
+            }
+        });
+        let mut slave = SocketLink::listen_unix(&path).unwrap();
+        let mut master = SocketLink::from_unix(client.join().unwrap()).unwrap();
+        assert!(!path.exists(), "Removed once connected");
+
+        master.send(0x56);
+        assert_eq!(wait_for(|| slave.receive()), Some(0x56));
+    }
+}
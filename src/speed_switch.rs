@@ -0,0 +1,82 @@
+use crate::memory::address_space::AddressSpace;
+
+// FF4D: Prepare speed switch (KEY1), CGB only
+pub const KEY1_ADDR: u16 = 0xFF4D;
+
+pub const SWITCH_ARMED: u8 = 0b0000_0001;
+const DOUBLE_SPEED: u8 = 0b1000_0000;
+
+/// CGB speed switch register (KEY1)
+///
+/// Arming the switch makes the next STOP instruction toggle the CPU between
+/// normal and double speed, instead of entering STOP mode.
+pub struct SpeedSwitch {
+    armed: bool,
+    double_speed: bool,
+}
+
+impl SpeedSwitch {
+    pub fn new() -> Self {
+        Self {
+            armed: false,
+            double_speed: false,
+        }
+    }
+}
+
+impl Default for SpeedSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AddressSpace for SpeedSwitch {
+    fn accepts(&self, addr: u16) -> bool {
+        addr == KEY1_ADDR
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        self.armed = byte & SWITCH_ARMED != 0;
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        assert!(self.accepts(addr), "OutOfBoundsError");
+        let mut value = 0x7E;
+        if self.double_speed {
+            value |= DOUBLE_SPEED;
+        }
+        if self.armed {
+            value |= SWITCH_ARMED;
+        }
+        value
+    }
+
+    fn set_double_speed(&mut self, enabled: bool) {
+        self.double_speed = enabled;
+        self.armed = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_unused_bits_as_set() {
+        let mut key1 = SpeedSwitch::new();
+        assert_eq!(key1.get_byte(KEY1_ADDR), 0x7E);
+        key1.set_byte(KEY1_ADDR, 0xFF);
+        assert_eq!(key1.get_byte(KEY1_ADDR), 0x7F, "Speed bit is read only");
+    }
+
+    #[test]
+    fn switching_speed_disarms() {
+        let mut key1 = SpeedSwitch::new();
+        key1.set_byte(KEY1_ADDR, SWITCH_ARMED);
+        key1.set_double_speed(true);
+        assert_eq!(key1.get_byte(KEY1_ADDR), 0xFE);
+        key1.set_double_speed(false);
+        assert_eq!(key1.get_byte(KEY1_ADDR), 0x7E);
+    }
+}
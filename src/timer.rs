@@ -0,0 +1,290 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::memory::address_space::AddressSpace;
+
+// FF04: Divider register (DIV)
+pub const DIV_ADDR: u16 = 0xFF04;
+
+// FF05: Timer counter (TIMA)
+pub const TIMA_ADDR: u16 = 0xFF05;
+
+// FF06: Timer modulo (TMA)
+pub const TMA_ADDR: u16 = 0xFF06;
+
+// FF07: Timer control (TAC)
+pub const TAC_ADDR: u16 = 0xFF07;
+
+// TIMA reads as 0x00 for one M-cycle after overflowing before it is reloaded.
+const RELOAD_DELAY: u8 = 4;
+
+pub struct Timer {
+    // DIV is the upper 8 bits of this internal counter, incremented every T-cycle.
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // Last value of the selected counter bit ANDed with the enable bit.
+    signal: bool,
+    // T-cycles until TIMA is reloaded from TMA, if an overflow occurred.
+    reload_cycles: u8,
+    double_speed: bool,
+    interrupts: Rc<RefCell<InterruptController>>,
+}
+
+impl Timer {
+    pub fn new(interrupts: Rc<RefCell<InterruptController>>) -> Self {
+        Self {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            signal: false,
+            reload_cycles: 0,
+            double_speed: false,
+            interrupts,
+        }
+    }
+
+    /// Sets the internal counter, e.g. to the value left by the boot ROM.
+    pub fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+        self.signal = false;
+    }
+
+    fn enabled(&self) -> bool {
+        self.tac & 0b100 != 0
+    }
+
+    /// Bit of the internal counter whose falling edge clocks TIMA.
+    fn selected_bit(&self) -> u16 {
+        match self.tac & 0b11 {
+            0b00 => 1 << 9, // 4096 Hz
+            0b01 => 1 << 3, // 262144 Hz
+            0b10 => 1 << 5, // 65536 Hz
+            _ => 1 << 7,    // 16384 Hz
+        }
+    }
+
+    /// TIMA is incremented on the falling edge of the selected counter bit.
+    /// As the enable bit is part of the same signal, writes to DIV and TAC
+    /// can also cause TIMA to increment.
+    fn detect_falling_edge(&mut self) {
+        let signal = self.enabled() && self.counter & self.selected_bit() != 0;
+        if self.signal && !signal {
+            self.increment_tima();
+        }
+        self.signal = signal;
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflow {
+            self.reload_cycles = RELOAD_DELAY;
+        }
+    }
+
+    fn step(&mut self) {
+        if self.reload_cycles > 0 {
+            self.reload_cycles -= 1;
+            if self.reload_cycles == 0 {
+                self.tima = self.tma;
+                self.interrupts.borrow_mut().request(Interrupt::Timer);
+            }
+        }
+        self.counter = self.counter.wrapping_add(1);
+        self.detect_falling_edge();
+    }
+}
+
+impl AddressSpace for Timer {
+    fn accepts(&self, addr: u16) -> bool {
+        (DIV_ADDR..=TAC_ADDR).contains(&addr)
+    }
+
+    fn set_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            DIV_ADDR => {
+                // Any write resets the whole internal counter.
+                self.counter = 0;
+                self.detect_falling_edge();
+            }
+            TIMA_ADDR => {
+                // Writing during the overflow delay cancels the reload.
+                self.reload_cycles = 0;
+                self.tima = byte;
+            }
+            TMA_ADDR => self.tma = byte,
+            TAC_ADDR => {
+                self.tac = byte & 0b111;
+                self.detect_falling_edge();
+            }
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn get_byte(&mut self, addr: u16) -> u8 {
+        match addr {
+            DIV_ADDR => (self.counter >> 8) as u8,
+            TIMA_ADDR => self.tima,
+            TMA_ADDR => self.tma,
+            // Unused upper bits of TAC always read back as 1.
+            TAC_ADDR => self.tac | 0b1111_1000,
+            _ => panic!("OutOfBoundsError"),
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        let cycles = if self.double_speed {
+            cycles as u16 * 2
+        } else {
+            cycles as u16
+        };
+        for _ in 0..cycles {
+            self.step();
+        }
+    }
+
+    fn set_double_speed(&mut self, enabled: bool) {
+        self.double_speed = enabled;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interrupts::IF_ADDR;
+
+    fn timer() -> (Timer, Rc<RefCell<InterruptController>>) {
+        let interrupts = Rc::new(RefCell::new(InterruptController::new()));
+        (Timer::new(interrupts.clone()), interrupts)
+    }
+
+    fn timer_interrupt_requested(interrupts: &Rc<RefCell<InterruptController>>) -> bool {
+        interrupts.borrow_mut().get_byte(IF_ADDR) & Interrupt::Timer.bit() != 0
+    }
+
+    #[test]
+    fn accepts_timer_registers() {
+        let (timer, _) = timer();
+        assert!(!timer.accepts(0xFF03));
+        assert!(timer.accepts(DIV_ADDR));
+        assert!(timer.accepts(TIMA_ADDR));
+        assert!(timer.accepts(TMA_ADDR));
+        assert!(timer.accepts(TAC_ADDR));
+        assert!(!timer.accepts(0xFF08));
+    }
+
+    #[test]
+    fn div_increments_every_256_cycles() {
+        let (mut timer, _) = timer();
+        timer.tick(255);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x00);
+        timer.tick(1);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x01);
+        timer.tick(255);
+        timer.tick(1);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x02);
+    }
+
+    #[test]
+    fn runs_twice_as_fast_in_double_speed() {
+        let (mut timer, _) = timer();
+        timer.set_double_speed(true);
+        timer.tick(128);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x01);
+    }
+
+    #[test]
+    fn writing_div_resets_it() {
+        let (mut timer, _) = timer();
+        timer.tick(255);
+        timer.tick(255);
+        timer.set_byte(DIV_ADDR, 0xAB);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x00);
+        timer.tick(255);
+        assert_eq!(timer.get_byte(DIV_ADDR), 0x00);
+    }
+
+    #[test]
+    fn tima_does_not_increment_when_disabled() {
+        let (mut timer, _) = timer();
+        timer.set_byte(TAC_ADDR, 0b001);
+        timer.tick(255);
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x00);
+    }
+
+    #[test]
+    fn tima_increments_at_selected_rate() {
+        let cases = [(0b100, 1024), (0b101, 16), (0b110, 64), (0b111, 256)];
+        for (tac, period) in cases {
+            let (mut timer, _) = timer();
+            timer.set_byte(TAC_ADDR, tac);
+            for _ in 0..(period - 1) {
+                timer.tick(1);
+            }
+            assert_eq!(timer.get_byte(TIMA_ADDR), 0x00, "TAC {tac:#05b}");
+            timer.tick(1);
+            assert_eq!(timer.get_byte(TIMA_ADDR), 0x01, "TAC {tac:#05b}");
+        }
+    }
+
+    #[test]
+    fn tima_reloads_from_tma_one_cycle_after_overflow() {
+        let (mut timer, interrupts) = timer();
+        timer.set_byte(TIMA_ADDR, 0xFF);
+        timer.set_byte(TMA_ADDR, 0x42);
+        timer.set_byte(TAC_ADDR, 0b101);
+
+        timer.tick(16);
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x00, "Reads 0 during delay");
+        assert!(!timer_interrupt_requested(&interrupts));
+
+        timer.tick(4);
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x42);
+        assert!(timer_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn writing_tima_during_overflow_delay_cancels_reload() {
+        let (mut timer, interrupts) = timer();
+        timer.set_byte(TIMA_ADDR, 0xFF);
+        timer.set_byte(TMA_ADDR, 0x42);
+        timer.set_byte(TAC_ADDR, 0b101);
+
+        timer.tick(16);
+        timer.set_byte(TIMA_ADDR, 0x10);
+        timer.tick(4);
+
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x10);
+        assert!(!timer_interrupt_requested(&interrupts));
+    }
+
+    #[test]
+    fn resetting_div_on_falling_edge_increments_tima() {
+        let (mut timer, _) = timer();
+        timer.set_byte(TAC_ADDR, 0b101);
+        // Bit 3 of the counter is now set.
+        timer.tick(8);
+        timer.set_byte(DIV_ADDR, 0x00);
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x01);
+    }
+
+    #[test]
+    fn disabling_timer_on_falling_edge_increments_tima() {
+        let (mut timer, _) = timer();
+        timer.set_byte(TAC_ADDR, 0b101);
+        timer.tick(8);
+        timer.set_byte(TAC_ADDR, 0b001);
+        assert_eq!(timer.get_byte(TIMA_ADDR), 0x01);
+    }
+
+    #[test]
+    fn unused_tac_bits_read_as_set() {
+        let (mut timer, _) = timer();
+        timer.set_byte(TAC_ADDR, 0b101);
+        assert_eq!(timer.get_byte(TAC_ADDR), 0xFD);
+    }
+}